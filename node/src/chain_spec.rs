@@ -153,6 +153,8 @@ fn testnet_genesis(
             "ipInitialDeposit": 1_000_000_000_000_000u128,
             "consumerServiceDeposit": 1_000_000u128,
             "priceStorageMbPerBlock": 1_000u128,
+            "priceVcorePerBlock": 1_000_000u128,
+            "priceRamMbPerBlock": 100u128,
         }
     })
 }
@@ -24,7 +24,9 @@
 use std::sync::Arc;
 
 use jsonrpsee::RpcModule;
+use sc_client_api::{Backend, BlockchainEvents, StorageProvider};
 use sc_consensus_babe::BabeWorkerHandle;
+use sc_rpc::SubscriptionTaskExecutor;
 pub use sc_rpc_api::DenyUnsafe;
 use sc_transaction_pool_api::TransactionPool;
 use sp_api::ProvideRuntimeApi;
@@ -34,7 +36,9 @@ use sp_consensus::SelectChain;
 use sp_consensus_babe::BabeApi;
 use sp_keystore::KeystorePtr;
 
-use deitos_runtime::{opaque::Block, AccountId, Balance, Nonce};
+use deitos_runtime::{opaque::Block, AccountId, AgreementId, Balance, BlockNumber, FileId, Nonce};
+
+pub mod deitos;
 
 /// Extra dependencies for BABE.
 pub struct BabeDeps {
@@ -56,23 +60,31 @@ pub struct FullDeps<C, P, SC> {
     pub deny_unsafe: DenyUnsafe,
     /// BABE specific dependencies.
     pub babe: BabeDeps,
+    /// Executor to drive the subscriptions
+    pub subscription_executor: SubscriptionTaskExecutor,
 }
 
 /// Instantiate all full RPC extensions.
-pub fn create_full<C, P, SC>(
+pub fn create_full<C, P, SC, B>(
     deps: FullDeps<C, P, SC>,
 ) -> Result<RpcModule<()>, Box<dyn std::error::Error + Send + Sync>>
 where
+    B: Backend<Block> + Send + Sync + 'static,
     C: ProvideRuntimeApi<Block>,
     C: HeaderBackend<Block> + HeaderMetadata<Block, Error = BlockChainError> + 'static,
+    C: StorageProvider<Block, B> + BlockchainEvents<Block>,
     C: Send + Sync + 'static,
     C::Api: substrate_frame_rpc_system::AccountNonceApi<Block, AccountId, Nonce>,
     C::Api: pallet_transaction_payment_rpc::TransactionPaymentRuntimeApi<Block, Balance>,
     C::Api: BabeApi<Block>,
     C::Api: BlockBuilder<Block>,
+    C::Api:
+        pallet_deitos_runtime_api::DeitosApi<Block, AccountId, Balance, BlockNumber, AgreementId>,
+    C::Api: pallet_deitos_fs_runtime_api::DeitosFsApi<Block, AgreementId, FileId>,
     P: TransactionPool + 'static,
     SC: SelectChain<Block> + 'static,
 {
+    use self::deitos::{Deitos, DeitosApiServer};
     use pallet_transaction_payment_rpc::{TransactionPayment, TransactionPaymentApiServer};
     use sc_consensus_babe_rpc::{Babe, BabeApiServer};
     use substrate_frame_rpc_system::{System, SystemApiServer};
@@ -84,6 +96,7 @@ where
         select_chain,
         deny_unsafe,
         babe,
+        subscription_executor,
     } = deps;
 
     let BabeDeps {
@@ -95,7 +108,7 @@ where
     module.merge(TransactionPayment::new(client.clone()).into_rpc())?;
     module.merge(
         Babe::new(
-            client,
+            client.clone(),
             babe_worker_handle,
             keystore,
             select_chain,
@@ -103,11 +116,7 @@ where
         )
         .into_rpc(),
     )?;
-
-    // Extend this RPC with a custom API by using the following syntax.
-    // `YourRpcStruct` should have a reference to a client, which is needed
-    // to call into the runtime.
-    // `module.merge(YourRpcTrait::into_rpc(YourRpcStruct::new(ReferenceToClient, ...)))?;`
+    module.merge(Deitos::<C, B>::new(client, subscription_executor).into_rpc())?;
 
     Ok(module)
 }
@@ -0,0 +1,516 @@
+// Copyright (C) NC2D Labs.
+// This file is part of Deitos Node.
+
+// Deitos Node is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Deitos Node is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Deitos Node.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The `deitos_*` RPC namespace. Query methods for IPs, agreements and files, and subscriptions
+//! to the agreement events of imported blocks.
+
+use std::{marker::PhantomData, sync::Arc};
+
+use futures::{future, stream, FutureExt, StreamExt};
+use jsonrpsee::{
+    core::RpcResult,
+    proc_macros::rpc,
+    types::{
+        error::{CallError, ErrorObject},
+        SubscriptionResult,
+    },
+    SubscriptionSink,
+};
+use parity_scale_codec::Decode;
+use sc_client_api::{Backend, BlockchainEvents, StorageProvider};
+use sc_rpc::SubscriptionTaskExecutor;
+use serde::{Deserialize, Serialize};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_core::{hashing::twox_128, storage::StorageKey};
+use sp_runtime::traits::Header as HeaderT;
+
+use deitos_runtime::{
+    opaque::{Block, Header},
+    AccountId, AgreementId, Balance, BlockNumber, FileId, Hash, RuntimeEvent,
+};
+use pallet_deitos::{
+    AgreementInfo, AgreementQuote, AgreementStatus, ComputeResources, IPInfo, ReliabilityFilter,
+    ReviewInfo, StorageSizeMB,
+};
+use pallet_deitos_fs::FileInfo;
+use pallet_deitos_fs_runtime_api::DeitosFsApi as DeitosFsRuntimeApi;
+use pallet_deitos_runtime_api::DeitosApi as DeitosRuntimeApi;
+
+/// Error code returned when a runtime API call fails.
+const RUNTIME_ERROR: i32 = 1;
+
+/// An agreement related event, pushed to the subscribers as blocks are imported.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgreementEvent {
+    /// The hash of the block the event was emitted in
+    pub block_hash: Hash,
+    /// The number of the block the event was emitted in
+    pub block_number: BlockNumber,
+    /// The agreement id
+    pub agreement_id: AgreementId,
+    /// The IP the agreement is with, if the agreement could be found
+    pub ip: Option<AccountId>,
+    /// The consumer the agreement is with, if the agreement could be found
+    pub consumer: Option<AccountId>,
+    /// What happened to the agreement
+    pub kind: AgreementEventKind,
+}
+
+/// The kinds of agreement events pushed to the subscribers.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum AgreementEventKind {
+    /// The agreement status has changed
+    StatusChanged {
+        /// The new status of the agreement
+        status: AgreementStatus,
+    },
+    /// The consumer has prepaid an installment
+    InstallmentPrepaid {
+        /// The cost of the installment
+        cost: Balance,
+    },
+    /// The IP has withdrawn installments
+    InstallmentsWithdrawn {
+        /// The total amount withdrawn
+        transferred: Balance,
+    },
+    /// The IP has terminated the agreement due to non-payment
+    IPTerminatedNonPay {
+        /// The total amount transferred to the IP
+        transferred: Balance,
+    },
+    /// A file of the agreement has been verified
+    FileVerified {
+        /// The file id
+        file_id: FileId,
+    },
+    /// A conflict has been found while verifying a file of the agreement
+    FileConflict {
+        /// The file id
+        file_id: FileId,
+    },
+}
+
+/// Deitos RPC methods.
+#[rpc(server)]
+pub trait DeitosApi {
+    /// Returns all the registered IPs with their status, capacity and average rating.
+    #[method(name = "deitos_ips")]
+    fn ips(&self, at: Option<Hash>) -> RpcResult<Vec<IPInfo<AccountId>>>;
+
+    /// Returns the registered IPs whose reliability record meets the filter.
+    #[method(name = "deitos_reliableIps")]
+    fn reliable_ips(
+        &self,
+        filter: ReliabilityFilter,
+        at: Option<Hash>,
+    ) -> RpcResult<Vec<IPInfo<AccountId>>>;
+
+    /// Returns up to `limit` reviews from the review log of the IP, starting at the given index.
+    #[method(name = "deitos_ipReviews")]
+    fn ip_reviews(
+        &self,
+        ip: AccountId,
+        start: u32,
+        limit: u32,
+        at: Option<Hash>,
+    ) -> RpcResult<Vec<ReviewInfo<AccountId, BlockNumber, AgreementId>>>;
+
+    /// Returns the storage of the IP that is not allocated to any agreement yet, or `None` if the
+    /// IP is not registered.
+    #[method(name = "deitos_ipFreeStorage")]
+    fn ip_free_storage(&self, ip: AccountId, at: Option<Hash>) -> RpcResult<Option<StorageSizeMB>>;
+
+    /// Returns a page of up to `limit` ids of the agreements of the IP, starting after the given
+    /// agreement id.
+    #[method(name = "deitos_ipAgreements")]
+    fn ip_agreements(
+        &self,
+        ip: AccountId,
+        start_after: Option<AgreementId>,
+        limit: u32,
+        at: Option<Hash>,
+    ) -> RpcResult<Vec<AgreementId>>;
+
+    /// Returns a page of up to `limit` ids of the agreements of the consumer, starting after the
+    /// given agreement id.
+    #[method(name = "deitos_consumerAgreements")]
+    fn consumer_agreements(
+        &self,
+        consumer: AccountId,
+        start_after: Option<AgreementId>,
+        limit: u32,
+        at: Option<Hash>,
+    ) -> RpcResult<Vec<AgreementId>>;
+
+    /// Returns the details of the agreement along with its next due installment and overdue
+    /// state.
+    #[method(name = "deitos_agreement")]
+    fn agreement(
+        &self,
+        agreement_id: AgreementId,
+        at: Option<Hash>,
+    ) -> RpcResult<Option<AgreementInfo<AccountId, Balance, BlockNumber>>>;
+
+    /// Returns the costs of a proposed agreement.
+    #[method(name = "deitos_quoteAgreement")]
+    fn quote_agreement(
+        &self,
+        storage: StorageSizeMB,
+        compute: ComputeResources,
+        activation_block: BlockNumber,
+        payment_plan: Vec<BlockNumber>,
+        at: Option<Hash>,
+    ) -> RpcResult<Option<AgreementQuote<Balance>>>;
+
+    /// Returns the ids of the files registered for the agreement.
+    #[method(name = "deitos_agreementFiles")]
+    fn agreement_files(
+        &self,
+        agreement_id: AgreementId,
+        at: Option<Hash>,
+    ) -> RpcResult<Vec<FileId>>;
+
+    /// Returns the details of the file.
+    #[method(name = "deitos_file")]
+    fn file(&self, file_id: FileId, at: Option<Hash>) -> RpcResult<Option<FileInfo<AgreementId>>>;
+
+    /// Subscribes to the events of the agreement.
+    #[subscription(
+        name = "deitos_subscribeAgreement" => "deitos_agreementEvent",
+        unsubscribe = "deitos_unsubscribeAgreement",
+        item = AgreementEvent
+    )]
+    fn subscribe_agreement(&self, agreement_id: AgreementId);
+
+    /// Subscribes to the events of all the agreements the account participates in, either as
+    /// the IP or as the consumer.
+    #[subscription(
+        name = "deitos_subscribeAccount" => "deitos_accountEvent",
+        unsubscribe = "deitos_unsubscribeAccount",
+        item = AgreementEvent
+    )]
+    fn subscribe_account(&self, account: AccountId);
+}
+
+/// Implementation of the Deitos RPC methods.
+pub struct Deitos<C, B> {
+    client: Arc<C>,
+    executor: SubscriptionTaskExecutor,
+    _marker: PhantomData<B>,
+}
+
+impl<C, B> Deitos<C, B> {
+    /// Creates a new instance of the Deitos RPC.
+    pub fn new(client: Arc<C>, executor: SubscriptionTaskExecutor) -> Self {
+        Self {
+            client,
+            executor,
+            _marker: Default::default(),
+        }
+    }
+}
+
+fn runtime_error(error: impl ToString, desc: &'static str) -> CallError {
+    CallError::Custom(ErrorObject::owned(
+        RUNTIME_ERROR,
+        desc,
+        Some(error.to_string()),
+    ))
+}
+
+impl<C, B> Deitos<C, B>
+where
+    B: Backend<Block> + Send + Sync + 'static,
+    C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + StorageProvider<Block, B>,
+    C: BlockchainEvents<Block> + Send + Sync + 'static,
+    C::Api: DeitosRuntimeApi<Block, AccountId, Balance, BlockNumber, AgreementId>,
+    C::Api: DeitosFsRuntimeApi<Block, AgreementId, FileId>,
+{
+    fn at(&self, at: Option<Hash>) -> Hash {
+        at.unwrap_or_else(|| self.client.info().best_hash)
+    }
+
+    /// Subscribes to the agreement events of the new best blocks which match the filter.
+    fn subscribe(
+        &self,
+        sink: SubscriptionSink,
+        filter: impl Fn(&AgreementEvent) -> bool + Send + 'static,
+    ) {
+        let client = self.client.clone();
+        let stream = self
+            .client
+            .import_notification_stream()
+            .filter(|notification| future::ready(notification.is_new_best))
+            .flat_map(move |notification| {
+                let events = agreement_events(&*client, notification.hash, notification.header)
+                    .unwrap_or_else(|e| {
+                        log::warn!("Failed to read the Deitos events of a block: {}", e);
+                        Vec::new()
+                    });
+                stream::iter(
+                    events
+                        .into_iter()
+                        .filter(|event| filter(event))
+                        .collect::<Vec<_>>(),
+                )
+            });
+
+        let fut = async move {
+            sink.pipe_from_stream(stream.boxed()).await;
+        };
+
+        self.executor
+            .spawn("deitos-rpc-subscription", Some("rpc"), fut.boxed());
+    }
+}
+
+/// Reads the agreement related events emitted in the block.
+fn agreement_events<C, B>(
+    client: &C,
+    hash: Hash,
+    header: Header,
+) -> Result<Vec<AgreementEvent>, String>
+where
+    B: Backend<Block>,
+    C: ProvideRuntimeApi<Block> + StorageProvider<Block, B>,
+    C::Api: DeitosRuntimeApi<Block, AccountId, Balance, BlockNumber, AgreementId>,
+    C::Api: DeitosFsRuntimeApi<Block, AgreementId, FileId>,
+{
+    let key = StorageKey([twox_128(b"System"), twox_128(b"Events")].concat());
+    let Some(data) = client.storage(hash, &key).map_err(|e| e.to_string())? else {
+        return Ok(Vec::new());
+    };
+    let records = Vec::<frame_system::EventRecord<RuntimeEvent, Hash>>::decode(&mut &data.0[..])
+        .map_err(|e| e.to_string())?;
+
+    let api = client.runtime_api();
+    let mut events = Vec::new();
+    for record in records {
+        let (agreement_id, kind) = match record.event {
+            RuntimeEvent::Deitos(pallet_deitos::Event::AgreementStatusChanged {
+                agreement_id,
+                status,
+            }) => (agreement_id, AgreementEventKind::StatusChanged { status }),
+            RuntimeEvent::Deitos(pallet_deitos::Event::ConsumerPrepaidInstallment {
+                agreement_id,
+                cost,
+                ..
+            }) => (
+                agreement_id,
+                AgreementEventKind::InstallmentPrepaid { cost },
+            ),
+            RuntimeEvent::Deitos(pallet_deitos::Event::IPWithdrewInstallments {
+                agreement_id,
+                transferred,
+                ..
+            }) => (
+                agreement_id,
+                AgreementEventKind::InstallmentsWithdrawn { transferred },
+            ),
+            RuntimeEvent::Deitos(pallet_deitos::Event::IPTerminatedNonPay {
+                agreement_id,
+                transferred,
+                ..
+            }) => (
+                agreement_id,
+                AgreementEventKind::IPTerminatedNonPay { transferred },
+            ),
+            RuntimeEvent::DeitosFS(pallet_deitos_fs::Event::FileVerified { file_id }) => {
+                let Some(file) = api.file(hash, file_id).map_err(|e| e.to_string())? else {
+                    continue;
+                };
+                (
+                    file.agreement_id,
+                    AgreementEventKind::FileVerified { file_id },
+                )
+            }
+            RuntimeEvent::DeitosFS(pallet_deitos_fs::Event::FileConflict { file_id }) => {
+                let Some(file) = api.file(hash, file_id).map_err(|e| e.to_string())? else {
+                    continue;
+                };
+                (
+                    file.agreement_id,
+                    AgreementEventKind::FileConflict { file_id },
+                )
+            }
+            _ => continue,
+        };
+
+        // The agreement might have been deleted in this block, so fall back to the parent block
+        let agreement = match api
+            .agreement(hash, agreement_id)
+            .map_err(|e| e.to_string())?
+        {
+            Some(agreement) => Some(agreement),
+            None => api
+                .agreement(*header.parent_hash(), agreement_id)
+                .map_err(|e| e.to_string())?,
+        };
+
+        events.push(AgreementEvent {
+            block_hash: hash,
+            block_number: *header.number(),
+            agreement_id,
+            ip: agreement.as_ref().map(|agreement| agreement.ip.clone()),
+            consumer: agreement.map(|agreement| agreement.consumer),
+            kind,
+        });
+    }
+
+    Ok(events)
+}
+
+impl<C, B> DeitosApiServer for Deitos<C, B>
+where
+    B: Backend<Block> + Send + Sync + 'static,
+    C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + StorageProvider<Block, B>,
+    C: BlockchainEvents<Block> + Send + Sync + 'static,
+    C::Api: DeitosRuntimeApi<Block, AccountId, Balance, BlockNumber, AgreementId>,
+    C::Api: DeitosFsRuntimeApi<Block, AgreementId, FileId>,
+{
+    fn ips(&self, at: Option<Hash>) -> RpcResult<Vec<IPInfo<AccountId>>> {
+        self.client
+            .runtime_api()
+            .ips(self.at(at))
+            .map_err(|e| runtime_error(e, "Unable to query the IPs.").into())
+    }
+
+    fn reliable_ips(
+        &self,
+        filter: ReliabilityFilter,
+        at: Option<Hash>,
+    ) -> RpcResult<Vec<IPInfo<AccountId>>> {
+        self.client
+            .runtime_api()
+            .reliable_ips(self.at(at), filter)
+            .map_err(|e| runtime_error(e, "Unable to query the reliable IPs.").into())
+    }
+
+    fn ip_reviews(
+        &self,
+        ip: AccountId,
+        start: u32,
+        limit: u32,
+        at: Option<Hash>,
+    ) -> RpcResult<Vec<ReviewInfo<AccountId, BlockNumber, AgreementId>>> {
+        self.client
+            .runtime_api()
+            .ip_reviews(self.at(at), ip, start, limit)
+            .map_err(|e| runtime_error(e, "Unable to query the IP reviews.").into())
+    }
+
+    fn ip_free_storage(&self, ip: AccountId, at: Option<Hash>) -> RpcResult<Option<StorageSizeMB>> {
+        self.client
+            .runtime_api()
+            .ip_free_storage(self.at(at), ip)
+            .map_err(|e| runtime_error(e, "Unable to query the free storage of the IP.").into())
+    }
+
+    fn ip_agreements(
+        &self,
+        ip: AccountId,
+        start_after: Option<AgreementId>,
+        limit: u32,
+        at: Option<Hash>,
+    ) -> RpcResult<Vec<AgreementId>> {
+        self.client
+            .runtime_api()
+            .ip_agreements(self.at(at), ip, start_after, limit)
+            .map_err(|e| runtime_error(e, "Unable to query the IP agreements.").into())
+    }
+
+    fn consumer_agreements(
+        &self,
+        consumer: AccountId,
+        start_after: Option<AgreementId>,
+        limit: u32,
+        at: Option<Hash>,
+    ) -> RpcResult<Vec<AgreementId>> {
+        self.client
+            .runtime_api()
+            .consumer_agreements(self.at(at), consumer, start_after, limit)
+            .map_err(|e| runtime_error(e, "Unable to query the consumer agreements.").into())
+    }
+
+    fn agreement(
+        &self,
+        agreement_id: AgreementId,
+        at: Option<Hash>,
+    ) -> RpcResult<Option<AgreementInfo<AccountId, Balance, BlockNumber>>> {
+        self.client
+            .runtime_api()
+            .agreement(self.at(at), agreement_id)
+            .map_err(|e| runtime_error(e, "Unable to query the agreement.").into())
+    }
+
+    fn quote_agreement(
+        &self,
+        storage: StorageSizeMB,
+        compute: ComputeResources,
+        activation_block: BlockNumber,
+        payment_plan: Vec<BlockNumber>,
+        at: Option<Hash>,
+    ) -> RpcResult<Option<AgreementQuote<Balance>>> {
+        self.client
+            .runtime_api()
+            .quote_agreement(
+                self.at(at),
+                storage,
+                compute,
+                activation_block,
+                payment_plan,
+            )
+            .map_err(|e| runtime_error(e, "Unable to quote the agreement.").into())
+    }
+
+    fn agreement_files(
+        &self,
+        agreement_id: AgreementId,
+        at: Option<Hash>,
+    ) -> RpcResult<Vec<FileId>> {
+        self.client
+            .runtime_api()
+            .agreement_files(self.at(at), agreement_id)
+            .map_err(|e| runtime_error(e, "Unable to query the agreement files.").into())
+    }
+
+    fn file(&self, file_id: FileId, at: Option<Hash>) -> RpcResult<Option<FileInfo<AgreementId>>> {
+        self.client
+            .runtime_api()
+            .file(self.at(at), file_id)
+            .map_err(|e| runtime_error(e, "Unable to query the file.").into())
+    }
+
+    fn subscribe_agreement(
+        &self,
+        sink: SubscriptionSink,
+        agreement_id: AgreementId,
+    ) -> SubscriptionResult {
+        self.subscribe(sink, move |event| event.agreement_id == agreement_id);
+        Ok(())
+    }
+
+    fn subscribe_account(&self, sink: SubscriptionSink, account: AccountId) -> SubscriptionResult {
+        self.subscribe(sink, move |event| {
+            event.ip.as_ref() == Some(&account) || event.consumer.as_ref() == Some(&account)
+        });
+        Ok(())
+    }
+}
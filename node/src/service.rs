@@ -174,7 +174,7 @@ pub fn new_partial(
         let select_chain = select_chain.clone();
         let keystore = keystore_container.keystore();
 
-        move |deny_unsafe, _| {
+        move |deny_unsafe, subscription_executor| {
             let deps = crate::rpc::FullDeps {
                 client: client.clone(),
                 pool: pool.clone(),
@@ -184,8 +184,9 @@ pub fn new_partial(
                     babe_worker_handle: babe_worker_handle.clone(),
                     keystore: keystore.clone(),
                 },
+                subscription_executor,
             };
-            crate::rpc::create_full(deps).map_err(Into::into)
+            crate::rpc::create_full::<_, _, _, FullBackend>(deps).map_err(Into::into)
         }
     };
 
@@ -0,0 +1,41 @@
+// Copyright (C) NC2D Labs.
+// This file is part of Deitos Node.
+
+// Deitos Node is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Deitos Node is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Deitos Node.  If not, see <http://www.gnu.org/licenses/>.
+
+//! # Deitos File System Runtime API
+//!
+//! Runtime API definition for querying the files registered for the Deitos agreements.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use parity_scale_codec::Codec;
+use sp_std::vec::Vec;
+
+pub use pallet_deitos_fs::FileInfo;
+
+sp_api::decl_runtime_apis! {
+    /// The API to query the files of the Deitos agreements.
+    pub trait DeitosFsApi<AgreementId, FileId>
+    where
+        AgreementId: Codec,
+        FileId: Codec,
+    {
+        /// Returns the details of the file.
+        fn file(file_id: FileId) -> Option<FileInfo<AgreementId>>;
+
+        /// Returns the ids of the files registered for the agreement.
+        fn agreement_files(agreement_id: AgreementId) -> Vec<FileId>;
+    }
+}
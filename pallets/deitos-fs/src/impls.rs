@@ -39,4 +39,21 @@ impl<T: Config> Pallet<T> {
             *value
         })
     }
+
+    /// Returns the details of the file, whether it is verified or still being checked.
+    /// Used by the runtime API.
+    pub fn file_info(file_id: T::FileId) -> Option<FileInfo<T::AgreementId>> {
+        Files::<T>::get(file_id)
+            .or_else(|| FilesToBeChecked::<T>::get(file_id))
+            .map(Into::into)
+    }
+
+    /// Returns the ids of the files registered for the agreement. Used by the runtime API.
+    pub fn agreement_file_ids(agreement_id: T::AgreementId) -> Vec<T::FileId> {
+        Files::<T>::iter()
+            .chain(FilesToBeChecked::<T>::iter())
+            .filter(|(_, file)| file.agreement_id == agreement_id)
+            .map(|(file_id, _)| file_id)
+            .collect()
+    }
 }
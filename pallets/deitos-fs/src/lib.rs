@@ -50,6 +50,7 @@ pub use sp_runtime::{
 
 use frame_support::traits::Randomness;
 use frame_system::offchain::{SendTransactionTypes, SubmitTransaction};
+use pallet_deitos::WeightInfo as DeitosWeightInfo;
 use rand_chacha::{
     rand_core::{RngCore, SeedableRng},
     ChaChaRng,
@@ -180,6 +181,8 @@ pub mod pallet {
         CheckDataInternalFailure,
         /// File fetched Failed
         FileFetchFailed,
+        /// The agreement is suspended, so its files are read-only
+        AgreementSuspended,
     }
 
     /// Hook
@@ -238,6 +241,7 @@ pub mod pallet {
     #[pallet::call]
     impl<T: Config> Pallet<T> {
         /// This call register a file for a certain agreement. It checks the consumer has an active agreement that allows the upload.
+        /// The files of a suspended agreement are read-only, so no files can be registered until the consumer pays the overdue installment.
         #[pallet::call_index(0)]
         #[pallet::weight(<T as Config>::WeightInfo::register_file())]
         pub fn register_file(
@@ -248,6 +252,10 @@ pub mod pallet {
         ) -> DispatchResult {
             let consumer = ensure_signed(origin)?;
             pallet_deitos::Pallet::<T>::consumer_has_agreement(&consumer, &agreement_id)?;
+            ensure!(
+                !pallet_deitos::Pallet::<T>::is_agreement_suspended(&agreement_id),
+                Error::<T>::AgreementSuspended
+            );
 
             let file_id: T::FileId = Self::next_file_id();
 
@@ -290,6 +298,9 @@ pub mod pallet {
 
                         if file_check.error_count > error_boundary {
                             file_check.status = FileValidationStatus::Conflict;
+                            pallet_deitos::Pallet::<T>::record_file_conflict(
+                                file_check.agreement_id,
+                            );
                             Self::deposit_event(Event::FileConflict { file_id });
                         }
                         if file_check.error_count > 10 {
@@ -304,14 +315,27 @@ pub mod pallet {
             Ok(())
         }
 
+        /// Unsigned call to submit the result of a data integrity check from the offchain worker.
+        /// The result is recorded in the reliability record of the IP storing the file, and a failed
+        /// check is reported against the SLA terms of the agreement the file belongs to. A failure
+        /// to report the check doesn't prevent the result from being recorded.
         #[pallet::call_index(2)]
-        #[pallet::weight(<T as Config>::WeightInfo::data_integrity_protocol())]
+        #[pallet::weight(<T as Config>::WeightInfo::data_integrity_protocol().saturating_add(
+            <T as pallet_deitos::Config>::WeightInfo::report_failed_integrity_check()
+        ))]
         pub fn data_integrity_protocol(
             origin: OriginFor<T>,
             file_id: T::FileId,
             result: CheckResult,
         ) -> DispatchResult {
             ensure_none(origin)?;
+            let passed = result == CheckResult::CheckPassed;
+            // The check counts towards the reliability of the IP and, if failed, against the SLA
+            // terms of the file agreement
+            if let Some(file) = Files::<T>::get(file_id) {
+                pallet_deitos::Pallet::<T>::record_integrity_check(file.agreement_id, passed);
+            }
+
             match result {
                 CheckResult::CheckPassed => {
                     Self::deposit_event(Event::DataIntegrityCheckSuccessful { file_id });
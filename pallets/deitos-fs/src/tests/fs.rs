@@ -15,11 +15,16 @@
 // along with Deitos Node.  If not, see <http://www.gnu.org/licenses/>.
 
 use super::*;
+use frame_support::{
+    assert_noop,
+    traits::{fungible, tokens::Precision::Exact, Get},
+};
 use frame_system::pallet_prelude::BlockNumberFor;
-use pallet_deitos::StorageSizeMB;
+use pallet_deitos::{ComputeResources, SlaTerms, StorageSizeMB};
+use sp_runtime::Perbill;
 
 use crate::{
-    pallet::{Event, Files},
+    pallet::{Error, Event, Files},
     types::*,
 };
 
@@ -43,8 +48,10 @@ pub fn create_agreement() {
         RuntimeOrigin::signed(CONSUMER),
         IP,
         storage,
+        ComputeResources::default(),
         activation_block,
         payment_plan.clone(),
+        None,
     ));
 
     // IP accepts agreement
@@ -86,6 +93,83 @@ fn file_is_correctly_registered() {
     });
 }
 
+#[test]
+fn file_is_not_registered_when_agreement_suspended() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+        let activation_block: BlockNumberFor<Test> = 100;
+        let payment_plan: PaymentPlan<Test> = vec![activation_block + 100, activation_block + 200]
+            .try_into()
+            .unwrap();
+        register_and_activate_ip(IP, storage);
+
+        assert_ok!(Deitos::consumer_request_agreement(
+            RuntimeOrigin::signed(CONSUMER),
+            IP,
+            storage,
+            ComputeResources::default(),
+            activation_block,
+            payment_plan,
+            None,
+        ));
+        let agreement_id = 1;
+        assert_ok!(Deitos::ip_accept_agreement(
+            RuntimeOrigin::signed(IP),
+            agreement_id,
+        ));
+
+        // The first installment is not prepaid, so the files are read-only after activation
+        System::set_block_number(activation_block);
+
+        let hash = to_hash("c43b3a108132702db1a3593550ef836081e781755dc32956c87c5be92e15d7c0");
+        assert_noop!(
+            DeitosFs::register_file(
+                RuntimeOrigin::signed(CONSUMER),
+                agreement_id,
+                hash.into(),
+                FileName::try_from(b"file.txt".to_vec()).unwrap()
+            ),
+            Error::<Test>::AgreementSuspended
+        );
+    });
+}
+
+#[test]
+fn file_is_registered_before_activation() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+        let activation_block: BlockNumberFor<Test> = 100;
+        let payment_plan: PaymentPlan<Test> = vec![activation_block + 100, activation_block + 200]
+            .try_into()
+            .unwrap();
+        register_and_activate_ip(IP, storage);
+
+        assert_ok!(Deitos::consumer_request_agreement(
+            RuntimeOrigin::signed(CONSUMER),
+            IP,
+            storage,
+            ComputeResources::default(),
+            activation_block,
+            payment_plan,
+            None,
+        ));
+        let agreement_id = 1;
+        assert_ok!(Deitos::ip_accept_agreement(
+            RuntimeOrigin::signed(IP),
+            agreement_id,
+        ));
+
+        // No installment is due before activation, so the files can be uploaded right away
+        let hash = to_hash("c43b3a108132702db1a3593550ef836081e781755dc32956c87c5be92e15d7c0");
+        assert_ok!(DeitosFs::register_file(
+            RuntimeOrigin::signed(CONSUMER),
+            agreement_id,
+            hash.into(),
+            FileName::try_from(b"file.txt".to_vec()).unwrap()
+        ));
+    });
+}
+
 #[test]
 fn file_is_correctly_verified() {
     new_test_ext().execute_with(|| {
@@ -156,3 +240,223 @@ fn file_is_not_verified() {
         assert_eq!(file.error_count, 1);
     });
 }
+
+#[test]
+fn integrity_checks_and_conflicts_are_recorded_in_ip_reliability() {
+    new_test_ext().execute_with(|| {
+        let agreement_id = 1;
+        create_agreement();
+
+        let hash = to_hash("c43b3a108132702db1a3593550ef836081e781755dc32956c87c5be92e15d7c0");
+        let returned_hash =
+            to_hash("XXXb3a108132702db1a3593550ef836081e781755dc32956c87c5be92e15d7c0");
+        for file_name in [b"conflict.txt".to_vec(), b"file.txt".to_vec()] {
+            assert_ok!(DeitosFs::register_file(
+                RuntimeOrigin::signed(CONSUMER),
+                agreement_id,
+                hash.into(),
+                FileName::try_from(file_name).unwrap()
+            ));
+        }
+
+        // The validation of the first file ends in a conflict once the error boundary is passed
+        let file = FilesToBeChecked::<Test>::get(1).unwrap();
+        for _ in 0..=ErrorBoundary::get() {
+            assert_ok!(DeitosFs::submit_file_validation(
+                RuntimeOrigin::none(),
+                1,
+                file.clone(),
+                returned_hash
+            ));
+        }
+        System::assert_has_event(RuntimeEvent::DeitosFs(Event::FileConflict { file_id: 1 }));
+
+        // The second file is verified and checked
+        let file = FilesToBeChecked::<Test>::get(2).unwrap();
+        assert_ok!(DeitosFs::submit_file_validation(
+            RuntimeOrigin::none(),
+            2,
+            file,
+            hash
+        ));
+        for result in [
+            CheckResult::CheckPassed,
+            CheckResult::CheckPassed,
+            CheckResult::DataIntegrityCheckFailed,
+        ] {
+            assert_ok!(DeitosFs::data_integrity_protocol(
+                RuntimeOrigin::none(),
+                2,
+                result
+            ));
+        }
+
+        let reliability = Deitos::get_ip(IP).unwrap().reliability;
+        assert_eq!(reliability.integrity_checks_passed, 2);
+        assert_eq!(reliability.integrity_checks_failed, 1);
+        assert_eq!(reliability.file_conflicts, 1);
+    });
+}
+
+#[test]
+fn failed_integrity_checks_breach_sla_terms() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+        let activation_block: BlockNumberFor<Test> = 100;
+        let payment_plan: PaymentPlan<Test> = vec![activation_block + 100, activation_block + 200]
+            .try_into()
+            .unwrap();
+        let sla = SlaTerms {
+            max_failed_checks: 0,
+            refund_per_breach: Perbill::from_percent(10),
+            termination_threshold: 1,
+        };
+        register_and_activate_ip(IP, storage);
+
+        assert_ok!(Deitos::consumer_request_agreement(
+            RuntimeOrigin::signed(CONSUMER),
+            IP,
+            storage,
+            ComputeResources::default(),
+            activation_block,
+            payment_plan,
+            Some(sla),
+        ));
+        let agreement_id = 1;
+        let file_id = 1;
+        assert_ok!(Deitos::ip_accept_agreement(
+            RuntimeOrigin::signed(IP),
+            agreement_id,
+        ));
+        assert_ok!(Deitos::consumer_prepay_installment(
+            RuntimeOrigin::signed(CONSUMER),
+            agreement_id,
+        ));
+
+        let hash = to_hash("c43b3a108132702db1a3593550ef836081e781755dc32956c87c5be92e15d7c0");
+        assert_ok!(DeitosFs::register_file(
+            RuntimeOrigin::signed(CONSUMER),
+            agreement_id,
+            hash.into(),
+            FileName::try_from(b"file.txt".to_vec()).unwrap()
+        ));
+        let file = FilesToBeChecked::<Test>::get(file_id).unwrap();
+        assert_ok!(DeitosFs::submit_file_validation(
+            RuntimeOrigin::none(),
+            file_id,
+            file,
+            hash
+        ));
+
+        // The first failed check refunds a share of the current installment
+        System::set_block_number(activation_block + 50);
+        assert_ok!(DeitosFs::data_integrity_protocol(
+            RuntimeOrigin::none(),
+            file_id,
+            CheckResult::DataIntegrityCheckFailed
+        ));
+        System::assert_has_event(RuntimeEvent::Deitos(pallet_deitos::Event::SlaBreached {
+            agreement_id,
+            consumer: CONSUMER,
+            breaches: 1,
+            refunded: 100 * PRICE_STORAGE * storage / 10,
+        }));
+
+        // The second failed check is past the termination threshold
+        assert_ok!(DeitosFs::data_integrity_protocol(
+            RuntimeOrigin::none(),
+            file_id,
+            CheckResult::DataIntegrityCheckFailed
+        ));
+        assert_eq!(Deitos::get_agreement(agreement_id), None);
+        System::assert_has_event(RuntimeEvent::Deitos(
+            pallet_deitos::Event::AgreementTerminatedSlaBreach {
+                agreement_id,
+                ip: IP,
+                consumer: CONSUMER,
+                refunded: 2 * 100 * PRICE_STORAGE * storage + CONSUMER_SERVICE_DEPOSIT
+                    - 100 * PRICE_STORAGE * storage / 10,
+            },
+        ));
+    });
+}
+
+#[test]
+fn integrity_check_is_recorded_when_the_sla_report_fails() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+        let activation_block: BlockNumberFor<Test> = 100;
+        let payment_plan: PaymentPlan<Test> = vec![activation_block + 100, activation_block + 200]
+            .try_into()
+            .unwrap();
+        let sla = SlaTerms {
+            max_failed_checks: 0,
+            refund_per_breach: Perbill::from_percent(10),
+            termination_threshold: 0,
+        };
+        register_and_activate_ip(IP, storage);
+
+        assert_ok!(Deitos::consumer_request_agreement(
+            RuntimeOrigin::signed(CONSUMER),
+            IP,
+            storage,
+            ComputeResources::default(),
+            activation_block,
+            payment_plan,
+            Some(sla),
+        ));
+        let agreement_id = 1;
+        let file_id = 1;
+        assert_ok!(Deitos::ip_accept_agreement(
+            RuntimeOrigin::signed(IP),
+            agreement_id,
+        ));
+        assert_ok!(Deitos::consumer_prepay_installment(
+            RuntimeOrigin::signed(CONSUMER),
+            agreement_id,
+        ));
+
+        let hash = to_hash("c43b3a108132702db1a3593550ef836081e781755dc32956c87c5be92e15d7c0");
+        assert_ok!(DeitosFs::register_file(
+            RuntimeOrigin::signed(CONSUMER),
+            agreement_id,
+            hash.into(),
+            FileName::try_from(b"file.txt".to_vec()).unwrap()
+        ));
+        let file = FilesToBeChecked::<Test>::get(file_id).unwrap();
+        assert_ok!(DeitosFs::submit_file_validation(
+            RuntimeOrigin::none(),
+            file_id,
+            file,
+            hash
+        ));
+
+        // The prepaid installment is no longer on hold, so terminating the agreement fails
+        assert_ok!(<Balances as fungible::MutateHold<_>>::release(
+            &pallet_deitos::HoldReason::ConsumerInstallment.into(),
+            &CONSUMER,
+            100 * PRICE_STORAGE * storage,
+            Exact,
+        ));
+
+        System::set_block_number(activation_block + 50);
+        assert_ok!(DeitosFs::data_integrity_protocol(
+            RuntimeOrigin::none(),
+            file_id,
+            CheckResult::DataIntegrityCheckFailed
+        ));
+
+        // The check is recorded even though the agreement could not be terminated
+        assert!(Deitos::get_agreement(agreement_id).is_some());
+        assert_eq!(
+            Deitos::get_ip(IP)
+                .unwrap()
+                .reliability
+                .integrity_checks_failed,
+            1
+        );
+        System::assert_last_event(RuntimeEvent::DeitosFs(Event::DataIntegrityCheckFailed {
+            file_id,
+        }));
+    });
+}
@@ -20,12 +20,13 @@ use frame_support::{
     traits::{ConstU32, ConstU64},
     PalletId,
 };
-use pallet_deitos::{IPStatus, PaymentPlan, StorageSizeMB};
+use frame_system::EnsureRoot;
+use pallet_deitos::{ComputeResources, IPStatus, PaymentPlan, StorageSizeMB};
 use sp_core::H256;
 use sp_keystore::{testing::MemoryKeystore, KeystoreExt};
 use sp_runtime::{
     traits::{BlakeTwo256, IdentityLookup},
-    BuildStorage,
+    BuildStorage, Perbill,
 };
 
 use crate as pallet_deitos_fs;
@@ -58,11 +59,17 @@ where
 
 parameter_types! {
     pub const DeitosPalletId: PalletId = PalletId(*b"DeitosId");
+    pub const ConsumerTerminationPenalty: Perbill = Perbill::from_percent(10);
+    pub const IPCancellationPenalty: Perbill = Perbill::from_percent(10);
+    pub const LateFee: Perbill = Perbill::from_percent(10);
+    pub const SlashedDepositConsumerShare: Perbill = Perbill::from_percent(50);
+    pub const ReputationRetainedWeight: Perbill = Perbill::from_percent(50);
 }
 
 type AccountId = u64;
 type Balance = u64;
 type AgreementId = u32;
+type OfferId = u32;
 type FileId = u32;
 
 pub const IP_INITIAL_DEPOSIT: Balance = 1_000_000;
@@ -149,9 +156,29 @@ impl pallet_deitos::Config for Test {
     type Currency = Balances;
     type RuntimeHoldReason = RuntimeHoldReason;
     type AgreementId = AgreementId;
+    type OfferId = OfferId;
     type PaymentPlanLimit = ConstU32<500>;
-    type IPAgreementsLimit = ConstU32<500>;
-    type ConsumerAgreementsLimit = ConstU32<500>;
+    type IPOffersLimit = ConstU32<100>;
+    type MaxEvidenceLength = ConstU32<128>;
+    type MaxCommentLength = ConstU32<256>;
+    type MaxSettlementsPerBlock = ConstU32<100>;
+    type AutoPayLeadBlocks = ConstU64<10>;
+    type ConsumerTerminationPenalty = ConsumerTerminationPenalty;
+    type IPCancellationPenalty = IPCancellationPenalty;
+    type PaymentGracePeriod = ConstU64<10>;
+    type LateFee = LateFee;
+    type FeedbackPeriod = ConstU64<100>;
+    type ReputationDecayPeriod = ConstU64<1000>;
+    type ReputationRetainedWeight = ReputationRetainedWeight;
+    type ReputationPriorWeight = ConstU64<1000>;
+    type ExpiredAgreementCleanupReward = ConstU64<100>;
+    type SlashedDepositConsumerShare = SlashedDepositConsumerShare;
+    type BiddingPeriod = ConstU64<10>;
+    type BidBond = ConstU64<100>;
+    type MaxBidsPerRequest = ConstU32<3>;
+    type TreasuryAccount = ConstU64<5>;
+    type DisputeOrigin = EnsureRoot<AccountId>;
+    type SlashOrigin = EnsureRoot<AccountId>;
     type PalletId = DeitosPalletId;
 }
 
@@ -193,6 +220,7 @@ pub fn new_test_ext() -> sp_io::TestExternalities {
         ip_initial_deposit: IP_INITIAL_DEPOSIT,
         consumer_service_deposit: CONSUMER_SERVICE_DEPOSIT,
         price_storage_mb_per_block: PRICE_STORAGE,
+        ..Default::default()
     }
     .assimilate_storage(&mut t)
     .unwrap();
@@ -206,7 +234,8 @@ pub fn new_test_ext() -> sp_io::TestExternalities {
 pub fn register_ip(ip: AccountId, total_storage: StorageSizeMB) {
     assert_ok!(Deitos::ip_register(
         RuntimeOrigin::signed(ip),
-        total_storage
+        total_storage,
+        ComputeResources::default(),
     ));
 }
 
@@ -15,6 +15,8 @@
 // along with Deitos Node.  If not, see <http://www.gnu.org/licenses/>.
 
 use scale_info::TypeInfo;
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
 
 use crate::*;
 
@@ -34,6 +36,7 @@ pub type FileHash = [u8; 64];
 
 /// Lifecycle of a file
 #[derive(Copy, Clone, Encode, Decode, Eq, PartialEq, MaxEncodedLen, TypeInfo, Debug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 #[scale_info(skip_type_params(T))]
 pub enum FileValidationStatus {
     /// FileStatus is being checked
@@ -94,3 +97,31 @@ pub enum CheckResult {
     /// Data integrity check
     DataIntegrityCheckFailed,
 }
+
+/// The details of a file returned by the runtime API.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, Debug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct FileInfo<AgreementId> {
+    /// Agreement ID
+    pub agreement_id: AgreementId,
+    /// hash of the file
+    pub hash: Vec<u8>,
+    /// file name
+    pub file_name: Vec<u8>,
+    /// File validation status
+    pub status: FileValidationStatus,
+    /// Check error count
+    pub error_count: u32,
+}
+
+impl<T: pallet::Config> From<FileDetails<T>> for FileInfo<T::AgreementId> {
+    fn from(file: FileDetails<T>) -> Self {
+        Self {
+            agreement_id: file.agreement_id,
+            hash: file.hash.to_vec(),
+            file_name: file.file_name.into_inner(),
+            status: file.status,
+            error_count: file.error_count,
+        }
+    }
+}
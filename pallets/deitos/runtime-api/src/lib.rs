@@ -0,0 +1,83 @@
+// Copyright (C) NC2D Labs.
+// This file is part of Deitos Node.
+
+// Deitos Node is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Deitos Node is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Deitos Node.  If not, see <http://www.gnu.org/licenses/>.
+
+//! # Deitos Runtime API
+//!
+//! Runtime API definition for querying the state of the Deitos protocol: IPs, agreements and the
+//! cost of proposed agreements.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use parity_scale_codec::Codec;
+use sp_std::vec::Vec;
+
+pub use pallet_deitos::{
+    AgreementInfo, AgreementQuote, ComputeResources, IPInfo, ReliabilityFilter, ReviewInfo,
+    StorageSizeMB,
+};
+
+sp_api::decl_runtime_apis! {
+    /// The API to query the state of the Deitos protocol.
+    pub trait DeitosApi<AccountId, Balance, BlockNumber, AgreementId>
+    where
+        AccountId: Codec,
+        Balance: Codec,
+        BlockNumber: Codec,
+        AgreementId: Codec,
+    {
+        /// Returns all the registered IPs with their status, capacity and average rating.
+        fn ips() -> Vec<IPInfo<AccountId>>;
+
+        /// Returns the registered IPs whose reliability record meets the filter.
+        fn reliable_ips(filter: ReliabilityFilter) -> Vec<IPInfo<AccountId>>;
+
+        /// Returns up to `limit` reviews from the review log of the IP, starting at the given index.
+        fn ip_reviews(
+            ip: AccountId,
+            start: u32,
+            limit: u32,
+        ) -> Vec<ReviewInfo<AccountId, BlockNumber, AgreementId>>;
+
+        /// Returns the storage of the IP that is not allocated to any agreement yet, or `None` if
+        /// the IP is not registered.
+        fn ip_free_storage(ip: AccountId) -> Option<StorageSizeMB>;
+
+        /// Returns a page of up to `limit` ids of the agreements of the IP, starting after the
+        /// given agreement id.
+        fn ip_agreements(ip: AccountId, start_after: Option<AgreementId>, limit: u32) -> Vec<AgreementId>;
+
+        /// Returns a page of up to `limit` ids of the agreements of the consumer, starting after
+        /// the given agreement id.
+        fn consumer_agreements(
+            consumer: AccountId,
+            start_after: Option<AgreementId>,
+            limit: u32,
+        ) -> Vec<AgreementId>;
+
+        /// Returns the details of the agreement along with its next due installment and overdue
+        /// state.
+        fn agreement(agreement_id: AgreementId) -> Option<AgreementInfo<AccountId, Balance, BlockNumber>>;
+
+        /// Returns the total cost, the deposits and the cost of every installment of a proposed
+        /// agreement. Returns `None` if the payment plan is invalid.
+        fn quote_agreement(
+            storage: StorageSizeMB,
+            compute: ComputeResources,
+            activation_block: BlockNumber,
+            payment_plan: Vec<BlockNumber>,
+        ) -> Option<AgreementQuote<Balance>>;
+    }
+}
@@ -32,6 +32,58 @@ use crate::Pallet as Deitos;
 
 use super::*;
 
+/// Funds, registers and activates an IP.
+fn register_ip<T: Config>(
+    ip: &T::AccountId,
+    total_storage: StorageSizeMB,
+) -> Result<(), BenchmarkError> {
+    T::Currency::mint_into(ip, BalanceOf::<T>::from(1_000_000_000_u32))?;
+    Deitos::<T>::ip_register(
+        RawOrigin::Signed(ip.clone()).into(),
+        total_storage,
+        ComputeResources::default(),
+    )?;
+    Deitos::<T>::update_ip_status(
+        RawOrigin::Root.into(),
+        T::Lookup::unlookup(ip.clone()),
+        IPStatus::Active,
+    )?;
+    Ok(())
+}
+
+/// Funds a consumer and creates an agreement with the IP accepted by it. The agreement starts at
+/// the next block and has an installment every 10 blocks.
+///
+/// Returns the agreement id and the payment plan.
+fn create_agreement<T: Config>(
+    ip: &T::AccountId,
+    consumer: &T::AccountId,
+    installments: u32,
+) -> Result<(T::AgreementId, Vec<BlockNumberFor<T>>), BenchmarkError> {
+    T::Currency::mint_into(consumer, BalanceOf::<T>::from(1_000_000_000_u32))?;
+
+    let activation_block = frame_system::Pallet::<T>::block_number() + 1u32.into();
+    let payment_plan: Vec<BlockNumberFor<T>> = (1..=installments)
+        .map(|i| activation_block + (10 * i).into())
+        .collect();
+    Deitos::<T>::consumer_request_agreement(
+        RawOrigin::Signed(consumer.clone()).into(),
+        T::Lookup::unlookup(ip.clone()),
+        1,
+        ComputeResources::default(),
+        activation_block,
+        payment_plan
+            .clone()
+            .try_into()
+            .map_err(|_| BenchmarkError::Stop("invalid payment plan"))?,
+        None,
+    )?;
+    let agreement_id = CurrentAgreementId::<T>::get();
+    Deitos::<T>::ip_accept_agreement(RawOrigin::Signed(ip.clone()).into(), agreement_id)?;
+
+    Ok((agreement_id, payment_plan))
+}
+
 #[benchmarks]
 mod benchmarks {
     use super::*;
@@ -39,6 +91,10 @@ mod benchmarks {
     #[benchmark]
     fn ip_register() {
         let total_storage: StorageSizeMB = 1000u64;
+        let total_compute = ComputeResources {
+            vcores: 8,
+            ram: 16_384,
+        };
 
         let caller = whitelisted_caller();
 
@@ -47,7 +103,188 @@ mod benchmarks {
         T::Currency::mint_into(&caller, balance);
 
         #[extrinsic_call]
-        _(RawOrigin::Signed(caller), total_storage);
+        _(RawOrigin::Signed(caller), total_storage, total_compute);
+    }
+
+    #[benchmark]
+    fn consumer_submit_feedback(
+        c: Linear<0, { T::MaxCommentLength::get() }>,
+    ) -> Result<(), BenchmarkError> {
+        let ip: T::AccountId = account("ip", 0, 0);
+        let consumer: T::AccountId = whitelisted_caller();
+
+        register_ip::<T>(&ip, 1000)?;
+
+        // Complete an agreement with a single installment, paid by the consumer security deposit
+        let (agreement_id, payment_plan) = create_agreement::<T>(&ip, &consumer, 1)?;
+        frame_system::Pallet::<T>::set_block_number(payment_plan[0] + 1u32.into());
+        Deitos::<T>::ip_withdraw_installments(RawOrigin::Signed(ip.clone()).into(), agreement_id)?;
+
+        let comment: Comment<T> = vec![b'x'; c as usize]
+            .try_into()
+            .map_err(|_| BenchmarkError::Stop("comment too long"))?;
+
+        #[extrinsic_call]
+        _(
+            RawOrigin::Signed(consumer),
+            agreement_id,
+            Score::Five,
+            Score::Five,
+            Score::Five,
+            comment,
+        );
+
+        assert_eq!(ReviewCount::<T>::get(&ip), 1);
+        Ok(())
+    }
+
+    #[benchmark]
+    fn settle_agreement() -> Result<(), BenchmarkError> {
+        let ip: T::AccountId = account("ip", 0, 0);
+        let consumer: T::AccountId = account("consumer", 0, 0);
+        register_ip::<T>(&ip, 1000)?;
+        let (agreement_id, payment_plan) = create_agreement::<T>(&ip, &consumer, 1)?;
+
+        // The last installment is settled, completing the agreement
+        let block_number = payment_plan[0] + 1u32.into();
+        frame_system::Pallet::<T>::set_block_number(block_number);
+
+        #[block]
+        {
+            Deitos::<T>::settle_agreement(agreement_id, block_number)?;
+        }
+
+        assert_eq!(
+            Agreements::<T>::get(agreement_id).map(|agreement| agreement.status),
+            Some(AgreementStatus::Completed)
+        );
+        Ok(())
+    }
+
+    #[benchmark]
+    fn auto_pay_installment() -> Result<(), BenchmarkError> {
+        let ip: T::AccountId = account("ip", 0, 0);
+        let consumer: T::AccountId = account("consumer", 0, 0);
+        register_ip::<T>(&ip, 1000)?;
+        let (agreement_id, _) = create_agreement::<T>(&ip, &consumer, 2)?;
+        Deitos::<T>::consumer_set_auto_pay(RawOrigin::Signed(consumer).into(), agreement_id, true)?;
+
+        // The first installment is overdue, so the agreement is suspended and the late fee is paid
+        // as well
+        let block_number = Agreements::<T>::get(agreement_id)
+            .map(|agreement| agreement.activation_block)
+            .ok_or(BenchmarkError::Stop("agreement not found"))?;
+        frame_system::Pallet::<T>::set_block_number(block_number);
+        Deitos::<T>::settle_agreement(agreement_id, block_number)?;
+
+        #[block]
+        {
+            Deitos::<T>::auto_pay_installment(agreement_id, block_number)?;
+        }
+
+        assert_eq!(
+            Agreements::<T>::get(agreement_id).map(|agreement| agreement.status),
+            Some(AgreementStatus::Active)
+        );
+        Ok(())
+    }
+
+    #[benchmark]
+    fn expire_agreement(o: Linear<0, { T::IPOffersLimit::get() }>) -> Result<(), BenchmarkError> {
+        let ip: T::AccountId = account("ip", 0, 0);
+        let consumer: T::AccountId = account("consumer", 0, 0);
+        register_ip::<T>(&ip, 1000)?;
+        for _ in 0..o {
+            Deitos::<T>::ip_create_offer(
+                RawOrigin::Signed(ip.clone()).into(),
+                OfferTerms {
+                    prices: Prices {
+                        storage_mb_per_block: BalanceOf::<T>::from(1_u32),
+                        vcore_per_block: Zero::zero(),
+                        ram_mb_per_block: Zero::zero(),
+                    },
+                    min_storage: 1,
+                    max_storage: 1000,
+                    min_duration: 10u32.into(),
+                    max_installment_period: 10u32.into(),
+                    auto_accept: false,
+                },
+            )?;
+        }
+        let (agreement_id, payment_plan) = create_agreement::<T>(&ip, &consumer, 1)?;
+
+        // The completed agreement of a draining IP expires, unregistering the IP
+        Deitos::<T>::ip_start_draining(RawOrigin::Signed(ip.clone()).into(), 1)?;
+        frame_system::Pallet::<T>::set_block_number(payment_plan[0] + 1u32.into());
+        Deitos::<T>::settle_agreement(agreement_id, payment_plan[0] + 1u32.into())?;
+
+        let block_number = Agreements::<T>::get(agreement_id)
+            .map(|agreement| agreement.feedback_deadline() + 1u32.into())
+            .ok_or(BenchmarkError::Stop("agreement not found"))?;
+        frame_system::Pallet::<T>::set_block_number(block_number);
+
+        #[block]
+        {
+            Deitos::<T>::expire_scheduled_agreement(agreement_id, block_number)?;
+        }
+
+        assert_eq!(
+            InfrastructureProviders::<T>::get(&ip).map(|ip_details| ip_details.status),
+            Some(IPStatus::Unregistered)
+        );
+        Ok(())
+    }
+
+    #[benchmark]
+    fn close_bidding(b: Linear<1, { T::MaxBidsPerRequest::get() }>) -> Result<(), BenchmarkError> {
+        let consumer: T::AccountId = account("consumer", 0, 0);
+        T::Currency::mint_into(&consumer, BalanceOf::<T>::from(1_000_000_000_u32))?;
+
+        let bidding_close =
+            frame_system::Pallet::<T>::block_number() + T::BiddingPeriod::get() + 1u32.into();
+        let activation_block = bidding_close + 1u32.into();
+        let payment_plan: PaymentPlan<T> = vec![activation_block + 10u32.into()]
+            .try_into()
+            .map_err(|_| BenchmarkError::Stop("invalid payment plan"))?;
+        Deitos::<T>::consumer_open_request(
+            RawOrigin::Signed(consumer).into(),
+            1,
+            ComputeResources::default(),
+            activation_block,
+            payment_plan,
+            BalanceOf::<T>::from(1_000_000_000_u32),
+            None,
+            None,
+        )?;
+        let request_id = CurrentAgreementId::<T>::get();
+
+        // Every bid but the most expensive one fails to be awarded, as its IP has no storage left
+        for i in 0..b {
+            let ip: T::AccountId = account("ip", i, 0);
+            register_ip::<T>(&ip, 1)?;
+            Deitos::<T>::ip_bid(
+                RawOrigin::Signed(ip.clone()).into(),
+                request_id,
+                Prices {
+                    storage_mb_per_block: BalanceOf::<T>::from(i + 1),
+                    vcore_per_block: Zero::zero(),
+                    ram_mb_per_block: Zero::zero(),
+                },
+            )?;
+            if i + 1 < b {
+                Deitos::<T>::ip_update_storage(RawOrigin::Signed(ip).into(), 0)?;
+            }
+        }
+        frame_system::Pallet::<T>::set_block_number(bidding_close);
+
+        #[block]
+        {
+            Deitos::<T>::close_bidding(request_id, bidding_close)?;
+        }
+
+        let winner: T::AccountId = account("ip", b - 1, 0);
+        assert_eq!(IPAgreementCount::<T>::get(&winner), 1);
+        Ok(())
     }
 
     impl_benchmark_test_suite!(Deitos, crate::tests::new_test_ext(), crate::tests::Test);
@@ -16,7 +16,7 @@
 
 use core::ops::Add;
 
-use frame_support::transactional;
+use frame_support::{transactional, weights::WeightMeter};
 use frame_system::pallet_prelude::BlockNumberFor;
 
 use crate::*;
@@ -28,6 +28,12 @@ impl<T: Config> Pallet<T> {
         Ok(())
     }
 
+    /// Checks if a multi-block migration of the pallet storage is in progress. The pallet calls
+    /// must not be dispatched until it is finished.
+    pub fn is_migrating() -> bool {
+        MigrationProgress::<T>::exists()
+    }
+
     /// Returns the current block number.
     pub fn current_block_number() -> BlockNumberFor<T> {
         frame_system::Pallet::<T>::block_number()
@@ -41,15 +47,22 @@ impl<T: Config> Pallet<T> {
         })
     }
 
+    /// Generates the next offer id and returns it.
+    pub fn next_offer_id() -> T::OfferId {
+        CurrentOfferId::<T>::mutate(|value| {
+            *value = value.add(One::one());
+            *value
+        })
+    }
+
     /// Checks if the consumer has the specified agreement.
     /// Returns Ok if the agreement is found, Err otherwise.
     pub fn consumer_has_agreement(
         consumer: &T::AccountId,
         agreement_id: &T::AgreementId,
     ) -> DispatchResult {
-        let agreements = ConsumerAgreements::<T>::get(consumer);
         // Check if the agreement ID exists in the consumer's agreements.
-        if agreements.contains(agreement_id) {
+        if ConsumerAgreements::<T>::contains_key(consumer, agreement_id) {
             // Agreement found, operation successful.
             Ok(())
         } else {
@@ -58,6 +71,129 @@ impl<T: Config> Pallet<T> {
         }
     }
 
+    /// Checks if the agreement is suspended, i.e. its status is `Suspended` or it is active with
+    /// an overdue installment that has not been detected yet. The files of a suspended agreement
+    /// are read-only.
+    pub fn is_agreement_suspended(agreement_id: &T::AgreementId) -> bool {
+        Agreements::<T>::get(agreement_id).is_some_and(|agreement| {
+            agreement.status == AgreementStatus::Suspended
+                || (agreement.status == AgreementStatus::Active
+                    && agreement.has_overdue_installments(Self::current_block_number()))
+        })
+    }
+
+    /// Creates an agreement requested by the consumer at the given prices. The activation block
+    /// must be in the future, the payment plan must be valid and the IP must be active and have
+    /// enough free storage and compute resources. The consumer deposits are held and the request
+    /// is scheduled to expire after its activation block.
+    ///
+    /// Returns the agreement id and the total deposit held.
+    #[transactional]
+    pub fn request_agreement(
+        consumer: T::AccountId,
+        ip: T::AccountId,
+        storage: StorageSizeMB,
+        compute: ComputeResources,
+        prices: Prices<T>,
+        activation_block: BlockNumberFor<T>,
+        payment_plan: PaymentPlan<T>,
+        sla: Option<SlaTerms>,
+    ) -> Result<(T::AgreementId, BalanceOf<T>), DispatchError> {
+        // Activation block must be in the future
+        ensure!(
+            activation_block > Self::current_block_number(),
+            Error::<T>::AgreementOutdated
+        );
+
+        // Payment plan must be valid
+        ensure!(
+            Self::is_valid_payment_plan(&payment_plan, activation_block),
+            Error::<T>::PaymentPlanInvalid
+        );
+
+        let (agreement_id, consumer_total_deposit) = Self::create_agreement(
+            consumer,
+            ip,
+            storage,
+            compute,
+            prices,
+            activation_block,
+            payment_plan,
+            sla,
+        )?;
+
+        Self::schedule_expiration(agreement_id, activation_block.saturating_add(One::one()));
+        Ok((agreement_id, consumer_total_deposit))
+    }
+
+    /// Creates an agreement at the given prices, with the status `ConsumerRequest`. The IP must
+    /// be active and have enough free storage and compute resources, and the consumer must meet
+    /// the minimum rating required by the IP. The consumer deposits are held.
+    ///
+    /// Returns the agreement id and the total deposit held.
+    #[transactional]
+    pub fn create_agreement(
+        consumer: T::AccountId,
+        ip: T::AccountId,
+        storage: StorageSizeMB,
+        compute: ComputeResources,
+        prices: Prices<T>,
+        activation_block: BlockNumberFor<T>,
+        payment_plan: PaymentPlan<T>,
+        sla: Option<SlaTerms>,
+    ) -> Result<(T::AgreementId, BalanceOf<T>), DispatchError> {
+        let ip_details = InfrastructureProviders::<T>::get(&ip).ok_or(Error::<T>::IPNotFound)?;
+
+        // IP is not leaving
+        ensure!(
+            ip_details.status != IPStatus::Draining,
+            Error::<T>::IPDraining
+        );
+
+        // IP is active
+        ensure!(
+            ip_details.status == IPStatus::Active,
+            Error::<T>::IPNotActive
+        );
+
+        // IP has enough free storage
+        ensure!(
+            storage > Zero::zero() && storage <= ip_details.free_storage(),
+            Error::<T>::InsufficientStorage
+        );
+
+        // IP has enough free compute resources
+        ensure!(
+            compute.fits_in(&ip_details.free_compute()),
+            Error::<T>::InsufficientCompute
+        );
+
+        // Consumer meets the minimum rating required by the IP
+        ensure!(
+            ConsumerProfiles::<T>::get(&consumer)
+                .rating
+                .meets(ip_details.min_consumer_rating),
+            Error::<T>::ConsumerRatingTooLow
+        );
+
+        let mut agreement = AgreementDetails::new_consumer_request(
+            ip,
+            consumer,
+            storage,
+            compute,
+            prices,
+            activation_block,
+            payment_plan,
+            sla,
+        );
+
+        let consumer_total_deposit =
+            agreement.hold_consumer_deposits(Self::consumer_service_deposit_amount())?;
+
+        let agreement_id = Self::insert_agreement(agreement)?;
+        Ok((agreement_id, consumer_total_deposit))
+    }
+
     /// Inserts a new agreement into the proper storages and returns the agreement id.
     #[transactional]
     pub fn insert_agreement(
@@ -68,45 +204,961 @@ impl<T: Config> Pallet<T> {
         InfrastructureProviders::<T>::try_mutate(&agreement.ip, |ip_details| {
             ip_details
                 .as_mut()
-                .ok_or(Error::<T>::IPNotFound)
-                .and_then(|x| {
-                    x.agreements
-                        .try_push(agreement_id)
-                        .map_err(|_| Error::<T>::IPAgreementsLimit)
+                .ok_or(Error::<T>::IPNotFound.into())
+                .and_then(|x| -> DispatchResult {
+                    x.allocate_storage(agreement.storage)?;
+                    x.allocate_compute(agreement.compute)
                 })
         })?;
-        ConsumerAgreements::<T>::try_mutate(&agreement.consumer, |agreements| {
-            agreements
-                .try_push(agreement_id)
-                .map_err(|_| Error::<T>::ConsumerAgreementsLimit)
-        })?;
+        IPAgreements::<T>::insert(&agreement.ip, agreement_id, ());
+        IPAgreementCount::<T>::mutate(&agreement.ip, |count| count.saturating_inc());
+        ConsumerAgreements::<T>::insert(&agreement.consumer, agreement_id, ());
+        ConsumerAgreementCount::<T>::mutate(&agreement.consumer, |count| count.saturating_inc());
 
         Agreements::<T>::insert(agreement_id, agreement);
         Ok(agreement_id)
     }
 
     /// Deletes an agreement updating the proper storages and returns the agreement details.
+    /// The resources allocated for the agreement are released, unless the agreement is completed
+    /// and the resources have been released already.
     pub fn delete_agreement(
         agreement_id: T::AgreementId,
     ) -> Result<AgreementDetails<T>, DispatchError> {
         let agreement = Agreements::<T>::take(agreement_id).ok_or(Error::<T>::AgreementNotFound)?;
-        InfrastructureProviders::<T>::mutate(&agreement.ip, |ip_details| {
-            ip_details.as_mut().and_then(|x| {
-                x.agreements
-                    .iter()
-                    .position(|&x| x == agreement_id)
-                    .map(|i| x.agreements.swap_remove(i))
-            })
+        RenewalProposals::<T>::remove(agreement_id);
+        AmendmentProposals::<T>::remove(agreement_id);
+        Self::unschedule_settlement(agreement_id);
+        Self::unschedule_auto_pay(agreement_id);
+        if agreement.status != AgreementStatus::Completed {
+            Self::release_ip_resources(&agreement.ip, agreement.storage, agreement.compute);
+        }
+        IPAgreements::<T>::remove(&agreement.ip, agreement_id);
+        IPAgreementCount::<T>::mutate_exists(&agreement.ip, |count| {
+            *count = count.map(|x| x.saturating_sub(1)).filter(|x| *x > 0)
         });
-        ConsumerAgreements::<T>::mutate(&agreement.consumer, |agreements| {
-            agreements
-                .iter()
-                .position(|&x| x == agreement_id)
-                .map(|i| agreements.swap_remove(i))
+        ConsumerAgreements::<T>::remove(&agreement.consumer, agreement_id);
+        ConsumerAgreementCount::<T>::mutate_exists(&agreement.consumer, |count| {
+            *count = count.map(|x| x.saturating_sub(1)).filter(|x| *x > 0)
         });
+        Self::unregister_if_drained(&agreement.ip)?;
         Ok(agreement)
     }
 
+    /// Rejects the agreements of the IP that are still at negotiation stage. Fails if the IP has
+    /// more than `max_agreements` agreements, which bounds the work done.
+    ///
+    /// Returns the number of agreements of the IP.
+    pub fn reject_pending_agreements(
+        ip: &T::AccountId,
+        max_agreements: u32,
+    ) -> Result<u32, DispatchError> {
+        ensure!(
+            InfrastructureProviders::<T>::contains_key(ip),
+            Error::<T>::IPNotFound
+        );
+        let agreements = IPAgreementCount::<T>::get(ip);
+        ensure!(agreements <= max_agreements, Error::<T>::TooManyAgreements);
+
+        let agreement_ids = IPAgreements::<T>::iter_key_prefix(ip).collect::<Vec<_>>();
+        for agreement_id in agreement_ids {
+            if let Some(agreement) = Self::get_agreement(agreement_id).filter(|agreement| {
+                agreement.status == AgreementStatus::ConsumerRequest
+                    || agreement.status == AgreementStatus::IPProposedPaymentPlan
+            }) {
+                Self::reject_agreement(agreement_id, agreement)?;
+            }
+        }
+        Ok(agreements)
+    }
+
+    /// Unregisters the IP. The offers of the IP are removed and its deposit is released.
+    pub fn unregister_ip(ip: &T::AccountId, ip_details: &mut IPDetails<T>) -> DispatchResult {
+        ip_details.status = IPStatus::Unregistered;
+
+        // Offers of an unregistered IP cannot be requested anymore
+        for offer_id in IPOffers::<T>::take(ip) {
+            Offers::<T>::remove(offer_id);
+        }
+
+        T::Currency::release(
+            &HoldReason::IPInitialDeposit.into(),
+            ip,
+            ip_details.deposit,
+            Exact,
+        )?;
+        Ok(())
+    }
+
+    /// Unregisters a draining IP once all its agreements are closed, i.e. once no agreements of
+    /// the IP are left, including the completed ones waiting for feedback and the disputed ones.
+    pub fn unregister_if_drained(ip: &T::AccountId) -> DispatchResult {
+        let unregistered = InfrastructureProviders::<T>::try_mutate(
+            ip,
+            |ip_details| -> Result<_, DispatchError> {
+                let Some(ip_details) = ip_details
+                    .as_mut()
+                    .filter(|x| x.status == IPStatus::Draining)
+                else {
+                    return Ok(false);
+                };
+
+                let drained = IPAgreementCount::<T>::get(ip) == 0;
+                if drained {
+                    Self::unregister_ip(ip, ip_details)?;
+                }
+                Ok(drained)
+            },
+        )?;
+
+        if unregistered {
+            Self::deposit_event(Event::IPUnregistered { ip: ip.clone() });
+        }
+        Ok(())
+    }
+
+    /// Checks if the IP is draining, i.e. it doesn't take on new agreements or renewals.
+    pub fn is_ip_draining(ip: &T::AccountId) -> bool {
+        Self::get_ip(ip).is_some_and(|ip_details| ip_details.status == IPStatus::Draining)
+    }
+
+    /// Rejects an agreement at negotiation stage. The consumer deposits are released and the
+    /// agreement is deleted.
+    pub fn reject_agreement(
+        agreement_id: T::AgreementId,
+        mut agreement: AgreementDetails<T>,
+    ) -> DispatchResult {
+        let consumer_total_deposit = agreement.release_consumer_deposits()?;
+        Self::delete_agreement(agreement_id)?;
+
+        Self::deposit_event(Event::IPRejectedAgreement {
+            agreement_id,
+            ip: agreement.ip,
+            consumer: agreement.consumer,
+            consumer_total_deposit,
+        });
+        Ok(())
+    }
+
+    /// Reports a failed integrity check of a file of the agreement. If the failed check breaches
+    /// the SLA terms of an active or suspended agreement, a share of the current installment is
+    /// refunded to the consumer. Once the breaches are past the termination threshold, the
+    /// agreement is terminated and the held funds are returned to the consumer instead.
+    #[transactional]
+    pub fn report_failed_integrity_check(agreement_id: T::AgreementId) -> DispatchResult {
+        let Some(mut agreement) = Agreements::<T>::get(agreement_id) else {
+            return Ok(());
+        };
+        let Some(sla) = agreement.sla.filter(|_| {
+            agreement.status == AgreementStatus::Active
+                || agreement.status == AgreementStatus::Suspended
+        }) else {
+            return Ok(());
+        };
+
+        let block_number = Self::current_block_number();
+        if !agreement.record_failed_check(block_number) {
+            Agreements::<T>::insert(agreement_id, agreement);
+            return Ok(());
+        }
+
+        if agreement.sla_record.breaches > sla.termination_threshold {
+            let refunded = agreement
+                .release_consumer_deposits()?
+                .saturating_add(agreement.release_installments()?);
+            Self::delete_agreement(agreement_id)?;
+            Self::allow_consumer_rating(agreement_id, &agreement);
+            Self::update_reliability(&agreement.ip, |record| {
+                record.sla_terminations.saturating_inc()
+            });
+
+            Self::deposit_event(Event::AgreementTerminatedSlaBreach {
+                agreement_id,
+                ip: agreement.ip,
+                consumer: agreement.consumer,
+                refunded,
+            });
+        } else {
+            let refunded =
+                agreement.refund_installment_share(sla.refund_per_breach, block_number)?;
+            let breaches = agreement.sla_record.breaches;
+            let consumer = agreement.consumer.clone();
+            Agreements::<T>::insert(agreement_id, agreement);
+
+            Self::deposit_event(Event::SlaBreached {
+                agreement_id,
+                consumer,
+                breaches,
+                refunded,
+            });
+        }
+        Ok(())
+    }
+
+    /// Records the result of a data integrity check of a file of the agreement in the reliability
+    /// record of the IP. A failed check is also reported against the SLA terms of the agreement.
+    /// A failure to report the check is logged, so the result is recorded regardless.
+    pub fn record_integrity_check(agreement_id: T::AgreementId, passed: bool) {
+        let Some(agreement) = Agreements::<T>::get(agreement_id) else {
+            return;
+        };
+
+        Self::update_reliability(&agreement.ip, |record| {
+            if passed {
+                record.integrity_checks_passed.saturating_inc()
+            } else {
+                record.integrity_checks_failed.saturating_inc()
+            }
+        });
+        if !passed {
+            if let Err(error) = Self::report_failed_integrity_check(agreement_id) {
+                log::warn!(
+                    "pallet-deitos: failed to report the integrity check of agreement {:?}: {:?}",
+                    agreement_id,
+                    error
+                );
+            }
+        }
+    }
+
+    /// Records a conflict in the validation of a file of the agreement in the reliability record
+    /// of the IP.
+    pub fn record_file_conflict(agreement_id: T::AgreementId) {
+        if let Some(agreement) = Agreements::<T>::get(agreement_id) {
+            Self::update_reliability(&agreement.ip, |record| {
+                record.file_conflicts.saturating_inc()
+            });
+        }
+    }
+
+    /// Appends the review to the review log of the IP.
+    ///
+    /// Returns the index of the review.
+    pub fn add_review(ip: &T::AccountId, review: Review<T>) -> u32 {
+        let review_index = ReviewCount::<T>::mutate(ip, |count| {
+            let review_index = *count;
+            count.saturating_inc();
+            review_index
+        });
+        Reviews::<T>::insert(ip, review_index, review);
+        review_index
+    }
+
+    /// Updates the reliability record of the IP, if the IP exists.
+    pub fn update_reliability(ip: &T::AccountId, f: impl FnOnce(&mut ReliabilityRecord)) {
+        InfrastructureProviders::<T>::mutate(ip, |ip_details| {
+            if let Some(ip_details) = ip_details {
+                f(&mut ip_details.reliability);
+            }
+        });
+    }
+
+    /// Deletes an expired agreement and releases the consumer deposits. If the agreement is cleaned
+    /// up by an account, the cleanup reward is paid to it from the consumer service deposit. A
+    /// completed agreement is archived as unrated in the IP details.
+    pub fn expire_agreement(
+        agreement_id: T::AgreementId,
+        mut agreement: AgreementDetails<T>,
+        cleaned_up_by: Option<T::AccountId>,
+    ) -> DispatchResult {
+        let reward = match &cleaned_up_by {
+            Some(who) => agreement
+                .pay_from_consumer_service_deposit(who, T::ExpiredAgreementCleanupReward::get())?,
+            None => BalanceOf::<T>::zero(),
+        };
+        let consumer_total_deposit = agreement.release_consumer_deposits()?;
+        Self::delete_agreement(agreement_id)?;
+
+        if agreement.status == AgreementStatus::Completed {
+            InfrastructureProviders::<T>::mutate(&agreement.ip, |ip_details| {
+                if let Some(ip_details) = ip_details {
+                    ip_details.unrated_agreements.saturating_inc();
+                }
+            });
+        }
+
+        Self::deposit_event(Event::AgreementExpired {
+            agreement_id,
+            consumer_total_deposit,
+            cleaned_up_by,
+            reward,
+        });
+        Ok(())
+    }
+
+    /// Changes the status of the agreement to `Active` and schedules the settlement of its next
+    /// installment. Used both to activate an agreement and to resume a suspended one.
+    pub fn activate_agreement(agreement_id: T::AgreementId, agreement: &mut AgreementDetails<T>) {
+        agreement.update_status(agreement_id, AgreementStatus::Active);
+        Self::schedule_settlement(
+            agreement_id,
+            agreement.next_settlement_block(Self::current_block_number()),
+        );
+    }
+
+    /// Changes the status of the agreement to `Completed` once all the installments and the
+    /// consumer security deposit are transferred to the IP. The IP resources are released, the
+    /// IP can rate the consumer and the expiration of the feedback window is scheduled.
+    pub fn complete_agreement(
+        agreement_id: T::AgreementId,
+        agreement: &mut AgreementDetails<T>,
+    ) -> DispatchResult {
+        agreement.update_status(agreement_id, AgreementStatus::Completed);
+        Self::release_ip_resources(&agreement.ip, agreement.storage, agreement.compute);
+        Self::update_reliability(&agreement.ip, |record| {
+            record.completed_agreements.saturating_inc()
+        });
+        Self::allow_consumer_rating(agreement_id, agreement);
+        RenewalProposals::<T>::remove(agreement_id);
+        AmendmentProposals::<T>::remove(agreement_id);
+        Self::schedule_expiration(
+            agreement_id,
+            agreement.feedback_deadline().saturating_add(One::one()),
+        );
+        Ok(())
+    }
+
+    /// Transfers the due installments of the agreement to the IP. If the agreement is flagged to
+    /// auto-renew and its last installment has started, the payment plan is extended first. If all
+    /// the installments have been transferred, the agreement is completed.
+    ///
+    /// Returns the amount transferred.
+    pub fn withdraw_installments(
+        agreement_id: T::AgreementId,
+        agreement: &mut AgreementDetails<T>,
+        block_number: BlockNumberFor<T>,
+    ) -> Result<BalanceOf<T>, DispatchError> {
+        if let Some(end_block) = agreement.auto_renew_end_block(block_number) {
+            let mut renewed = agreement.clone();
+            match Self::renew_agreement(agreement_id, &mut renewed, &[end_block]) {
+                Ok(()) => *agreement = renewed,
+                // The agreement ends normally if the consumer cannot afford the renewal
+                Err(_) => {
+                    agreement.auto_renew = false;
+                    Self::deposit_event(Event::AutoRenewFailed { agreement_id });
+                }
+            }
+        }
+
+        let transferred = agreement.transfer_installments(block_number)?;
+
+        // Check if all installments have been withdrawn
+        if agreement.consumer_security_deposit_transferred {
+            Self::complete_agreement(agreement_id, agreement)?;
+        }
+
+        Ok(transferred)
+    }
+
+    /// Extends the payment plan of the agreement with the given installments and adjusts the
+    /// consumer security deposit. If auto-pay is enabled, the prepayment of the next installment
+    /// is scheduled.
+    #[transactional]
+    pub fn renew_agreement(
+        agreement_id: T::AgreementId,
+        agreement: &mut AgreementDetails<T>,
+        extension: &[BlockNumberFor<T>],
+    ) -> DispatchResult {
+        // A draining IP doesn't take on renewals
+        ensure!(!Self::is_ip_draining(&agreement.ip), Error::<T>::IPDraining);
+
+        let consumer_security_deposit = agreement.extend_payment_plan(extension)?;
+
+        if agreement.auto_pay {
+            if let Some(block_number) = agreement.next_auto_pay_block() {
+                Self::schedule_auto_pay(agreement_id, block_number);
+            }
+        }
+
+        Self::deposit_event(Event::AgreementRenewed {
+            agreement_id,
+            payment_plan: agreement.payment_plan.clone(),
+            consumer_security_deposit,
+            auto_renew: agreement.auto_renew,
+        });
+        Ok(())
+    }
+
+    /// Amends the agreement with the given proposal. The IP storage allocated to the agreement is
+    /// updated, failing if the IP doesn't have enough free storage, and the agreement terms are
+    /// recalculated. The settlement and auto-pay of the agreement are rescheduled if the payment
+    /// plan changes.
+    #[transactional]
+    pub fn amend_agreement(
+        agreement_id: T::AgreementId,
+        agreement: &mut AgreementDetails<T>,
+        proposal: AmendmentProposal<T>,
+    ) -> DispatchResult {
+        let block_number = Self::current_block_number();
+
+        if let Some(storage) = proposal.storage {
+            InfrastructureProviders::<T>::try_mutate(
+                &agreement.ip,
+                |ip_details| -> DispatchResult {
+                    let ip_details = ip_details.as_mut().ok_or(Error::<T>::IPNotFound)?;
+                    ip_details.release_storage(agreement.storage);
+                    ip_details.allocate_storage(storage)
+                },
+            )?;
+        }
+
+        let settlement_block = agreement.next_settlement_block(block_number);
+        let auto_pay_block = agreement.next_auto_pay_block();
+
+        let consumer_security_deposit = agreement.amend(
+            proposal.storage,
+            proposal
+                .payment_plan_tail
+                .as_ref()
+                .map(|tail| tail.as_slice()),
+            block_number,
+        )?;
+
+        let new_settlement_block = agreement.next_settlement_block(block_number);
+        if new_settlement_block != settlement_block {
+            Self::schedule_settlement(agreement_id, new_settlement_block);
+        }
+
+        let new_auto_pay_block = agreement.next_auto_pay_block();
+        if agreement.auto_pay && new_auto_pay_block != auto_pay_block {
+            match new_auto_pay_block {
+                Some(new_auto_pay_block) => {
+                    Self::schedule_auto_pay(agreement_id, new_auto_pay_block)
+                }
+                None => Self::unschedule_auto_pay(agreement_id),
+            }
+        }
+
+        Self::deposit_event(Event::AgreementAmended {
+            agreement_id,
+            storage: agreement.storage,
+            payment_plan: agreement.payment_plan.clone(),
+            consumer_security_deposit,
+        });
+        Ok(())
+    }
+
+    /// Adds the agreement to the settlement queue at the given block, replacing its previous
+    /// settlement if any. If the queue of the block is full, the agreement is added to the first
+    /// following block with room in its queue.
+    pub fn schedule_settlement(agreement_id: T::AgreementId, mut block_number: BlockNumberFor<T>) {
+        Self::unschedule_settlement(agreement_id);
+        while SettlementQueue::<T>::try_mutate(block_number, |queue| queue.try_push(agreement_id))
+            .is_err()
+        {
+            block_number = block_number.saturating_add(One::one());
+        }
+        SettlementBlocks::<T>::insert(agreement_id, block_number);
+    }
+
+    /// Removes the agreement from the block of the settlement queue where it is scheduled.
+    pub fn unschedule_settlement(agreement_id: T::AgreementId) {
+        if let Some(block_number) = SettlementBlocks::<T>::take(agreement_id) {
+            SettlementQueue::<T>::mutate_exists(block_number, |queue| {
+                Self::remove_from_queue(queue, agreement_id)
+            });
+        }
+    }
+
+    /// Adds the agreement to the auto-pay queue at the given block, or at the next block if the
+    /// given one is not in the future, replacing its previous prepayment if any. If the queue of
+    /// the block is full, the agreement is added to the first following block with room in its
+    /// queue.
+    pub fn schedule_auto_pay(agreement_id: T::AgreementId, block_number: BlockNumberFor<T>) {
+        Self::unschedule_auto_pay(agreement_id);
+        let mut block_number =
+            block_number.max(Self::current_block_number().saturating_add(One::one()));
+        while AutoPayQueue::<T>::try_mutate(block_number, |queue| queue.try_push(agreement_id))
+            .is_err()
+        {
+            block_number = block_number.saturating_add(One::one());
+        }
+        AutoPayBlocks::<T>::insert(agreement_id, block_number);
+    }
+
+    /// Removes the agreement from the block of the auto-pay queue where it is scheduled.
+    pub fn unschedule_auto_pay(agreement_id: T::AgreementId) {
+        if let Some(block_number) = AutoPayBlocks::<T>::take(agreement_id) {
+            AutoPayQueue::<T>::mutate_exists(block_number, |queue| {
+                Self::remove_from_queue(queue, agreement_id)
+            });
+        }
+    }
+
+    /// Removes the agreement from a queue, removing the queue if it becomes empty.
+    fn remove_from_queue(queue: &mut Option<SettlementsVec<T>>, agreement_id: T::AgreementId) {
+        if let Some(agreements) = queue {
+            agreements.retain(|id| *id != agreement_id);
+            if agreements.is_empty() {
+                *queue = None;
+            }
+        }
+    }
+
+    /// Adds the agreement to the expiration queue at the given block, or at the current
+    /// block if the given one is in the past. If the queue of the block is full, the agreement is
+    /// added to the first following block with room in its queue.
+    pub fn schedule_expiration(agreement_id: T::AgreementId, block_number: BlockNumberFor<T>) {
+        let mut block_number = block_number.max(Self::current_block_number());
+        while ExpirationQueue::<T>::try_mutate(block_number, |queue| queue.try_push(agreement_id))
+            .is_err()
+        {
+            block_number = block_number.saturating_add(One::one());
+        }
+    }
+
+    /// Schedules the bidding of an open request to be closed at the given block. If the block is
+    /// full, the request is added to the first following block with room in its queue.
+    pub fn schedule_bidding_close(request_id: T::AgreementId, block_number: BlockNumberFor<T>) {
+        let mut block_number = block_number.max(Self::current_block_number());
+        while BiddingQueue::<T>::try_mutate(block_number, |queue| queue.try_push(request_id))
+            .is_err()
+        {
+            block_number = block_number.saturating_add(One::one());
+        }
+    }
+
+    /// Processes the auto-pay queue and then the settlement queue up to the given block, so
+    /// installments due at the block are prepaid before the agreements are settled. The expired
+    /// agreement requests are deleted afterwards, and the open requests whose bidding has ended
+    /// are awarded last.
+    ///
+    /// Returns the weight consumed.
+    pub fn process_scheduled_agreements(block_number: BlockNumberFor<T>, limit: Weight) -> Weight {
+        let mut meter = WeightMeter::with_limit(limit);
+        let cursor_weight = T::DbWeight::get().reads_writes(1, 1);
+
+        if meter.try_consume(cursor_weight).is_ok() {
+            let cursor = Self::process_queue(
+                &mut meter,
+                block_number,
+                AutoPayCursor::<T>::get(),
+                |cursor| AutoPayQueue::<T>::take(cursor),
+                |cursor, queue| AutoPayQueue::<T>::insert(cursor, queue),
+                T::WeightInfo::auto_pay_installment(),
+                Self::auto_pay_installment,
+            );
+            AutoPayCursor::<T>::put(cursor);
+        }
+
+        if meter.try_consume(cursor_weight).is_ok() {
+            let cursor = Self::process_queue(
+                &mut meter,
+                block_number,
+                SettlementCursor::<T>::get(),
+                |cursor| SettlementQueue::<T>::take(cursor),
+                |cursor, queue| SettlementQueue::<T>::insert(cursor, queue),
+                T::WeightInfo::settle_agreement(),
+                Self::settle_scheduled_agreement,
+            );
+            SettlementCursor::<T>::put(cursor);
+        }
+
+        if meter.try_consume(cursor_weight).is_ok() {
+            let cursor = Self::process_queue(
+                &mut meter,
+                block_number,
+                ExpirationCursor::<T>::get(),
+                |cursor| ExpirationQueue::<T>::take(cursor),
+                |cursor, queue| ExpirationQueue::<T>::insert(cursor, queue),
+                T::WeightInfo::expire_agreement(T::IPOffersLimit::get()),
+                Self::expire_scheduled_agreement,
+            );
+            ExpirationCursor::<T>::put(cursor);
+        }
+
+        if meter.try_consume(cursor_weight).is_ok() {
+            let cursor = Self::process_queue(
+                &mut meter,
+                block_number,
+                BiddingCursor::<T>::get(),
+                |cursor| BiddingQueue::<T>::take(cursor),
+                |cursor, queue| BiddingQueue::<T>::insert(cursor, queue),
+                T::WeightInfo::close_bidding(T::MaxBidsPerRequest::get()),
+                Self::close_bidding,
+            );
+            BiddingCursor::<T>::put(cursor);
+        }
+
+        meter.consumed()
+    }
+
+    /// Processes the agreements of a queue up to the given block, starting from the cursor. The
+    /// agreements are processed while there is weight left. The remaining ones are kept in the
+    /// queue and processed in the following blocks.
+    ///
+    /// Returns the new cursor.
+    fn process_queue(
+        meter: &mut WeightMeter,
+        block_number: BlockNumberFor<T>,
+        mut cursor: BlockNumberFor<T>,
+        take: impl Fn(BlockNumberFor<T>) -> SettlementsVec<T>,
+        insert: impl Fn(BlockNumberFor<T>, SettlementsVec<T>),
+        agreement_weight: Weight,
+        process: impl Fn(T::AgreementId, BlockNumberFor<T>) -> DispatchResult,
+    ) -> BlockNumberFor<T> {
+        let step_weight = T::DbWeight::get().reads_writes(1, 1);
+
+        while cursor <= block_number && meter.try_consume(step_weight).is_ok() {
+            let queue = take(cursor);
+
+            let mut processed = 0;
+            for agreement_id in queue.iter() {
+                if meter.try_consume(agreement_weight).is_err() {
+                    break;
+                }
+                if let Err(error) = process(*agreement_id, block_number) {
+                    log::warn!(
+                        "pallet-deitos: failed to process agreement {:?}: {:?}",
+                        agreement_id,
+                        error
+                    );
+                }
+                processed += 1;
+            }
+
+            // Keep the agreements that were not processed for the next blocks
+            if processed < queue.len() {
+                insert(
+                    cursor,
+                    SettlementsVec::<T>::truncate_from(queue.into_iter().skip(processed).collect()),
+                );
+                break;
+            }
+
+            cursor = cursor.saturating_add(One::one());
+        }
+
+        cursor
+    }
+
+    /// Holds the next installment of the agreement. If the installment is overdue, the consumer
+    /// also pays the late fee to the IP. A suspended agreement becomes `Active` again once no
+    /// installment is overdue.
+    ///
+    /// Returns the cost of the installment.
+    #[transactional]
+    pub fn prepay_installment(
+        agreement_id: T::AgreementId,
+        agreement: &mut AgreementDetails<T>,
+        block_number: BlockNumberFor<T>,
+    ) -> Result<BalanceOf<T>, DispatchError> {
+        let overdue = agreement.has_overdue_installments(block_number);
+        let cost = agreement.hold_next_installment()?;
+
+        if overdue {
+            let late_fee = agreement.pay_late_fee(cost)?;
+            if !late_fee.is_zero() {
+                Self::deposit_event(Event::ConsumerPaidLateFee {
+                    agreement_id,
+                    consumer: agreement.consumer.clone(),
+                    late_fee,
+                });
+            }
+        }
+
+        if agreement.status == AgreementStatus::Suspended
+            && !agreement.has_overdue_installments(block_number)
+        {
+            Self::activate_agreement(agreement_id, agreement);
+        }
+        Ok(cost)
+    }
+
+    /// Prepays the next installment of an active or suspended agreement with auto-pay enabled,
+    /// the same way the consumer prepays it, and schedules the prepayment of the following one.
+    /// If the consumer balance is too low, a warning event is emitted and the prepayment is retried at the block
+    /// before the installment is due, or after `AutoPayLeadBlocks` blocks if it is too late.
+    #[transactional]
+    pub fn auto_pay_installment(
+        agreement_id: T::AgreementId,
+        block_number: BlockNumberFor<T>,
+    ) -> DispatchResult {
+        let Some(mut agreement) = Agreements::<T>::get(agreement_id) else {
+            return Ok(());
+        };
+        if !(agreement.status == AgreementStatus::Active
+            || agreement.status == AgreementStatus::Suspended)
+            || !agreement.auto_pay
+        {
+            return Ok(());
+        }
+        let Some(installment) = agreement.next_due_installment() else {
+            return Ok(());
+        };
+
+        // The consumer has prepaid the installment already
+        let auto_pay_block = installment
+            .due_block
+            .saturating_sub(T::AutoPayLeadBlocks::get());
+        if auto_pay_block > block_number {
+            Self::schedule_auto_pay(agreement_id, auto_pay_block);
+            return Ok(());
+        }
+
+        match Self::prepay_installment(agreement_id, &mut agreement, block_number) {
+            Ok(cost) => {
+                if let Some(next_block) = agreement.next_auto_pay_block() {
+                    Self::schedule_auto_pay(agreement_id, next_block);
+                }
+                let consumer = agreement.consumer.clone();
+                Agreements::<T>::insert(agreement_id, agreement);
+
+                Self::deposit_event(Event::ConsumerPrepaidInstallment {
+                    agreement_id,
+                    consumer,
+                    cost,
+                });
+            }
+            Err(_) => {
+                let last_block = installment.due_block.saturating_sub(One::one());
+                let retry_block = if block_number < last_block {
+                    last_block
+                } else {
+                    block_number.saturating_add(T::AutoPayLeadBlocks::get())
+                };
+                Self::schedule_auto_pay(agreement_id, retry_block);
+
+                Self::deposit_event(Event::AutoPayInsufficientBalance {
+                    agreement_id,
+                    consumer: agreement.consumer,
+                    cost: installment.cost,
+                    due_block: installment.due_block,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Transfers the due installments of an active agreement to the IP, the same way the IP
+    /// withdraws them. If all the installments have been transferred, the agreement is completed.
+    /// If an installment is overdue, the agreement is suspended until the consumer pays it.
+    /// Otherwise, the settlement of the next installment is scheduled. Agreements that are not
+    /// active anymore are skipped.
+    #[transactional]
+    pub fn settle_agreement(
+        agreement_id: T::AgreementId,
+        block_number: BlockNumberFor<T>,
+    ) -> DispatchResult {
+        let Some(mut agreement) = Agreements::<T>::get(agreement_id) else {
+            return Ok(());
+        };
+        if agreement.status != AgreementStatus::Active {
+            return Ok(());
+        }
+
+        let transferred = Self::withdraw_installments(agreement_id, &mut agreement, block_number)?;
+        if agreement.status == AgreementStatus::Active {
+            if agreement.has_overdue_installments(block_number) {
+                agreement.update_status(agreement_id, AgreementStatus::Suspended);
+            } else {
+                Self::schedule_settlement(
+                    agreement_id,
+                    agreement.next_settlement_block(block_number),
+                );
+            }
+        }
+
+        let ip = agreement.ip.clone();
+        Agreements::<T>::insert(agreement_id, agreement);
+
+        if !transferred.is_zero() {
+            Self::deposit_event(Event::IPWithdrewInstallments {
+                agreement_id,
+                ip,
+                transferred,
+            });
+        }
+        Ok(())
+    }
+
+    /// Settles an agreement scheduled at the given block. If the settlement fails, it is retried
+    /// at the next block, so the agreement keeps being settled automatically.
+    pub fn settle_scheduled_agreement(
+        agreement_id: T::AgreementId,
+        block_number: BlockNumberFor<T>,
+    ) -> DispatchResult {
+        if let Err(error) = Self::settle_agreement(agreement_id, block_number) {
+            let retry_block = block_number.saturating_add(One::one());
+            Self::schedule_settlement(agreement_id, retry_block);
+            Self::deposit_event(Event::SettlementFailed {
+                agreement_id,
+                error,
+                retry_block,
+            });
+        }
+        Ok(())
+    }
+
+    /// Deletes an agreement scheduled to expire at the given block. Agreements that have been
+    /// accepted, rated or deleted in the meantime are skipped. The IP can no longer rate the
+    /// consumer once the feedback window has closed.
+    #[transactional]
+    pub fn expire_scheduled_agreement(
+        agreement_id: T::AgreementId,
+        block_number: BlockNumberFor<T>,
+    ) -> DispatchResult {
+        if PendingConsumerRatings::<T>::get(agreement_id)
+            .is_some_and(|pending| pending.deadline < block_number)
+        {
+            PendingConsumerRatings::<T>::remove(agreement_id);
+        }
+
+        match Agreements::<T>::get(agreement_id) {
+            Some(agreement) if agreement.is_expired(block_number) => {
+                Self::expire_agreement(agreement_id, agreement, None)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Closes the bidding of an open request. The bids are tried from the lowest cost, the
+    /// earliest bid winning a tie, and the first one that can still be turned into an agreement
+    /// is awarded the request. If no bid qualifies, or the bidding is closed too late for the
+    /// consumer to prepay the first installment, the request is closed without an agreement.
+    /// Requests accepted or cancelled in the meantime are skipped.
+    pub fn close_bidding(
+        request_id: T::AgreementId,
+        block_number: BlockNumberFor<T>,
+    ) -> DispatchResult {
+        let Some(request) = OpenRequests::<T>::get(request_id) else {
+            return Ok(());
+        };
+
+        let mut bids: Vec<_> = Bids::<T>::iter_prefix(request_id).collect();
+        bids.sort_by(|(_, a), (_, b)| a.cost.cmp(&b.cost).then(a.placed_at.cmp(&b.placed_at)));
+        if request.activation_block <= block_number {
+            bids.clear();
+        }
+
+        for (ip, bid) in bids {
+            // The IP may no longer have the capacity or the status for the agreement
+            match Self::award_open_request(request_id, request.clone(), ip.clone(), bid) {
+                Ok(()) => return Ok(()),
+                Err(error) => log::debug!(
+                    "pallet-deitos: bid of {:?} for open request {:?} not awarded: {:?}",
+                    ip,
+                    request_id,
+                    error
+                ),
+            }
+        }
+
+        Self::close_open_request(request_id, &request)?;
+        Self::success_event(Event::OpenRequestClosed {
+            request_id,
+            consumer: request.consumer,
+        })
+    }
+
+    /// Awards an open request to the bid of the IP. The request is closed and the agreement is
+    /// created at the bid prices and activated right away, so it is not scheduled to expire as a
+    /// request. The award fails once the activation block has been reached, as the consumer could
+    /// not prepay the first installment anymore.
+    #[transactional]
+    pub fn award_open_request(
+        request_id: T::AgreementId,
+        request: OpenRequest<T>,
+        ip: T::AccountId,
+        bid: Bid<T>,
+    ) -> DispatchResult {
+        ensure!(
+            request.activation_block > Self::current_block_number(),
+            Error::<T>::AgreementOutdated
+        );
+        Self::close_open_request(request_id, &request)?;
+
+        let (agreement_id, _) = Self::create_agreement(
+            request.consumer.clone(),
+            ip.clone(),
+            request.storage,
+            request.compute,
+            bid.prices,
+            request.activation_block,
+            request.payment_plan,
+            request.sla,
+        )?;
+
+        Agreements::<T>::mutate(agreement_id, |agreement| {
+            agreement
+                .as_mut()
+                .map(|x| Self::activate_agreement(agreement_id, x))
+        });
+
+        Self::success_event(Event::OpenRequestAwarded {
+            request_id,
+            agreement_id,
+            ip,
+            consumer: request.consumer,
+            cost: bid.cost,
+        })
+    }
+
+    /// Removes an open request and its bids, releasing the consumer deposit and the bonds of all
+    /// the bidders.
+    #[transactional]
+    pub fn close_open_request(
+        request_id: T::AgreementId,
+        request: &OpenRequest<T>,
+    ) -> DispatchResult {
+        OpenRequests::<T>::remove(request_id);
+
+        T::Currency::release(
+            &HoldReason::ConsumerServiceDeposit.into(),
+            &request.consumer,
+            request.deposit,
+            Exact,
+        )?;
+
+        for (ip, bid) in Bids::<T>::drain_prefix(request_id) {
+            T::Currency::release(&HoldReason::IPBidBond.into(), &ip, bid.bond, Exact)?;
+        }
+        Ok(())
+    }
+
+    /// Allows the IP of a completed or terminated agreement to rate the consumer until the end of
+    /// the feedback window. A completed agreement is rated within the feedback window of the
+    /// consumer, whose expiration is scheduled already, while the window of a terminated one
+    /// starts at termination and its expiration is scheduled here.
+    pub fn allow_consumer_rating(agreement_id: T::AgreementId, agreement: &AgreementDetails<T>) {
+        let deadline = if agreement.status == AgreementStatus::Completed {
+            agreement.feedback_deadline()
+        } else {
+            let deadline = Self::current_block_number().saturating_add(T::FeedbackPeriod::get());
+            Self::schedule_expiration(agreement_id, deadline.saturating_add(One::one()));
+            deadline
+        };
+
+        PendingConsumerRatings::<T>::insert(
+            agreement_id,
+            PendingConsumerRating {
+                ip: agreement.ip.clone(),
+                consumer: agreement.consumer.clone(),
+                deadline,
+            },
+        );
+    }
+
+    /// Releases the storage and compute resources allocated to an agreement by the IP.
+    pub fn release_ip_resources(
+        ip: &T::AccountId,
+        storage: StorageSizeMB,
+        compute: ComputeResources,
+    ) {
+        InfrastructureProviders::<T>::mutate(ip, |ip_details| {
+            ip_details.as_mut().map(|x| {
+                x.release_storage(storage);
+                x.release_compute(compute);
+            })
+        });
+    }
+
+    /// Returns the storage of the IP that is not allocated to any agreement yet, or `None` if the
+    /// IP is not registered.
+    pub fn ip_free_storage(ip: &T::AccountId) -> Option<StorageSizeMB> {
+        InfrastructureProviders::<T>::get(ip).map(|ip_details| ip_details.free_storage())
+    }
+
     /// Check if the payment plan is valid. The payment plan is valid if the first installment
     /// block is in the future and the installments are strictly increasing.
     pub fn is_valid_payment_plan(
@@ -119,4 +1171,264 @@ impl<T: Config> Pallet<T> {
             .unwrap_or(false)
             && is_strictly_increasing(payment_plan)
     }
+
+    /// Deducts the given share of the IP deposit. If an affected consumer is given, it receives
+    /// `consumer_share` of the deducted amount and the rest goes to the treasury. An active IP
+    /// whose deposit falls below the required deposit goes back to pending.
+    ///
+    /// Returns the amount deducted, the amount paid to the consumer and the amount paid to the
+    /// treasury.
+    #[transactional]
+    pub fn deduct_ip_deposit(
+        ip: &T::AccountId,
+        share: Perbill,
+        consumer_share: Perbill,
+        consumer: Option<&T::AccountId>,
+    ) -> Result<(BalanceOf<T>, BalanceOf<T>, BalanceOf<T>), DispatchError> {
+        let (slashed, consumer_amount, treasury_amount, deactivated) =
+            InfrastructureProviders::<T>::try_mutate(
+                ip,
+                |ip_details| -> Result<_, DispatchError> {
+                    let ip_details = ip_details.as_mut().ok_or(Error::<T>::IPNotFound)?;
+
+                    let slashed = share.mul_floor(ip_details.deposit);
+                    let consumer_amount = consumer
+                        .map(|_| consumer_share.mul_floor(slashed))
+                        .unwrap_or_else(Zero::zero);
+                    let treasury_amount = slashed.saturating_sub(consumer_amount);
+                    if let Some(consumer) = consumer.filter(|_| !consumer_amount.is_zero()) {
+                        T::Currency::transfer_on_hold(
+                            &HoldReason::IPInitialDeposit.into(),
+                            ip,
+                            consumer,
+                            consumer_amount,
+                            Exact,
+                            Free,
+                            Force,
+                        )?;
+                    }
+                    if !treasury_amount.is_zero() {
+                        T::Currency::transfer_on_hold(
+                            &HoldReason::IPInitialDeposit.into(),
+                            ip,
+                            &T::TreasuryAccount::get(),
+                            treasury_amount,
+                            Exact,
+                            Free,
+                            Force,
+                        )?;
+                    }
+                    ip_details.deposit = ip_details.deposit.saturating_sub(slashed);
+
+                    // The IP has to be activated again once its deposit is restored
+                    let deactivated = ip_details.status == IPStatus::Active
+                        && ip_details.deposit < IPDepositAmount::<T>::get();
+                    if deactivated {
+                        ip_details.status = IPStatus::Pending;
+                    }
+                    Ok((slashed, consumer_amount, treasury_amount, deactivated))
+                },
+            )?;
+
+        if deactivated {
+            Self::deposit_event(Event::IPStatusChanged {
+                ip: ip.clone(),
+                status: IPStatus::Pending,
+            });
+        }
+        Ok((slashed, consumer_amount, treasury_amount))
+    }
+}
+
+impl<T: Config> IPPenaltyHandler<T::AccountId, BalanceOf<T>> for Pallet<T> {
+    #[transactional]
+    fn slash_ip(
+        ip: &T::AccountId,
+        share: Perbill,
+        consumer: Option<&T::AccountId>,
+    ) -> Result<BalanceOf<T>, DispatchError> {
+        let (slashed, consumer_amount, treasury_amount) =
+            Self::deduct_ip_deposit(ip, share, T::SlashedDepositConsumerShare::get(), consumer)?;
+
+        Self::deposit_event(Event::IPSlashed {
+            ip: ip.clone(),
+            slashed,
+            consumer: consumer.cloned(),
+            consumer_amount,
+            treasury_amount,
+        });
+        Ok(slashed)
+    }
+}
+
+impl<T: Config> Pallet<T> {
+    /// Returns the summary of all the registered IPs. Used by the runtime API.
+    pub fn ips_info() -> Vec<IPInfo<T::AccountId>> {
+        InfrastructureProviders::<T>::iter()
+            .map(|(ip, ip_details)| IPInfo {
+                ip,
+                status: ip_details.status,
+                total_storage: ip_details.total_storage,
+                free_storage: ip_details.free_storage(),
+                total_compute: ip_details.total_compute,
+                free_compute: ip_details.free_compute(),
+                average_rating: ip_details.rating.average(),
+                weighted_rating: ip_details
+                    .current_reputation()
+                    .average(ip_details.rating.number_of_scores),
+                reputation: ip_details.reputation_score(),
+                disputes: ip_details.disputes,
+                reliability: ip_details.reliability,
+                min_consumer_rating: ip_details.min_consumer_rating,
+                unrated_agreements: ip_details.unrated_agreements,
+            })
+            .collect()
+    }
+
+    /// Returns the summary of the registered IPs whose reliability record meets the filter. Used
+    /// by the runtime API.
+    pub fn reliable_ips_info(filter: &ReliabilityFilter) -> Vec<IPInfo<T::AccountId>> {
+        Self::ips_info()
+            .into_iter()
+            .filter(|ip_info| ip_info.reliability.meets(filter))
+            .collect()
+    }
+
+    /// Returns up to `limit` reviews from the review log of the IP, starting at the given index.
+    /// Used by the runtime API.
+    pub fn ip_reviews(
+        ip: &T::AccountId,
+        start: u32,
+        limit: u32,
+    ) -> Vec<ReviewInfo<T::AccountId, BlockNumberFor<T>, T::AgreementId>> {
+        (start..ReviewCount::<T>::get(ip))
+            .take(limit as usize)
+            .filter_map(|index| {
+                Reviews::<T>::get(ip, index).map(|review| ReviewInfo {
+                    index,
+                    agreement_id: review.agreement_id,
+                    consumer: review.consumer,
+                    score_performance: review.score_performance,
+                    score_stability: review.score_stability,
+                    score_support: review.score_support,
+                    comment: review.comment.into_inner(),
+                    block_number: review.block_number,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns a page of up to `limit` ids of the agreements of the IP, starting after the given
+    /// agreement id. The ids are not sorted, but the pages are stable while the agreements don't
+    /// change. Used by the runtime API.
+    pub fn ip_agreement_ids(
+        ip: &T::AccountId,
+        start_after: Option<T::AgreementId>,
+        limit: u32,
+    ) -> Vec<T::AgreementId> {
+        match start_after {
+            Some(agreement_id) => IPAgreements::<T>::iter_key_prefix_from(
+                ip,
+                IPAgreements::<T>::hashed_key_for(ip, agreement_id),
+            )
+            .take(limit as usize)
+            .collect(),
+            None => IPAgreements::<T>::iter_key_prefix(ip)
+                .take(limit as usize)
+                .collect(),
+        }
+    }
+
+    /// Returns a page of up to `limit` ids of the agreements of the consumer, starting after the
+    /// given agreement id. The ids are not sorted, but the pages are stable while the agreements
+    /// don't change. Used by the runtime API.
+    pub fn consumer_agreement_ids(
+        consumer: &T::AccountId,
+        start_after: Option<T::AgreementId>,
+        limit: u32,
+    ) -> Vec<T::AgreementId> {
+        match start_after {
+            Some(agreement_id) => ConsumerAgreements::<T>::iter_key_prefix_from(
+                consumer,
+                ConsumerAgreements::<T>::hashed_key_for(consumer, agreement_id),
+            )
+            .take(limit as usize)
+            .collect(),
+            None => ConsumerAgreements::<T>::iter_key_prefix(consumer)
+                .take(limit as usize)
+                .collect(),
+        }
+    }
+
+    /// Returns the details of the agreement along with its next due installment and overdue
+    /// state. Used by the runtime API.
+    pub fn agreement_info(
+        agreement_id: T::AgreementId,
+    ) -> Option<AgreementInfo<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>> {
+        let agreement = Agreements::<T>::get(agreement_id)?;
+
+        Some(AgreementInfo {
+            next_installment: agreement.next_due_installment(),
+            overdue: (agreement.status == AgreementStatus::Active
+                || agreement.status == AgreementStatus::Suspended)
+                && agreement.has_overdue_installments(Self::current_block_number()),
+            paid_installments: agreement
+                .payment_history
+                .records
+                .iter()
+                .map(|record| record.amount)
+                .collect(),
+            transferred_installments: agreement.payment_history.next_transfer_installment_index,
+            ip: agreement.ip,
+            consumer: agreement.consumer,
+            status: agreement.status,
+            storage: agreement.storage,
+            compute: agreement.compute,
+            storage_mb_per_block: agreement.prices.storage_mb_per_block,
+            vcore_per_block: agreement.prices.vcore_per_block,
+            ram_mb_per_block: agreement.prices.ram_mb_per_block,
+            activation_block: agreement.activation_block,
+            payment_plan: agreement.payment_plan.into_inner(),
+            consumer_service_deposit: agreement.consumer_service_deposit,
+            consumer_security_deposit: agreement.consumer_security_deposit,
+            consumer_security_deposit_transferred: agreement.consumer_security_deposit_transferred,
+            auto_pay: agreement.auto_pay,
+            auto_renew: agreement.auto_renew,
+            sla: agreement.sla,
+            sla_breaches: agreement.sla_record.breaches,
+        })
+    }
+
+    /// Calculates the costs of a proposed agreement using the current prices. Returns `None` if
+    /// the payment plan is invalid. Used by the runtime API.
+    pub fn quote_agreement(
+        storage: StorageSizeMB,
+        compute: ComputeResources,
+        activation_block: BlockNumberFor<T>,
+        payment_plan: Vec<BlockNumberFor<T>>,
+    ) -> Option<AgreementQuote<BalanceOf<T>>> {
+        let payment_plan = PaymentPlan::<T>::try_from(payment_plan).ok()?;
+        if !Self::is_valid_payment_plan(&payment_plan, activation_block) {
+            return None;
+        }
+
+        let prices = CurrentPrices::<T>::get();
+        let installments: Vec<_> = sp_std::iter::once(&activation_block)
+            .chain(payment_plan.iter())
+            .collect::<Vec<_>>()
+            .windows(2)
+            .map(|window| prices.cost(storage, compute, (*window[1]).saturating_sub(*window[0])))
+            .collect();
+
+        Some(AgreementQuote {
+            total_cost: installments
+                .iter()
+                .fold(Zero::zero(), |total: BalanceOf<T>, cost| {
+                    total.saturating_add(*cost)
+                }),
+            security_deposit: *installments.last()?,
+            service_deposit: Self::consumer_service_deposit_amount(),
+            installments,
+        })
+    }
 }
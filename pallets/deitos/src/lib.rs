@@ -35,17 +35,18 @@ use frame_support::{
                 },
                 Inspect as FunInspect, Mutate as FunMutate,
             },
+            Fortitude::Force,
             Precision::Exact,
+            Restriction::Free,
         },
         Get,
     },
     PalletId,
 };
 use parity_scale_codec::{Decode, Encode, MaxEncodedLen};
-use scale_info::prelude::string::String;
 use sp_runtime::{
     traits::{One, Saturating, StaticLookup, Zero},
-    BoundedVec, SaturatedConversion,
+    BoundedVec, PerThing, Perbill, SaturatedConversion,
 };
 use sp_std::{convert::TryInto, prelude::*};
 
@@ -55,6 +56,7 @@ pub use types::*;
 pub use weights::*;
 
 mod impls;
+pub mod migrations;
 #[cfg(test)]
 mod tests;
 
@@ -71,7 +73,11 @@ pub mod pallet {
 
     use super::*;
 
+    /// The current storage version.
+    const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+
     #[pallet::pallet]
+    #[pallet::storage_version(STORAGE_VERSION)]
     pub struct Pallet<T>(_);
 
     #[pallet::config]
@@ -104,17 +110,109 @@ pub mod pallet {
             + One
             + Zero;
 
+        /// Offer Id type
+        type OfferId: Member
+            + Default
+            + Parameter
+            + Copy
+            + Clone
+            + MaybeSerializeDeserialize
+            + MaxEncodedLen
+            + Saturating
+            + One
+            + Zero;
+
         /// Payment Plan Limit
         #[pallet::constant]
         type PaymentPlanLimit: Get<u32>;
 
-        /// Agreements per IP Limit
+        /// Offers per IP Limit
+        #[pallet::constant]
+        type IPOffersLimit: Get<u32>;
+
+        /// Maximum length of the evidence supporting a dispute
+        #[pallet::constant]
+        type MaxEvidenceLength: Get<u32>;
+
+        /// Maximum length of the comment of a feedback
+        #[pallet::constant]
+        type MaxCommentLength: Get<u32>;
+
+        /// Maximum number of agreements scheduled to be settled, auto-paid or expired at the same
+        /// block
+        #[pallet::constant]
+        type MaxSettlementsPerBlock: Get<u32>;
+
+        /// Number of blocks before an installment is due that auto-pay holds it
+        #[pallet::constant]
+        type AutoPayLeadBlocks: Get<BlockNumberFor<Self>>;
+
+        /// Share of the consumer security deposit paid to the IP as a penalty when the consumer
+        /// terminates an active agreement early
+        #[pallet::constant]
+        type ConsumerTerminationPenalty: Get<Perbill>;
+
+        /// Share of the IP deposit paid to the consumer as a penalty when the IP cancels an
+        /// accepted agreement before its activation
+        #[pallet::constant]
+        type IPCancellationPenalty: Get<Perbill>;
+
+        /// Number of blocks after an installment is due the consumer can still pay it before the
+        /// IP can terminate the agreement
+        #[pallet::constant]
+        type PaymentGracePeriod: Get<BlockNumberFor<Self>>;
+
+        /// Share of the cost of an overdue installment the consumer pays to the IP as a late fee
+        #[pallet::constant]
+        type LateFee: Get<Perbill>;
+
+        /// Number of blocks after the end of an agreement the consumer can leave feedback
+        #[pallet::constant]
+        type FeedbackPeriod: Get<BlockNumberFor<Self>>;
+
+        /// Number of blocks after which the weights of the IP scores decay
+        #[pallet::constant]
+        type ReputationDecayPeriod: Get<BlockNumberFor<Self>>;
+
+        /// Share of the weights of the IP scores retained every decay period
+        #[pallet::constant]
+        type ReputationRetainedWeight: Get<Perbill>;
+
+        /// Weight of the neutral score every IP reputation starts with. The higher the weight, the
+        /// more value the rated agreements need to move the reputation score away from neutral.
+        #[pallet::constant]
+        type ReputationPriorWeight: Get<BalanceOf<Self>>;
+
+        /// Reward paid from the consumer service deposit to the account cleaning up an expired
+        /// agreement
+        #[pallet::constant]
+        type ExpiredAgreementCleanupReward: Get<BalanceOf<Self>>;
+
+        /// Share of the slashed IP deposit paid to the affected consumer. The rest is paid to the
+        /// treasury
+        #[pallet::constant]
+        type SlashedDepositConsumerShare: Get<Perbill>;
+
+        /// Number of blocks the IPs can bid for an open request after it is posted
+        #[pallet::constant]
+        type BiddingPeriod: Get<BlockNumberFor<Self>>;
+
+        /// Bond held from an IP bidding for an open request until the request is closed
         #[pallet::constant]
-        type IPAgreementsLimit: Get<u32>;
+        type BidBond: Get<BalanceOf<Self>>;
 
-        /// Agreements per Consumer Limit
+        /// Maximum number of bids for an open request
         #[pallet::constant]
-        type ConsumerAgreementsLimit: Get<u32>;
+        type MaxBidsPerRequest: Get<u32>;
+
+        /// The account receiving the slashed IP deposits not paid to consumers
+        type TreasuryAccount: Get<Self::AccountId>;
+
+        /// The origin that resolves disputes
+        type DisputeOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+
+        /// The origin that slashes IP deposits
+        type SlashOrigin: EnsureOrigin<Self::RuntimeOrigin>;
 
         /// Pallet ID
         #[pallet::constant]
@@ -132,6 +230,8 @@ pub mod pallet {
         ConsumerSecurityDeposit,
         /// Consumer installment payment
         ConsumerInstallment,
+        /// IP bond for a bid on an open request
+        IPBidBond,
     }
 
     #[pallet::genesis_config]
@@ -143,6 +243,10 @@ pub mod pallet {
         pub consumer_service_deposit: BalanceOf<T>,
         /// The price for storage of 1 MB per block
         pub price_storage_mb_per_block: BalanceOf<T>,
+        /// The price for compute of 1 vCore per block
+        pub price_vcore_per_block: BalanceOf<T>,
+        /// The price for compute of 1 MB of RAM per block
+        pub price_ram_mb_per_block: BalanceOf<T>,
     }
 
     #[pallet::genesis_build]
@@ -152,6 +256,8 @@ pub mod pallet {
             ConsumerServiceDepositAmount::<T>::put(self.consumer_service_deposit);
             CurrentPrices::<T>::put(Prices {
                 storage_mb_per_block: self.price_storage_mb_per_block,
+                vcore_per_block: self.price_vcore_per_block,
+                ram_mb_per_block: self.price_ram_mb_per_block,
             });
         }
     }
@@ -183,12 +289,43 @@ pub mod pallet {
     pub(super) type Agreements<T: Config> =
         StorageMap<_, Blake2_128Concat, T::AgreementId, AgreementDetails<T>>;
 
+    /// IPs` agreements currently existing in the network. This is a mapping from the IP and the
+    /// agreement id to nothing, so that agreements are added and removed in constant time.
+    #[pallet::storage]
+    pub type IPAgreements<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        Blake2_128Concat,
+        T::AgreementId,
+        (),
+        OptionQuery,
+    >;
+
+    /// Number of agreements of every IP
+    #[pallet::storage]
+    #[pallet::getter(fn ip_agreement_count)]
+    pub type IPAgreementCount<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, u32, ValueQuery>;
+
     /// Customers` agreements currently existing in the network. This is a mapping from the customer
-    /// to a vector of agreement ids.
+    /// and the agreement id to nothing, so that agreements are added and removed in constant time.
+    #[pallet::storage]
+    pub(super) type ConsumerAgreements<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        Blake2_128Concat,
+        T::AgreementId,
+        (),
+        OptionQuery,
+    >;
+
+    /// Number of agreements of every customer
     #[pallet::storage]
-    #[pallet::getter(fn get_consumer_agreement)]
-    pub(super) type ConsumerAgreements<T: Config> =
-        StorageMap<_, Blake2_128Concat, T::AccountId, ConsumerAgreementsVec<T>, ValueQuery>;
+    #[pallet::getter(fn consumer_agreement_count)]
+    pub type ConsumerAgreementCount<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, u32, ValueQuery>;
 
     /// Current agreement id. This is used to assign a unique id to each created agreement.
     /// The id is incremented by one for each new agreement.
@@ -196,6 +333,188 @@ pub mod pallet {
     #[pallet::getter(fn current_agreement_id)]
     pub type CurrentAgreementId<T: Config> = StorageValue<_, T::AgreementId, ValueQuery>;
 
+    /// Offers published by IPs. Agreements requested against an offer use its terms.
+    #[pallet::storage]
+    #[pallet::getter(fn get_offer)]
+    pub type Offers<T: Config> = StorageMap<_, Blake2_128Concat, T::OfferId, OfferDetails<T>>;
+
+    /// IPs` offers currently existing in the network. This is a mapping from the IP to a vector of
+    /// offer ids.
+    #[pallet::storage]
+    #[pallet::getter(fn get_ip_offers)]
+    pub type IPOffers<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, IPOffersVec<T>, ValueQuery>;
+
+    /// Current offer id. This is used to assign a unique id to each created offer.
+    /// The id is incremented by one for each new offer.
+    #[pallet::storage]
+    #[pallet::getter(fn current_offer_id)]
+    pub type CurrentOfferId<T: Config> = StorageValue<_, T::OfferId, ValueQuery>;
+
+    /// Consumers` profiles. This is a mapping from the consumer to its profile.
+    #[pallet::storage]
+    #[pallet::getter(fn get_consumer_profile)]
+    pub type ConsumerProfiles<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, ConsumerProfile, ValueQuery>;
+
+    /// Disputes currently open. This is a mapping from the disputed agreement to the dispute.
+    #[pallet::storage]
+    #[pallet::getter(fn get_dispute)]
+    pub type Disputes<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AgreementId, DisputeDetails<T>>;
+
+    /// Consumers that can be rated by IPs. This is a mapping from the completed or terminated
+    /// agreement to the IP, the consumer and the end of the feedback window of the agreement.
+    /// Entries are removed once the window closes.
+    #[pallet::storage]
+    #[pallet::getter(fn get_pending_consumer_rating)]
+    pub type PendingConsumerRatings<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AgreementId, PendingConsumerRating<T>>;
+
+    /// Review log of the IPs. This is a mapping from the IP and the index of the review to the
+    /// review left by the consumer with the feedback.
+    #[pallet::storage]
+    pub type Reviews<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId,
+        Blake2_128Concat,
+        u32,
+        Review<T>,
+        OptionQuery,
+    >;
+
+    /// Number of reviews of every IP. This is also the index of the next review of the IP.
+    #[pallet::storage]
+    #[pallet::getter(fn review_count)]
+    pub type ReviewCount<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, u32, ValueQuery>;
+
+    /// Agreements to be settled automatically. This is a mapping from the block when the next
+    /// installment of the agreements can be transferred to the IPs to a vector of agreement ids.
+    #[pallet::storage]
+    #[pallet::getter(fn get_settlement_queue)]
+    pub type SettlementQueue<T: Config> =
+        StorageMap<_, Twox64Concat, BlockNumberFor<T>, SettlementsVec<T>, ValueQuery>;
+
+    /// The next block of the settlement queue to be processed. Blocks before it have been
+    /// processed already.
+    #[pallet::storage]
+    #[pallet::getter(fn settlement_cursor)]
+    pub type SettlementCursor<T: Config> = StorageValue<_, BlockNumberFor<T>, ValueQuery>;
+
+    /// The block of the settlement queue where each agreement is scheduled. The block may differ
+    /// from the one requested when the queue of that block was full.
+    #[pallet::storage]
+    pub type SettlementBlocks<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AgreementId, BlockNumberFor<T>>;
+
+    /// Agreements whose next installment is to be prepaid automatically. This is a mapping from
+    /// the block when the installment is prepaid to a vector of agreement ids.
+    #[pallet::storage]
+    #[pallet::getter(fn get_auto_pay_queue)]
+    pub type AutoPayQueue<T: Config> =
+        StorageMap<_, Twox64Concat, BlockNumberFor<T>, SettlementsVec<T>, ValueQuery>;
+
+    /// The next block of the auto-pay queue to be processed. Blocks before it have been
+    /// processed already.
+    #[pallet::storage]
+    #[pallet::getter(fn auto_pay_cursor)]
+    pub type AutoPayCursor<T: Config> = StorageValue<_, BlockNumberFor<T>, ValueQuery>;
+
+    /// The block of the auto-pay queue where each agreement is scheduled. The block may differ
+    /// from the one requested when the queue of that block was full.
+    #[pallet::storage]
+    pub type AutoPayBlocks<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AgreementId, BlockNumberFor<T>>;
+
+    /// Agreements indexed by the block when they expire. Requests expire at the block following
+    /// their activation block, and completed agreements at the block following their feedback
+    /// deadline. The agreements still not accepted or rated by then are deleted.
+    #[pallet::storage]
+    #[pallet::getter(fn expiration_queue)]
+    pub type ExpirationQueue<T: Config> =
+        StorageMap<_, Twox64Concat, BlockNumberFor<T>, SettlementsVec<T>, ValueQuery>;
+
+    /// The next block of the expiration queue to be processed. Blocks before it have been
+    /// processed already.
+    #[pallet::storage]
+    #[pallet::getter(fn expiration_cursor)]
+    pub type ExpirationCursor<T: Config> = StorageValue<_, BlockNumberFor<T>, ValueQuery>;
+
+    /// Open requests posted by consumers for the IPs to bid for. Open requests share the id
+    /// sequence of the agreements, so that they can be scheduled in the same kind of queue.
+    #[pallet::storage]
+    #[pallet::getter(fn get_open_request)]
+    pub type OpenRequests<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AgreementId, OpenRequest<T>>;
+
+    /// Bids for the open requests. This is a mapping from the open request and the bidding IP to
+    /// the bid.
+    #[pallet::storage]
+    #[pallet::getter(fn get_bid)]
+    pub type Bids<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        T::AgreementId,
+        Blake2_128Concat,
+        T::AccountId,
+        Bid<T>,
+        OptionQuery,
+    >;
+
+    /// Open requests indexed by the block when their bidding is closed. The lowest qualifying bid
+    /// of the requests still open by then is awarded the agreement.
+    #[pallet::storage]
+    #[pallet::getter(fn bidding_queue)]
+    pub type BiddingQueue<T: Config> =
+        StorageMap<_, Twox64Concat, BlockNumberFor<T>, SettlementsVec<T>, ValueQuery>;
+
+    /// The next block of the bidding queue to be processed. Blocks before it have been processed
+    /// already.
+    #[pallet::storage]
+    #[pallet::getter(fn bidding_cursor)]
+    pub type BiddingCursor<T: Config> = StorageValue<_, BlockNumberFor<T>, ValueQuery>;
+
+    /// Renewals proposed by a party of an agreement and not yet accepted by the other party. This
+    /// is a mapping from the agreement to the proposal.
+    #[pallet::storage]
+    #[pallet::getter(fn get_renewal_proposal)]
+    pub type RenewalProposals<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AgreementId, RenewalProposal<T>>;
+
+    /// Amendments proposed by a party of an agreement and not yet accepted by the other party.
+    /// This is a mapping from the agreement to the proposal.
+    #[pallet::storage]
+    #[pallet::getter(fn get_amendment_proposal)]
+    pub type AmendmentProposals<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AgreementId, AmendmentProposal<T>>;
+
+    /// Progress of the migration to storage version 1, which is spread over several blocks. The
+    /// pallet calls are filtered out by the runtime while the migration is in progress.
+    #[pallet::storage]
+    #[pallet::getter(fn migration_progress)]
+    pub type MigrationProgress<T: Config> =
+        StorageValue<_, migrations::v1::MigrationStage, OptionQuery>;
+
+    #[pallet::hooks]
+    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+        /// Migrates the next storage entries if a multi-block migration is in progress.
+        fn on_initialize(_block_number: BlockNumberFor<T>) -> Weight {
+            migrations::v1::MigrateToV1::<T>::step(migrations::v1::MAX_ENTRIES_PER_BLOCK)
+        }
+
+        /// Prepays the upcoming installments of the agreements with auto-pay and settles the due
+        /// installments of the active agreements with the weight left in the block. Nothing is
+        /// processed until the storage is migrated.
+        fn on_idle(block_number: BlockNumberFor<T>, remaining_weight: Weight) -> Weight {
+            if Self::is_migrating() {
+                return T::DbWeight::get().reads(1);
+            }
+            Self::process_scheduled_agreements(block_number, remaining_weight)
+        }
+    }
+
     #[pallet::event]
     #[pallet::generate_deposit(pub(super) fn deposit_event)]
     pub enum Event<T: Config> {
@@ -205,6 +524,8 @@ pub mod pallet {
             ip: T::AccountId,
             /// The total storage of the IP
             total_storage: StorageSizeMB,
+            /// The total compute resources of the IP
+            total_compute: ComputeResources,
         },
         /// An IP has updated its storage amount
         IPStorageUpdated {
@@ -213,6 +534,13 @@ pub mod pallet {
             /// The new total storage of the IP
             total_storage: StorageSizeMB,
         },
+        /// An IP has updated its compute resources
+        IPComputeUpdated {
+            /// The IP that has updated its compute resources
+            ip: T::AccountId,
+            /// The new total compute resources of the IP
+            total_compute: ComputeResources,
+        },
         /// An IP has updated its status
         IPStatusChanged {
             /// The IP that has updated its status
@@ -230,6 +558,13 @@ pub mod pallet {
             /// The new price for storage per block
             price_storage_per_block: BalanceOf<T>,
         },
+        /// The prices for compute per block have been updated
+        ComputePriceUnitUpdated {
+            /// The new price for 1 vCore per block
+            price_vcore_per_block: BalanceOf<T>,
+            /// The new price for 1 MB of RAM per block
+            price_ram_mb_per_block: BalanceOf<T>,
+        },
         /// An agreement status has changed
         AgreementStatusChanged {
             /// The agreement id
@@ -249,10 +584,14 @@ pub mod pallet {
             consumer_total_deposit: BalanceOf<T>,
             /// The amount of storage covered by the agreement
             storage: StorageSizeMB,
+            /// The compute resources covered by the agreement
+            compute: ComputeResources,
             /// The block number when the rental starts
             activation_block: BlockNumberFor<T>,
             /// The payment plan for the agreement
             payment_plan: PaymentPlan<T>,
+            /// The SLA terms of the agreement, if any
+            sla: Option<SlaTerms>,
         },
         /// A consumer has revoked an agreement
         ConsumerRevokedAgreement {
@@ -265,6 +604,31 @@ pub mod pallet {
             /// The total deposit released
             consumer_total_deposit: BalanceOf<T>,
         },
+        /// An agreement request has expired before being accepted, or a completed agreement has
+        /// expired without feedback, and it has been deleted
+        AgreementExpired {
+            /// The agreement id
+            agreement_id: T::AgreementId,
+            /// The total deposit released to the consumer
+            consumer_total_deposit: BalanceOf<T>,
+            /// The account cleaning up the agreement, if it was not cleaned up automatically
+            cleaned_up_by: Option<T::AccountId>,
+            /// The reward paid to the account cleaning up the agreement
+            reward: BalanceOf<T>,
+        },
+        /// A consumer has terminated an active agreement early
+        ConsumerTerminatedAgreement {
+            /// The agreement id
+            agreement_id: T::AgreementId,
+            /// The IP the agreement is with
+            ip: T::AccountId,
+            /// The consumer terminating the agreement
+            consumer: T::AccountId,
+            /// The amount transferred to the IP, including the penalty
+            transferred: BalanceOf<T>,
+            /// The amount released to the consumer
+            refunded: BalanceOf<T>,
+        },
         /// An IP has accepted an agreement
         IPAcceptedAgreement {
             /// The agreement id
@@ -316,6 +680,39 @@ pub mod pallet {
             /// The total amount withdrawn
             transferred: BalanceOf<T>,
         },
+        /// An IP has rejected an agreement requested by a consumer
+        IPRejectedAgreement {
+            /// The agreement id
+            agreement_id: T::AgreementId,
+            /// The IP rejecting the agreement
+            ip: T::AccountId,
+            /// The consumer of the agreement
+            consumer: T::AccountId,
+            /// The total deposit released to the consumer
+            consumer_total_deposit: BalanceOf<T>,
+        },
+        /// An IP has cancelled an accepted agreement before its activation
+        IPCancelledAgreement {
+            /// The agreement id
+            agreement_id: T::AgreementId,
+            /// The IP cancelling the agreement
+            ip: T::AccountId,
+            /// The consumer of the agreement
+            consumer: T::AccountId,
+            /// The total amount released to the consumer
+            refunded: BalanceOf<T>,
+            /// The penalty paid by the IP to the consumer
+            penalty: BalanceOf<T>,
+        },
+        /// A consumer has paid a late fee for an overdue installment
+        ConsumerPaidLateFee {
+            /// The agreement id
+            agreement_id: T::AgreementId,
+            /// The consumer paying the fee
+            consumer: T::AccountId,
+            /// The late fee transferred to the IP
+            late_fee: BalanceOf<T>,
+        },
         /// An IP has terminated an agreement due to non-payment
         IPTerminatedNonPay {
             /// The agreement id
@@ -339,20 +736,264 @@ pub mod pallet {
             score_stability: Score,
             /// The support score
             score_support: Score,
-            /// The comment of the feedback
-            comment: String,
+            /// Index of the review in the review log of the IP
+            review_index: u32,
+        },
+        /// An IP has published an offer
+        IPOfferCreated {
+            /// The offer id
+            offer_id: T::OfferId,
+            /// The IP publishing the offer
+            ip: T::AccountId,
+            /// The terms of the offer
+            terms: OfferTerms<T>,
+        },
+        /// An IP has updated the terms of an offer
+        IPOfferUpdated {
+            /// The offer id
+            offer_id: T::OfferId,
+            /// The IP the offer belongs to
+            ip: T::AccountId,
+            /// The new terms of the offer
+            terms: OfferTerms<T>,
+        },
+        /// An IP has removed an offer
+        IPOfferRemoved {
+            /// The offer id
+            offer_id: T::OfferId,
+            /// The IP the offer belonged to
+            ip: T::AccountId,
+        },
+        /// A party of an agreement has opened a dispute
+        DisputeOpened {
+            /// The agreement id
+            agreement_id: T::AgreementId,
+            /// The party opening the dispute
+            opened_by: T::AccountId,
+            /// The evidence supporting the dispute
+            evidence: EvidenceHash<T>,
+        },
+        /// A dispute has been resolved
+        DisputeResolved {
+            /// The agreement id
+            agreement_id: T::AgreementId,
+            /// The outcome of the dispute
+            outcome: DisputeOutcome,
+            /// The amount released to the consumer
+            consumer_amount: BalanceOf<T>,
+            /// The amount transferred to the IP
+            ip_amount: BalanceOf<T>,
+        },
+        /// An IP has rated the consumer of an agreement
+        IPRatedConsumer {
+            /// The agreement id
+            agreement_id: T::AgreementId,
+            /// The IP rating the consumer
+            ip: T::AccountId,
+            /// The rated consumer
+            consumer: T::AccountId,
+            /// The score
+            score: Score,
+        },
+        /// An IP has updated the minimum rating consumers must have to request agreements
+        IPMinConsumerRatingUpdated {
+            /// The IP
+            ip: T::AccountId,
+            /// The new minimum consumer rating
+            min_consumer_rating: Option<u32>,
+        },
+        /// A consumer has enabled or disabled auto-pay for an agreement
+        ConsumerAutoPayUpdated {
+            /// The agreement id
+            agreement_id: T::AgreementId,
+            /// The consumer of the agreement
+            consumer: T::AccountId,
+            /// Whether auto-pay is enabled
+            enabled: bool,
+        },
+        /// A party of an agreement has proposed to renew it
+        RenewalProposed {
+            /// The agreement id
+            agreement_id: T::AgreementId,
+            /// The party proposing the renewal
+            proposed_by: T::AccountId,
+            /// The installments appended to the payment plan
+            extension: PaymentPlan<T>,
+            /// Whether the agreement is flagged to auto-renew once accepted
+            auto_renew: bool,
+        },
+        /// An agreement has been renewed
+        AgreementRenewed {
+            /// The agreement id
+            agreement_id: T::AgreementId,
+            /// The extended payment plan
+            payment_plan: PaymentPlan<T>,
+            /// The security deposit held from the consumer
+            consumer_security_deposit: BalanceOf<T>,
+            /// Whether the agreement is flagged to auto-renew
+            auto_renew: bool,
+        },
+        /// A party of an agreement has cancelled its auto-renewal
+        AutoRenewCancelled {
+            /// The agreement id
+            agreement_id: T::AgreementId,
+            /// The party cancelling the auto-renewal
+            cancelled_by: T::AccountId,
+        },
+        /// An agreement could not be renewed automatically, so it ends with its payment plan
+        AutoRenewFailed {
+            /// The agreement id
+            agreement_id: T::AgreementId,
+        },
+        /// An amendment of an agreement has been proposed
+        AmendmentProposed {
+            /// The agreement id
+            agreement_id: T::AgreementId,
+            /// The party proposing the amendment
+            proposed_by: T::AccountId,
+            /// The new storage of the agreement, if it changes
+            storage: Option<StorageSizeMB>,
+            /// The installments replacing the unpaid ones, if they change
+            payment_plan_tail: Option<PaymentPlan<T>>,
+        },
+        /// An amendment of an agreement has been rejected or withdrawn
+        AmendmentRejected {
+            /// The agreement id
+            agreement_id: T::AgreementId,
+            /// The party rejecting the amendment
+            rejected_by: T::AccountId,
+        },
+        /// An agreement has been amended
+        AgreementAmended {
+            /// The agreement id
+            agreement_id: T::AgreementId,
+            /// The storage of the agreement
+            storage: StorageSizeMB,
+            /// The amended payment plan
+            payment_plan: PaymentPlan<T>,
+            /// The security deposit held from the consumer
+            consumer_security_deposit: BalanceOf<T>,
+        },
+        /// The automatic settlement of an agreement failed and has been rescheduled
+        SettlementFailed {
+            /// The agreement id
+            agreement_id: T::AgreementId,
+            /// The error of the settlement
+            error: DispatchError,
+            /// The block number when the settlement is retried
+            retry_block: BlockNumberFor<T>,
+        },
+        /// Auto-pay could not prepay an installment because the consumer balance is too low
+        AutoPayInsufficientBalance {
+            /// The agreement id
+            agreement_id: T::AgreementId,
+            /// The consumer of the agreement
+            consumer: T::AccountId,
+            /// The cost of the installment
+            cost: BalanceOf<T>,
+            /// The block number when the installment is due
+            due_block: BlockNumberFor<T>,
+        },
+        /// The deposit of an IP has been slashed
+        IPSlashed {
+            /// The IP whose deposit has been slashed
+            ip: T::AccountId,
+            /// The total amount slashed
+            slashed: BalanceOf<T>,
+            /// The affected consumer, if any
+            consumer: Option<T::AccountId>,
+            /// The amount paid to the affected consumer
+            consumer_amount: BalanceOf<T>,
+            /// The amount paid to the treasury
+            treasury_amount: BalanceOf<T>,
+        },
+        /// An IP has topped up its deposit
+        IPDepositToppedUp {
+            /// The IP that has topped up its deposit
+            ip: T::AccountId,
+            /// The amount added to the deposit
+            amount: BalanceOf<T>,
+            /// The new deposit of the IP
+            deposit: BalanceOf<T>,
+        },
+        /// A failed integrity check has breached the SLA terms of an agreement
+        SlaBreached {
+            /// The agreement id
+            agreement_id: T::AgreementId,
+            /// The consumer of the agreement
+            consumer: T::AccountId,
+            /// The total number of breaches of the SLA terms
+            breaches: u32,
+            /// The share of the current installment refunded to the consumer
+            refunded: BalanceOf<T>,
+        },
+        /// An agreement has been terminated because its SLA terms were breached too many times
+        AgreementTerminatedSlaBreach {
+            /// The agreement id
+            agreement_id: T::AgreementId,
+            /// The IP the agreement is with
+            ip: T::AccountId,
+            /// The consumer of the agreement
+            consumer: T::AccountId,
+            /// The total amount returned to the consumer
+            refunded: BalanceOf<T>,
+        },
+        /// A consumer has posted an open request for the IPs to bid for
+        OpenRequestCreated {
+            /// The open request id
+            request_id: T::AgreementId,
+            /// The consumer posting the request
+            consumer: T::AccountId,
+            /// The requested storage
+            storage: StorageSizeMB,
+            /// The requested compute resources
+            compute: ComputeResources,
+            /// The activation block of the agreement
+            activation_block: BlockNumberFor<T>,
+            /// The maximum total cost of the agreement
+            max_price: BalanceOf<T>,
+            /// The last block the IPs can bid at
+            bidding_end: BlockNumberFor<T>,
+        },
+        /// An IP has placed or updated a bid for an open request
+        IPPlacedBid {
+            /// The open request id
+            request_id: T::AgreementId,
+            /// The bidding IP
+            ip: T::AccountId,
+            /// The total cost of the agreement at the bid prices
+            cost: BalanceOf<T>,
+        },
+        /// A bid has been awarded an open request and the agreement is active
+        OpenRequestAwarded {
+            /// The open request id
+            request_id: T::AgreementId,
+            /// The id of the agreement created from the winning bid
+            agreement_id: T::AgreementId,
+            /// The winning IP
+            ip: T::AccountId,
+            /// The consumer of the agreement
+            consumer: T::AccountId,
+            /// The total cost of the agreement
+            cost: BalanceOf<T>,
+        },
+        /// An open request has been closed without an agreement, either cancelled by the
+        /// consumer or with no bid qualifying when the bidding ended
+        OpenRequestClosed {
+            /// The open request id
+            request_id: T::AgreementId,
+            /// The consumer of the request
+            consumer: T::AccountId,
         },
     }
 
     /// Errors.
     #[pallet::error]
     pub enum Error<T> {
-        /// IP agreements limit reached
-        IPAgreementsLimit,
-        /// Consumer agreements limit reached
-        ConsumerAgreementsLimit,
         /// Insufficient storage
         InsufficientStorage,
+        /// Insufficient compute resources
+        InsufficientCompute,
         /// Payment plan invalid
         PaymentPlanInvalid,
         /// IP already exists,
@@ -375,17 +1016,75 @@ pub mod pallet {
         NoUnpaidInstallments,
         /// Agreement not found for consumer
         NoAgreementForConsumer,
+        /// Total storage cannot be less than the storage allocated to agreements
+        StorageBelowAllocated,
+        /// Total compute resources cannot be less than the ones allocated to agreements
+        ComputeBelowAllocated,
+        /// IP offers limit reached
+        IPOffersLimit,
+        /// Offer not found
+        OfferNotFound,
+        /// Offer terms are inconsistent
+        OfferInvalid,
+        /// The requested agreement doesn't meet the offer terms
+        OfferTermsNotMet,
+        /// Dispute not found
+        DisputeNotFound,
+        /// The consumer rating is below the minimum required by the IP
+        ConsumerRatingTooLow,
+        /// The minimum consumer rating is above the highest possible score
+        MinConsumerRatingInvalid,
+        /// No consumer to be rated for the agreement
+        ConsumerRatingNotFound,
+        /// No renewal proposed by the other party of the agreement
+        RenewalNotFound,
+        /// The agreement has not expired
+        AgreementNotExpired,
+        /// The feedback deadline of the agreement has passed
+        FeedbackPeriodEnded,
+        /// The grace period of the overdue installment has not expired yet
+        GracePeriodNotExpired,
+        /// No amendment proposed by the other party of the agreement
+        AmendmentNotFound,
+        /// The amendment doesn't change the storage nor the payment plan
+        AmendmentInvalid,
+        /// The IP is draining and doesn't take on new agreements or renewals
+        IPDraining,
+        /// Open request not found
+        OpenRequestNotFound,
+        /// The bidding window of the open request has ended
+        BiddingClosed,
+        /// The activation block of the open request is not after the bidding is closed
+        BiddingPeriodInvalid,
+        /// The total cost of the bid is above the maximum price of the open request
+        BidAboveMaxPrice,
+        /// The IP reputation is below the minimum required by the open request
+        IPReputationTooLow,
+        /// Bid not found
+        BidNotFound,
+        /// The open request has reached the maximum number of bids
+        TooManyBids,
+        /// The minimum IP reputation is above the highest possible score
+        MinIPReputationInvalid,
+        /// The IP deposit is below the required deposit
+        IPDepositInsufficient,
+        /// The IP has more agreements than the given maximum
+        TooManyAgreements,
     }
 
     #[pallet::call]
     impl<T: Config> Pallet<T> {
         /// Register an IP. The IP must not be registered already, or must have been unregistered.
         /// The IP must pay a deposit to register. The deposit is returned when the IP unregisters.
-        /// The IP must also specify the total storage it has.
+        /// The IP must also specify the total storage and compute resources it has.
         /// The IP is registered with status `Pending` and must be activated by the network operator.
         #[pallet::call_index(0)]
         #[pallet::weight(T::WeightInfo::ip_register())]
-        pub fn ip_register(origin: OriginFor<T>, total_storage: StorageSizeMB) -> DispatchResult {
+        pub fn ip_register(
+            origin: OriginFor<T>,
+            total_storage: StorageSizeMB,
+            total_compute: ComputeResources,
+        ) -> DispatchResult {
             let ip = ensure_signed(origin)?;
 
             // Checks that the IP is either not registered or is registered but with Unregistered status
@@ -402,14 +1101,20 @@ pub mod pallet {
                 Self::ip_deposit_amount(),
             )?;
 
-            let ip_details = IPDetails::new(total_storage, Self::ip_deposit_amount());
+            let ip_details =
+                IPDetails::new(total_storage, total_compute, Self::ip_deposit_amount());
             InfrastructureProviders::<T>::insert(&ip, ip_details);
 
-            Self::deposit_event(Event::IPRegistered { ip, total_storage });
+            Self::deposit_event(Event::IPRegistered {
+                ip,
+                total_storage,
+                total_compute,
+            });
             Ok(())
         }
 
         /// Update the status of an IP. Only the network operator can update the status of an IP.
+        /// An IP can only be activated with the full required deposit.
         #[pallet::call_index(1)]
         #[pallet::weight(T::WeightInfo::update_ip_status())]
         pub fn update_ip_status(
@@ -420,17 +1125,24 @@ pub mod pallet {
             ensure_root(origin)?;
             let ip = T::Lookup::lookup(ip)?;
 
-            InfrastructureProviders::<T>::try_mutate(&ip, |ip_details| {
-                ip_details
-                    .as_mut()
-                    .map(|x| x.status = status)
-                    .ok_or(Error::<T>::IPNotFound)
+            InfrastructureProviders::<T>::try_mutate(&ip, |ip_details| -> DispatchResult {
+                let ip_details = ip_details.as_mut().ok_or(Error::<T>::IPNotFound)?;
+
+                // A slashed IP has to top up its deposit before it is activated again
+                ensure!(
+                    status != IPStatus::Active || ip_details.deposit >= Self::ip_deposit_amount(),
+                    Error::<T>::IPDepositInsufficient
+                );
+
+                ip_details.status = status;
+                Ok(())
             })?;
 
             Self::success_event(Event::IPStatusChanged { ip, status })
         }
 
-        /// Update the total storage of an IP.
+        /// Update the total storage of an IP. The total storage cannot be less than the storage
+        /// allocated to the IP's agreements.
         #[pallet::call_index(2)]
         #[pallet::weight(T::WeightInfo::ip_update_storage())]
         pub fn ip_update_storage(
@@ -439,52 +1151,62 @@ pub mod pallet {
         ) -> DispatchResult {
             let ip = ensure_signed(origin)?;
 
-            InfrastructureProviders::<T>::try_mutate(&ip, |ip_details| {
-                ip_details
-                    .as_mut()
-                    .map(|x| x.total_storage = total_storage)
-                    .ok_or(Error::<T>::IPNotFound)
-            })?;
+            InfrastructureProviders::<T>::try_mutate(
+                &ip,
+                |ip_details| -> Result<_, DispatchError> {
+                    let ip_details = ip_details.as_mut().ok_or(Error::<T>::IPNotFound)?;
+
+                    // The IP cannot shrink below the storage it has already promised
+                    ensure!(
+                        total_storage >= ip_details.allocated_storage,
+                        Error::<T>::StorageBelowAllocated
+                    );
+
+                    ip_details.total_storage = total_storage;
+                    Ok(())
+                },
+            )?;
 
             Self::success_event(Event::IPStorageUpdated { ip, total_storage })
         }
 
         /// Unregister an IP. The IP must be registered and must not have any agreements in progress.
         /// The IP gets back the deposit it payed during registration.
+        ///
+        /// `max_agreements` must be at least the number of agreements of the IP, as the ones at
+        /// negotiation stage are rejected. The weight is refunded down to the actual number.
         #[pallet::call_index(3)]
-        #[pallet::weight(T::WeightInfo::ip_unregister())]
-        pub fn ip_unregister(origin: OriginFor<T>) -> DispatchResult {
+        #[pallet::weight(T::WeightInfo::ip_unregister(*max_agreements))]
+        pub fn ip_unregister(
+            origin: OriginFor<T>,
+            max_agreements: u32,
+        ) -> DispatchResultWithPostInfo {
             let ip = ensure_signed(origin)?;
 
+            // Agreements at negotiation stage are rejected
+            let agreements = Self::reject_pending_agreements(&ip, max_agreements)?;
+
             InfrastructureProviders::<T>::try_mutate(
                 &ip,
                 |ip_details| -> Result<_, DispatchError> {
                     let ip_details = ip_details.as_mut().ok_or(Error::<T>::IPNotFound)?;
 
-                    // TODO: Agreements at negotiation stage should not prevent the IP from unregistering.
                     ensure!(
-                        ip_details.agreements.len() == 0,
+                        IPAgreementCount::<T>::get(&ip) == 0,
                         Error::<T>::AgreementInProgress
                     );
 
-                    ip_details.status = IPStatus::Unregistered;
-
-                    T::Currency::release(
-                        &HoldReason::IPInitialDeposit.into(),
-                        &ip,
-                        ip_details.deposit,
-                        Exact,
-                    )?;
-
-                    Ok(())
+                    Self::unregister_ip(&ip, ip_details)
                 },
             )?;
 
-            Self::success_event(Event::IPUnregistered { ip })
+            Self::deposit_event(Event::IPUnregistered { ip });
+            Ok(Some(T::WeightInfo::ip_unregister(agreements)).into())
         }
 
         /// Update the price for storage per block. Only the network operator can update the price.
-        /// This change doesn't affect installments that have already been paid.
+        /// This change doesn't affect existing agreements, which keep the prices locked when they
+        /// were requested.
         #[pallet::call_index(4)]
         #[pallet::weight(T::WeightInfo::update_storage_cost_per_unit())]
         pub fn update_storage_cost_per_unit(
@@ -493,8 +1215,8 @@ pub mod pallet {
         ) -> DispatchResult {
             ensure_root(origin)?;
 
-            CurrentPrices::<T>::put(Prices {
-                storage_mb_per_block: price_storage_per_block,
+            CurrentPrices::<T>::mutate(|prices| {
+                prices.storage_mb_per_block = price_storage_per_block;
             });
 
             Self::success_event(Event::StoragePriceUnitUpdated {
@@ -502,10 +1224,13 @@ pub mod pallet {
             })
         }
 
-        /// Request an agreement with an IP. The IP must be registered and active. The consumer must
+        /// Request an agreement with an IP. The IP must be registered and active and must have
+        /// enough free storage and compute resources, which are allocated to the agreement. The consumer must
         /// pay a deposit to secure the agreement. The deposit is returned if the consumer revokes
         /// the agreement, or is used to pay for the last installment. The consumer must specify the
-        /// amount of storage it needs, the block number when the rental starts and the payment plan.
+        /// amount of storage and compute resources it needs, the block number when the rental starts and the payment plan.
+        /// If the IP requires a minimum consumer rating, the consumer must meet it. The consumer may
+        /// also specify SLA terms, which the IP agrees to by accepting the agreement.
         ///
         /// The payment plan must is a vector of block numbers. Every element represents the
         /// end of an installment. The first installment starts at the activation block. The last element
@@ -517,51 +1242,24 @@ pub mod pallet {
             origin: OriginFor<T>,
             ip: AccountIdLookupOf<T>,
             storage: StorageSizeMB,
+            compute: ComputeResources,
             activation_block: BlockNumberFor<T>,
             payment_plan: PaymentPlan<T>,
+            sla: Option<SlaTerms>,
         ) -> DispatchResult {
             let consumer = ensure_signed(origin)?;
-
-            // Activation block must be in the future
-            ensure!(
-                activation_block > Self::current_block_number(),
-                Error::<T>::AgreementOutdated
-            );
-
-            // Payment plan must be valid
-            ensure!(
-                Self::is_valid_payment_plan(&payment_plan, activation_block),
-                Error::<T>::PaymentPlanInvalid
-            );
-
             let ip = T::Lookup::lookup(ip)?;
-            let ip_details =
-                InfrastructureProviders::<T>::get(&ip).ok_or(Error::<T>::IPNotFound)?;
 
-            // IP is active
-            ensure!(
-                ip_details.status == IPStatus::Active,
-                Error::<T>::IPNotActive
-            );
-
-            // IP has enough storage
-            ensure!(
-                storage > Zero::zero() && storage <= ip_details.total_storage,
-                Error::<T>::InsufficientStorage
-            );
-
-            let mut agreement = AgreementDetails::new_consumer_request(
-                ip.clone(),
+            let (agreement_id, consumer_total_deposit) = Self::request_agreement(
                 consumer.clone(),
+                ip.clone(),
                 storage,
+                compute,
+                CurrentPrices::<T>::get(),
                 activation_block,
                 payment_plan.clone(),
-            );
-
-            let consumer_total_deposit =
-                agreement.hold_consumer_deposits(Self::consumer_service_deposit_amount())?;
-
-            let agreement_id = Self::insert_agreement(agreement)?;
+                sla,
+            )?;
 
             Self::success_event(Event::ConsumerRequestedAgreement {
                 agreement_id,
@@ -569,8 +1267,10 @@ pub mod pallet {
                 consumer,
                 consumer_total_deposit,
                 storage,
+                compute,
                 activation_block,
                 payment_plan,
+                sla,
             })
         }
 
@@ -645,7 +1345,7 @@ pub mod pallet {
                         Error::<T>::AgreementOutdated
                     );
 
-                    agreement.update_status(agreement_id, AgreementStatus::Active);
+                    Self::activate_agreement(agreement_id, agreement);
                     Ok(agreement.consumer.clone())
                 },
             )?;
@@ -733,7 +1433,7 @@ pub mod pallet {
                         let old_deposit = agreement.consumer_security_deposit;
                         let new_deposit = agreement.adjust_consumer_security_deposit()?;
 
-                        agreement.update_status(agreement_id, AgreementStatus::Active);
+                        Self::activate_agreement(agreement_id, agreement);
                         Ok((agreement.ip.clone(), old_deposit, new_deposit))
                     },
                 )?;
@@ -747,8 +1447,13 @@ pub mod pallet {
             })
         }
 
-        /// Prepay an installment. The agreement status must be `Active`. The consumer pays the cost
-        /// of the next unpaid installment. All payments are saved in the agreement's payment history.
+        /// Prepay an installment. The agreement status must be `Active` or `Suspended`. The consumer
+        /// pays the cost of the next unpaid installment. All payments are saved in the agreement's
+        /// payment history. Installments can also be prepaid automatically, see
+        /// `consumer_set_auto_pay`.
+        ///
+        /// If the installment is overdue, the consumer also pays a late fee to the IP. A suspended
+        /// agreement becomes `Active` again once no installment is overdue.
         #[pallet::call_index(10)]
         #[pallet::weight(T::WeightInfo::consumer_prepay_installment())]
         pub fn consumer_prepay_installment(
@@ -770,12 +1475,12 @@ pub mod pallet {
 
                     // Check that the agreement is in progress
                     ensure!(
-                        agreement.status == AgreementStatus::Active,
+                        agreement.status == AgreementStatus::Active
+                            || agreement.status == AgreementStatus::Suspended,
                         Error::<T>::AgreementStatusInvalid
                     );
 
-                    let cost = agreement.hold_next_installment()?;
-                    Ok(cost)
+                    Self::prepay_installment(agreement_id, agreement, Self::current_block_number())
                 },
             )?;
 
@@ -786,10 +1491,12 @@ pub mod pallet {
             })
         }
 
-        /// Withdraw installments. The agreement status must be `Active`. The IP withdraws all complete installments
+        /// Withdraw installments. The agreement status must be `Active` or `Suspended`. The IP withdraws all complete installments
         /// from the agreement. The IP can withdraw installments only if the consumer has prepaid them.
+        /// Due installments are also settled automatically when blocks have weight left.
         ///
-        /// If the agreement is fully paid, the status changes to `Completed`.
+        /// If the agreement is fully paid, the status changes to `Completed`, unless it is flagged to
+        /// auto-renew.
         #[pallet::call_index(11)]
         #[pallet::weight(T::WeightInfo::ip_withdraw_installments())]
         pub fn ip_withdraw_installments(
@@ -808,19 +1515,16 @@ pub mod pallet {
 
                     // Check that the agreement is in progress
                     ensure!(
-                        agreement.status == AgreementStatus::Active,
+                        agreement.status == AgreementStatus::Active
+                            || agreement.status == AgreementStatus::Suspended,
                         Error::<T>::AgreementStatusInvalid
                     );
 
-                    let current_block_number = Self::current_block_number();
-                    let transferred = agreement.transfer_installments(current_block_number)?;
-
-                    // Check if all installments have been withdrawn
-                    if agreement.consumer_security_deposit_transferred {
-                        agreement.update_status(agreement_id, AgreementStatus::Completed);
-                    }
-
-                    Ok(transferred)
+                    Self::withdraw_installments(
+                        agreement_id,
+                        agreement,
+                        Self::current_block_number(),
+                    )
                 },
             )?;
 
@@ -831,8 +1535,10 @@ pub mod pallet {
             })
         }
 
-        /// Terminate an agreement due to non-payment. The agreement status must be `Active`. The IP
-        /// receives all unpaid installments and the consumer deposit. The agreement is deleted.
+        /// Terminate an agreement due to non-payment. The agreement status must be `Active` or
+        /// `Suspended`, and the grace period of the overdue installment must have expired. The IP
+        /// receives all unpaid installments and the consumer deposit. The agreement is deleted and
+        /// the termination is recorded in the consumer profile and the IP reliability record.
         #[pallet::call_index(12)]
         #[pallet::weight(T::WeightInfo::ip_terminate_nonpay())]
         pub fn ip_terminate_nonpay(
@@ -849,11 +1555,19 @@ pub mod pallet {
 
             // Check that the agreement is in progress
             ensure!(
-                agreement.status == AgreementStatus::Active,
+                agreement.status == AgreementStatus::Active
+                    || agreement.status == AgreementStatus::Suspended,
                 Error::<T>::AgreementStatusInvalid
             );
 
+            // Check that the grace period of the overdue installment has expired
             let current_block_number = Self::current_block_number();
+            ensure!(
+                !agreement.has_overdue_installments(current_block_number)
+                    || agreement.grace_period_expired(current_block_number),
+                Error::<T>::GracePeriodNotExpired
+            );
+
             let transferred = agreement
                 .has_overdue_installments(current_block_number)
                 .then(|| -> Result<_, DispatchError> {
@@ -862,6 +1576,14 @@ pub mod pallet {
                     let service_deposit = agreement.transfer_consumer_service_deposit()?;
                     Self::delete_agreement(agreement_id)?;
 
+                    ConsumerProfiles::<T>::mutate(&agreement.consumer, |profile| {
+                        profile.nonpay_terminations.saturating_inc()
+                    });
+                    Self::update_reliability(&ip, |record| {
+                        record.nonpay_terminations.saturating_inc()
+                    });
+                    Self::allow_consumer_rating(agreement_id, &agreement);
+
                     Ok(installments
                         .saturating_add(security_deposit)
                         .saturating_add(service_deposit))
@@ -876,17 +1598,18 @@ pub mod pallet {
         }
 
         /// Submit feedback for an agreement. The agreement status must be `Completed`. The consumer
-        /// submits a score and a comment. The completed agreement is deleted. The consumer service
-        /// deposit is released.
+        /// submits a score and a comment of up to `MaxCommentLength` bytes, which are stored in the
+        /// review log of the IP. The completed agreement is deleted. The consumer service deposit is
+        /// released.
         #[pallet::call_index(13)]
-        #[pallet::weight(T::WeightInfo::consumer_submit_feedback())]
+        #[pallet::weight(T::WeightInfo::consumer_submit_feedback(comment.len() as u32))]
         pub fn consumer_submit_feedback(
             origin: OriginFor<T>,
             agreement_id: T::AgreementId,
             score_performance: Score,
             score_stability: Score,
             score_support: Score,
-            comment: String,
+            comment: Comment<T>,
         ) -> DispatchResult {
             let consumer = ensure_signed(origin)?;
 
@@ -905,13 +1628,37 @@ pub mod pallet {
                 Error::<T>::AgreementStatusInvalid
             );
 
-            //Save the score. The IP must exist.
+            // Check that the feedback deadline has not passed
+            ensure!(
+                !agreement.is_expired(Self::current_block_number()),
+                Error::<T>::FeedbackPeriodEnded
+            );
+
+            // Save the score weighted by the amount paid for the agreement. The IP must exist.
             InfrastructureProviders::<T>::mutate(&agreement.ip, |ip_details| {
-                ip_details
-                    .as_mut()
-                    .map(|x| x.add_score(score_performance, score_stability, score_support))
+                ip_details.as_mut().map(|x| {
+                    x.add_score(
+                        score_performance,
+                        score_stability,
+                        score_support,
+                        agreement.total_paid(),
+                    )
+                })
             });
 
+            let review_index = Self::add_review(
+                &agreement.ip,
+                Review {
+                    agreement_id,
+                    consumer: consumer.clone(),
+                    score_performance,
+                    score_stability,
+                    score_support,
+                    comment,
+                    block_number: Self::current_block_number(),
+                },
+            );
+
             agreement.release_consumer_deposits()?;
 
             Self::delete_agreement(agreement_id)?;
@@ -923,7 +1670,1106 @@ pub mod pallet {
                 score_performance,
                 score_stability,
                 score_support,
-                comment,
+                review_index,
+            })
+        }
+
+        /// Publish an offer. The IP must be registered. Consumers can request agreements against
+        /// the offer at the offer prices, as long as the requests meet the offer terms.
+        #[pallet::call_index(14)]
+        #[pallet::weight(T::WeightInfo::ip_create_offer())]
+        pub fn ip_create_offer(origin: OriginFor<T>, terms: OfferTerms<T>) -> DispatchResult {
+            let ip = ensure_signed(origin)?;
+
+            // Check that the IP is registered
+            ensure!(
+                Self::get_ip(&ip).is_some_and(|x| x.status != IPStatus::Unregistered),
+                Error::<T>::IPNotFound
+            );
+
+            // Check that the terms are consistent
+            ensure!(terms.is_valid(), Error::<T>::OfferInvalid);
+
+            let offer_id = Self::next_offer_id();
+            IPOffers::<T>::try_mutate(&ip, |offers| {
+                offers
+                    .try_push(offer_id)
+                    .map_err(|_| Error::<T>::IPOffersLimit)
+            })?;
+            Offers::<T>::insert(
+                offer_id,
+                OfferDetails {
+                    ip: ip.clone(),
+                    terms: terms.clone(),
+                },
+            );
+
+            Self::success_event(Event::IPOfferCreated {
+                offer_id,
+                ip,
+                terms,
+            })
+        }
+
+        /// Update the terms of an offer. Only the IP that published the offer can update it. The
+        /// agreements already requested against the offer keep their terms.
+        #[pallet::call_index(15)]
+        #[pallet::weight(T::WeightInfo::ip_update_offer())]
+        pub fn ip_update_offer(
+            origin: OriginFor<T>,
+            offer_id: T::OfferId,
+            terms: OfferTerms<T>,
+        ) -> DispatchResult {
+            let ip = ensure_signed(origin)?;
+
+            // Check that the terms are consistent
+            ensure!(terms.is_valid(), Error::<T>::OfferInvalid);
+
+            Offers::<T>::try_mutate(offer_id, |offer| -> DispatchResult {
+                let offer = offer.as_mut().ok_or(Error::<T>::OfferNotFound)?;
+
+                // Check that the transaction was signed by the IP
+                ensure!(offer.ip == ip, Error::<T>::OfferNotFound);
+
+                offer.terms = terms.clone();
+                Ok(())
+            })?;
+
+            Self::success_event(Event::IPOfferUpdated {
+                offer_id,
+                ip,
+                terms,
+            })
+        }
+
+        /// Remove an offer. Only the IP that published the offer can remove it. The agreements
+        /// already requested against the offer are not affected.
+        #[pallet::call_index(16)]
+        #[pallet::weight(T::WeightInfo::ip_remove_offer())]
+        pub fn ip_remove_offer(origin: OriginFor<T>, offer_id: T::OfferId) -> DispatchResult {
+            let ip = ensure_signed(origin)?;
+
+            let offer = Self::get_offer(offer_id).ok_or(Error::<T>::OfferNotFound)?;
+
+            // Check that the transaction was signed by the IP
+            ensure!(offer.ip == ip, Error::<T>::OfferNotFound);
+
+            Offers::<T>::remove(offer_id);
+            IPOffers::<T>::mutate(&ip, |offers| offers.retain(|&x| x != offer_id));
+
+            Self::success_event(Event::IPOfferRemoved { offer_id, ip })
+        }
+
+        /// Request an agreement against an offer. The request must meet the offer terms and the same
+        /// rules as `consumer_request_agreement` apply. The prices of the offer are locked in the
+        /// agreement. If the offer allows it, the agreement is accepted straight away and the status
+        /// changes to `Active` without waiting for the IP.
+        #[pallet::call_index(17)]
+        #[pallet::weight(T::WeightInfo::consumer_request_offer_agreement())]
+        #[frame_support::transactional]
+        pub fn consumer_request_offer_agreement(
+            origin: OriginFor<T>,
+            offer_id: T::OfferId,
+            storage: StorageSizeMB,
+            compute: ComputeResources,
+            activation_block: BlockNumberFor<T>,
+            payment_plan: PaymentPlan<T>,
+            sla: Option<SlaTerms>,
+        ) -> DispatchResult {
+            let consumer = ensure_signed(origin)?;
+
+            let offer = Self::get_offer(offer_id).ok_or(Error::<T>::OfferNotFound)?;
+
+            // Check that the request meets the offer terms
+            ensure!(
+                offer.terms.allows(storage, activation_block, &payment_plan),
+                Error::<T>::OfferTermsNotMet
+            );
+
+            let (agreement_id, consumer_total_deposit) = Self::request_agreement(
+                consumer.clone(),
+                offer.ip.clone(),
+                storage,
+                compute,
+                offer.terms.prices,
+                activation_block,
+                payment_plan.clone(),
+                sla,
+            )?;
+
+            Self::deposit_event(Event::ConsumerRequestedAgreement {
+                agreement_id,
+                ip: offer.ip.clone(),
+                consumer: consumer.clone(),
+                consumer_total_deposit,
+                storage,
+                compute,
+                activation_block,
+                payment_plan,
+                sla,
+            });
+
+            if offer.terms.auto_accept {
+                Agreements::<T>::mutate(agreement_id, |agreement| {
+                    agreement
+                        .as_mut()
+                        .map(|x| Self::activate_agreement(agreement_id, x))
+                });
+
+                Self::deposit_event(Event::IPAcceptedAgreement {
+                    agreement_id,
+                    ip: offer.ip,
+                    consumer,
+                });
+            }
+
+            Ok(())
+        }
+
+        /// Update the total compute resources of an IP. The total compute resources cannot be less
+        /// than the ones allocated to the IP's agreements.
+        #[pallet::call_index(18)]
+        #[pallet::weight(T::WeightInfo::ip_update_compute())]
+        pub fn ip_update_compute(
+            origin: OriginFor<T>,
+            total_compute: ComputeResources,
+        ) -> DispatchResult {
+            let ip = ensure_signed(origin)?;
+
+            InfrastructureProviders::<T>::try_mutate(
+                &ip,
+                |ip_details| -> Result<_, DispatchError> {
+                    let ip_details = ip_details.as_mut().ok_or(Error::<T>::IPNotFound)?;
+
+                    // The IP cannot shrink below the compute resources it has already promised
+                    ensure!(
+                        ip_details.allocated_compute.fits_in(&total_compute),
+                        Error::<T>::ComputeBelowAllocated
+                    );
+
+                    ip_details.total_compute = total_compute;
+                    Ok(())
+                },
+            )?;
+
+            Self::success_event(Event::IPComputeUpdated { ip, total_compute })
+        }
+
+        /// Update the prices for compute per block. Only the network operator can update the prices.
+        /// This change doesn't affect existing agreements, which keep the prices locked when they
+        /// were requested.
+        #[pallet::call_index(19)]
+        #[pallet::weight(T::WeightInfo::update_compute_cost_per_unit())]
+        pub fn update_compute_cost_per_unit(
+            origin: OriginFor<T>,
+            price_vcore_per_block: BalanceOf<T>,
+            price_ram_mb_per_block: BalanceOf<T>,
+        ) -> DispatchResult {
+            ensure_root(origin)?;
+
+            CurrentPrices::<T>::mutate(|prices| {
+                prices.vcore_per_block = price_vcore_per_block;
+                prices.ram_mb_per_block = price_ram_mb_per_block;
+            });
+
+            Self::success_event(Event::ComputePriceUnitUpdated {
+                price_vcore_per_block,
+                price_ram_mb_per_block,
+            })
+        }
+
+        /// Open a dispute for an agreement. The agreement status must be `Active` and only the IP or
+        /// the consumer of the agreement can open a dispute. The status changes to `Disputed`, so
+        /// installments cannot be transferred and the agreement cannot be terminated due to
+        /// non-payment until the dispute is resolved.
+        #[pallet::call_index(20)]
+        #[pallet::weight(T::WeightInfo::open_dispute())]
+        pub fn open_dispute(
+            origin: OriginFor<T>,
+            agreement_id: T::AgreementId,
+            evidence: EvidenceHash<T>,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            Agreements::<T>::try_mutate(agreement_id, |agreement| -> DispatchResult {
+                let agreement = agreement.as_mut().ok_or(Error::<T>::AgreementNotFound)?;
+
+                // Check that the transaction was signed by a party of the agreement
+                let opened_by = agreement.party(&who).ok_or(Error::<T>::AgreementNotFound)?;
+
+                // Check that the agreement is in progress
+                ensure!(
+                    agreement.status == AgreementStatus::Active,
+                    Error::<T>::AgreementStatusInvalid
+                );
+
+                agreement.update_status(agreement_id, AgreementStatus::Disputed);
+                Disputes::<T>::insert(
+                    agreement_id,
+                    DisputeDetails {
+                        opened_by,
+                        evidence: evidence.clone(),
+                        opened_at: Self::current_block_number(),
+                    },
+                );
+                Ok(())
+            })?;
+
+            Self::success_event(Event::DisputeOpened {
+                agreement_id,
+                opened_by: who,
+                evidence,
+            })
+        }
+
+        /// Resolve a dispute. Only the dispute origin can resolve disputes. The funds held for the
+        /// agreement are split: the consumer gets `consumer_share` of them and the IP gets the rest.
+        /// The outcome is recorded in the IP and consumer profiles and the agreement is deleted.
+        #[pallet::call_index(21)]
+        #[pallet::weight(T::WeightInfo::resolve_dispute())]
+        #[frame_support::transactional]
+        pub fn resolve_dispute(
+            origin: OriginFor<T>,
+            agreement_id: T::AgreementId,
+            consumer_share: Perbill,
+        ) -> DispatchResult {
+            T::DisputeOrigin::ensure_origin(origin)?;
+
+            Disputes::<T>::take(agreement_id).ok_or(Error::<T>::DisputeNotFound)?;
+            let mut agreement =
+                Self::get_agreement(agreement_id).ok_or(Error::<T>::AgreementNotFound)?;
+
+            let (consumer_amount, ip_amount) = agreement.split_held_funds(consumer_share)?;
+            Self::delete_agreement(agreement_id)?;
+
+            let outcome = DisputeOutcome::from(consumer_share);
+            InfrastructureProviders::<T>::mutate(&agreement.ip, |ip_details| {
+                ip_details
+                    .as_mut()
+                    .map(|x| x.disputes.add_outcome(outcome, AgreementParty::IP))
+            });
+            ConsumerProfiles::<T>::mutate(&agreement.consumer, |profile| {
+                profile
+                    .disputes
+                    .add_outcome(outcome, AgreementParty::Consumer)
+            });
+
+            Self::success_event(Event::DisputeResolved {
+                agreement_id,
+                outcome,
+                consumer_amount,
+                ip_amount,
+            })
+        }
+
+        /// Rate the consumer of an agreement. The agreement must have been completed or terminated,
+        /// and the feedback window must not have closed. The IP can rate the consumer of every
+        /// agreement only once.
+        #[pallet::call_index(22)]
+        #[pallet::weight(T::WeightInfo::ip_rate_consumer())]
+        pub fn ip_rate_consumer(
+            origin: OriginFor<T>,
+            agreement_id: T::AgreementId,
+            score: Score,
+        ) -> DispatchResult {
+            let ip = ensure_signed(origin)?;
+
+            let pending = PendingConsumerRatings::<T>::get(agreement_id)
+                .ok_or(Error::<T>::ConsumerRatingNotFound)?;
+
+            // Check that the transaction was signed by the IP
+            ensure!(pending.ip == ip, Error::<T>::ConsumerRatingNotFound);
+            ensure!(
+                Self::current_block_number() <= pending.deadline,
+                Error::<T>::FeedbackPeriodEnded
+            );
+
+            PendingConsumerRatings::<T>::remove(agreement_id);
+            ConsumerProfiles::<T>::mutate(&pending.consumer, |profile| profile.add_score(score));
+
+            Self::success_event(Event::IPRatedConsumer {
+                agreement_id,
+                ip,
+                consumer: pending.consumer,
+                score,
+            })
+        }
+
+        /// Set the minimum average rating consumers must have to request agreements with the IP.
+        /// The rating is multiplied by `AverageRating::SCALE`, e.g. `350` means an average score of
+        /// 3.5. Consumers that have not been rated yet can always request agreements. `None`
+        /// removes the requirement.
+        #[pallet::call_index(23)]
+        #[pallet::weight(T::WeightInfo::ip_set_min_consumer_rating())]
+        pub fn ip_set_min_consumer_rating(
+            origin: OriginFor<T>,
+            min_consumer_rating: Option<u32>,
+        ) -> DispatchResult {
+            let ip = ensure_signed(origin)?;
+
+            ensure!(
+                min_consumer_rating
+                    .map(|x| x <= Score::Five as u32 * AverageRating::SCALE)
+                    .unwrap_or(true),
+                Error::<T>::MinConsumerRatingInvalid
+            );
+
+            InfrastructureProviders::<T>::try_mutate(&ip, |ip_details| -> DispatchResult {
+                let ip_details = ip_details.as_mut().ok_or(Error::<T>::IPNotFound)?;
+                ip_details.min_consumer_rating = min_consumer_rating;
+                Ok(())
+            })?;
+
+            Self::success_event(Event::IPMinConsumerRatingUpdated {
+                ip,
+                min_consumer_rating,
+            })
+        }
+
+        /// Enable or disable auto-pay for an agreement. The agreement status must be `Active`.
+        /// With auto-pay enabled, every installment is prepaid `AutoPayLeadBlocks` blocks before
+        /// it is due. If the consumer balance is too low, a warning event is emitted and the
+        /// prepayment is retried at the block before the installment is due.
+        #[pallet::call_index(24)]
+        #[pallet::weight(T::WeightInfo::consumer_set_auto_pay())]
+        pub fn consumer_set_auto_pay(
+            origin: OriginFor<T>,
+            agreement_id: T::AgreementId,
+            enabled: bool,
+        ) -> DispatchResult {
+            let consumer = ensure_signed(origin)?;
+
+            Agreements::<T>::try_mutate(agreement_id, |agreement| -> DispatchResult {
+                let agreement = agreement.as_mut().ok_or(Error::<T>::AgreementNotFound)?;
+
+                // Check that the transaction was signed by the consumer
+                ensure!(
+                    agreement.consumer == consumer,
+                    Error::<T>::AgreementNotFound
+                );
+
+                // Check that the agreement is in progress
+                ensure!(
+                    agreement.status == AgreementStatus::Active,
+                    Error::<T>::AgreementStatusInvalid
+                );
+
+                if enabled && !agreement.auto_pay {
+                    if let Some(block_number) = agreement.next_auto_pay_block() {
+                        Self::schedule_auto_pay(agreement_id, block_number);
+                    }
+                }
+                agreement.auto_pay = enabled;
+                Ok(())
+            })?;
+
+            Self::success_event(Event::ConsumerAutoPayUpdated {
+                agreement_id,
+                consumer,
+                enabled,
+            })
+        }
+
+        /// Propose to renew an agreement. The agreement status must be `Active`. Either party can
+        /// propose to append installments to the payment plan, and optionally to flag the agreement
+        /// to auto-renew. A new proposal replaces the previous one. The renewal takes effect when
+        /// the other party accepts it.
+        #[pallet::call_index(25)]
+        #[pallet::weight(T::WeightInfo::propose_renewal())]
+        pub fn propose_renewal(
+            origin: OriginFor<T>,
+            agreement_id: T::AgreementId,
+            extension: PaymentPlan<T>,
+            auto_renew: bool,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let agreement =
+                Agreements::<T>::get(agreement_id).ok_or(Error::<T>::AgreementNotFound)?;
+
+            // Check that the transaction was signed by a party of the agreement
+            let proposed_by = agreement.party(&who).ok_or(Error::<T>::AgreementNotFound)?;
+
+            // Check that the agreement is in progress
+            ensure!(
+                agreement.status == AgreementStatus::Active,
+                Error::<T>::AgreementStatusInvalid
+            );
+
+            // A draining IP doesn't take on renewals
+            ensure!(!Self::is_ip_draining(&agreement.ip), Error::<T>::IPDraining);
+
+            // The extended payment plan must be valid
+            agreement.extended_payment_plan(&extension)?;
+
+            RenewalProposals::<T>::insert(
+                agreement_id,
+                RenewalProposal {
+                    proposed_by,
+                    extension: extension.clone(),
+                    auto_renew,
+                },
+            );
+
+            Self::success_event(Event::RenewalProposed {
+                agreement_id,
+                proposed_by: who,
+                extension,
+                auto_renew,
+            })
+        }
+
+        /// Accept the renewal of an agreement proposed by the other party. The agreement status
+        /// must be `Active`. The payment plan is extended, the consumer security deposit is
+        /// adjusted to the new last installment and the agreement keeps its id.
+        #[pallet::call_index(26)]
+        #[pallet::weight(T::WeightInfo::accept_renewal())]
+        #[frame_support::transactional]
+        pub fn accept_renewal(
+            origin: OriginFor<T>,
+            agreement_id: T::AgreementId,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let mut agreement =
+                Agreements::<T>::get(agreement_id).ok_or(Error::<T>::AgreementNotFound)?;
+
+            // Check that the transaction was signed by a party of the agreement
+            let party = agreement.party(&who).ok_or(Error::<T>::AgreementNotFound)?;
+
+            // Check that the agreement is in progress
+            ensure!(
+                agreement.status == AgreementStatus::Active,
+                Error::<T>::AgreementStatusInvalid
+            );
+
+            // Check that the other party has proposed the renewal
+            let proposal = RenewalProposals::<T>::get(agreement_id)
+                .filter(|proposal| proposal.proposed_by != party)
+                .ok_or(Error::<T>::RenewalNotFound)?;
+
+            RenewalProposals::<T>::remove(agreement_id);
+            agreement.auto_renew = proposal.auto_renew;
+            Self::renew_agreement(agreement_id, &mut agreement, &proposal.extension)?;
+            Agreements::<T>::insert(agreement_id, agreement);
+
+            Ok(())
+        }
+
+        /// Cancel the auto-renewal of an agreement. The agreement status must be `Active` or
+        /// `Suspended`. Either party can cancel it. The agreement ends with its current payment
+        /// plan.
+        #[pallet::call_index(27)]
+        #[pallet::weight(T::WeightInfo::cancel_auto_renew())]
+        pub fn cancel_auto_renew(
+            origin: OriginFor<T>,
+            agreement_id: T::AgreementId,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            Agreements::<T>::try_mutate(agreement_id, |agreement| -> DispatchResult {
+                let agreement = agreement.as_mut().ok_or(Error::<T>::AgreementNotFound)?;
+
+                // Check that the transaction was signed by a party of the agreement
+                agreement.party(&who).ok_or(Error::<T>::AgreementNotFound)?;
+
+                // Check that the agreement is in progress
+                ensure!(
+                    agreement.status == AgreementStatus::Active
+                        || agreement.status == AgreementStatus::Suspended,
+                    Error::<T>::AgreementStatusInvalid
+                );
+
+                agreement.auto_renew = false;
+                Ok(())
+            })?;
+
+            Self::success_event(Event::AutoRenewCancelled {
+                agreement_id,
+                cancelled_by: who,
+            })
+        }
+
+        /// Propose to amend an agreement. The agreement status must be `Active`. Either party can
+        /// propose a new storage and/or new installments replacing the unpaid ones. A new proposal
+        /// replaces the previous one. The amendment takes effect when the other party accepts it.
+        #[pallet::call_index(28)]
+        #[pallet::weight(T::WeightInfo::propose_amendment())]
+        pub fn propose_amendment(
+            origin: OriginFor<T>,
+            agreement_id: T::AgreementId,
+            storage: Option<StorageSizeMB>,
+            payment_plan_tail: Option<PaymentPlan<T>>,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let agreement =
+                Agreements::<T>::get(agreement_id).ok_or(Error::<T>::AgreementNotFound)?;
+
+            // Check that the transaction was signed by a party of the agreement
+            let proposed_by = agreement.party(&who).ok_or(Error::<T>::AgreementNotFound)?;
+
+            // Check that the agreement is in progress
+            ensure!(
+                agreement.status == AgreementStatus::Active,
+                Error::<T>::AgreementStatusInvalid
+            );
+
+            // The amendment must change something
+            ensure!(
+                storage.is_some() || payment_plan_tail.is_some(),
+                Error::<T>::AmendmentInvalid
+            );
+            ensure!(storage != Some(0), Error::<T>::InsufficientStorage);
+
+            // The amended payment plan must be valid
+            if let Some(tail) = &payment_plan_tail {
+                agreement.amended_payment_plan(tail, Self::current_block_number())?;
+            }
+
+            AmendmentProposals::<T>::insert(
+                agreement_id,
+                AmendmentProposal {
+                    proposed_by,
+                    storage,
+                    payment_plan_tail: payment_plan_tail.clone(),
+                },
+            );
+
+            Self::success_event(Event::AmendmentProposed {
+                agreement_id,
+                proposed_by: who,
+                storage,
+                payment_plan_tail,
+            })
+        }
+
+        /// Accept the amendment of an agreement proposed by the other party. The agreement status
+        /// must be `Active`. The IP storage allocated to the agreement, the prepaid installments
+        /// that have not started yet and the consumer security deposit are recalculated with the
+        /// new terms.
+        #[pallet::call_index(29)]
+        #[pallet::weight(T::WeightInfo::accept_amendment())]
+        #[frame_support::transactional]
+        pub fn accept_amendment(
+            origin: OriginFor<T>,
+            agreement_id: T::AgreementId,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let mut agreement =
+                Agreements::<T>::get(agreement_id).ok_or(Error::<T>::AgreementNotFound)?;
+
+            // Check that the transaction was signed by a party of the agreement
+            let party = agreement.party(&who).ok_or(Error::<T>::AgreementNotFound)?;
+
+            // Check that the agreement is in progress
+            ensure!(
+                agreement.status == AgreementStatus::Active,
+                Error::<T>::AgreementStatusInvalid
+            );
+
+            // Check that the other party has proposed the amendment
+            let proposal = AmendmentProposals::<T>::get(agreement_id)
+                .filter(|proposal| proposal.proposed_by != party)
+                .ok_or(Error::<T>::AmendmentNotFound)?;
+
+            AmendmentProposals::<T>::remove(agreement_id);
+            Self::amend_agreement(agreement_id, &mut agreement, proposal)?;
+            Agreements::<T>::insert(agreement_id, agreement);
+
+            Ok(())
+        }
+
+        /// Reject the amendment of an agreement proposed by the other party, or withdraw the one
+        /// proposed by the caller.
+        #[pallet::call_index(30)]
+        #[pallet::weight(T::WeightInfo::reject_amendment())]
+        pub fn reject_amendment(
+            origin: OriginFor<T>,
+            agreement_id: T::AgreementId,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let agreement =
+                Agreements::<T>::get(agreement_id).ok_or(Error::<T>::AgreementNotFound)?;
+
+            // Check that the transaction was signed by a party of the agreement
+            agreement.party(&who).ok_or(Error::<T>::AgreementNotFound)?;
+
+            AmendmentProposals::<T>::take(agreement_id).ok_or(Error::<T>::AmendmentNotFound)?;
+
+            Self::success_event(Event::AmendmentRejected {
+                agreement_id,
+                rejected_by: who,
+            })
+        }
+
+        /// Terminate an active agreement early. The agreement status must be `Active`. The IP
+        /// receives the installments earned up to the current block and a penalty taken from the
+        /// security deposit. The installments prepaid for future periods, the rest of the security
+        /// deposit and the service deposit are released to the consumer. The agreement is deleted.
+        #[pallet::call_index(31)]
+        #[pallet::weight(T::WeightInfo::consumer_terminate_agreement())]
+        pub fn consumer_terminate_agreement(
+            origin: OriginFor<T>,
+            agreement_id: T::AgreementId,
+        ) -> DispatchResult {
+            let consumer = ensure_signed(origin)?;
+
+            let mut agreement =
+                Self::get_agreement(agreement_id).ok_or(Error::<T>::AgreementNotFound)?;
+
+            // Check that the transaction was signed by the consumer
+            ensure!(
+                agreement.consumer == consumer,
+                Error::<T>::AgreementNotFound
+            );
+
+            // Check that the agreement is in progress
+            ensure!(
+                agreement.status == AgreementStatus::Active,
+                Error::<T>::AgreementStatusInvalid
+            );
+
+            let (transferred, refunded) = agreement.terminate_early(
+                Self::current_block_number(),
+                T::ConsumerTerminationPenalty::get(),
+            )?;
+            Self::delete_agreement(agreement_id)?;
+            Self::allow_consumer_rating(agreement_id, &agreement);
+
+            Self::success_event(Event::ConsumerTerminatedAgreement {
+                agreement_id,
+                ip: agreement.ip,
+                consumer,
+                transferred,
+                refunded,
+            })
+        }
+
+        /// Reject an agreement requested by a consumer. The agreement status must be
+        /// `ConsumerRequest` or `IPProposedPaymentPlan`. The consumer deposits are released and
+        /// the agreement is deleted.
+        #[pallet::call_index(32)]
+        #[pallet::weight(T::WeightInfo::ip_reject_agreement())]
+        pub fn ip_reject_agreement(
+            origin: OriginFor<T>,
+            agreement_id: T::AgreementId,
+        ) -> DispatchResult {
+            let ip = ensure_signed(origin)?;
+
+            let agreement =
+                Self::get_agreement(agreement_id).ok_or(Error::<T>::AgreementNotFound)?;
+
+            // Check that the transaction was signed by the IP
+            ensure!(agreement.ip == ip, Error::<T>::AgreementNotFound);
+
+            // Check that the agreement is not in progress
+            ensure!(
+                agreement.status == AgreementStatus::ConsumerRequest
+                    || agreement.status == AgreementStatus::IPProposedPaymentPlan,
+                Error::<T>::AgreementInProgress
+            );
+
+            Self::reject_agreement(agreement_id, agreement)
+        }
+
+        /// Cancel an accepted agreement before its activation block. The agreement status must be
+        /// `Active`. The consumer deposits and prepaid installments are released, and a penalty
+        /// taken from the IP deposit is paid to the consumer. An active IP whose deposit falls
+        /// below the required deposit goes back to pending. The agreement is deleted.
+        #[pallet::call_index(33)]
+        #[pallet::weight(T::WeightInfo::ip_cancel_agreement())]
+        pub fn ip_cancel_agreement(
+            origin: OriginFor<T>,
+            agreement_id: T::AgreementId,
+        ) -> DispatchResult {
+            let ip = ensure_signed(origin)?;
+
+            let mut agreement =
+                Self::get_agreement(agreement_id).ok_or(Error::<T>::AgreementNotFound)?;
+
+            // Check that the transaction was signed by the IP
+            ensure!(agreement.ip == ip, Error::<T>::AgreementNotFound);
+
+            // Check that the agreement is accepted and has not started yet
+            ensure!(
+                agreement.status == AgreementStatus::Active,
+                Error::<T>::AgreementStatusInvalid
+            );
+            ensure!(
+                Self::current_block_number() < agreement.activation_block,
+                Error::<T>::AgreementInProgress
+            );
+
+            let refunded = agreement
+                .release_consumer_deposits()?
+                .saturating_add(agreement.release_installments()?);
+
+            // The whole penalty is paid to the consumer
+            let (penalty, _, _) = Self::deduct_ip_deposit(
+                &ip,
+                T::IPCancellationPenalty::get(),
+                Perbill::one(),
+                Some(&agreement.consumer),
+            )?;
+
+            Self::delete_agreement(agreement_id)?;
+
+            Self::success_event(Event::IPCancelledAgreement {
+                agreement_id,
+                ip,
+                consumer: agreement.consumer,
+                refunded,
+                penalty,
+            })
+        }
+
+        /// Clean up an agreement that has expired, i.e. a request whose activation block has
+        /// passed before being accepted, or a completed agreement whose feedback deadline has
+        /// passed without feedback. Anyone can clean it up and receive a reward from the consumer
+        /// service deposit. The rest of the consumer deposits are released and the agreement is
+        /// deleted. A completed agreement is archived as unrated, leaving the IP rating unchanged.
+        #[pallet::call_index(34)]
+        #[pallet::weight(T::WeightInfo::cleanup_expired_agreement())]
+        pub fn cleanup_expired_agreement(
+            origin: OriginFor<T>,
+            agreement_id: T::AgreementId,
+        ) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+
+            let agreement =
+                Self::get_agreement(agreement_id).ok_or(Error::<T>::AgreementNotFound)?;
+
+            ensure!(
+                agreement.is_expired(Self::current_block_number()),
+                Error::<T>::AgreementNotExpired
+            );
+
+            Self::expire_agreement(agreement_id, agreement, Some(who))
+        }
+
+        /// Slash a share of the deposit of an IP, e.g. for failed data integrity checks or lost
+        /// disputes. If an affected consumer is given, it receives `SlashedDepositConsumerShare`
+        /// of the slashed amount and the rest goes to the treasury. An active IP whose deposit
+        /// falls below the required deposit goes back to pending.
+        #[pallet::call_index(35)]
+        #[pallet::weight(T::WeightInfo::slash_ip_deposit())]
+        pub fn slash_ip_deposit(
+            origin: OriginFor<T>,
+            ip: AccountIdLookupOf<T>,
+            share: Perbill,
+            consumer: Option<AccountIdLookupOf<T>>,
+        ) -> DispatchResult {
+            T::SlashOrigin::ensure_origin(origin)?;
+            let ip = T::Lookup::lookup(ip)?;
+            let consumer = consumer.map(T::Lookup::lookup).transpose()?;
+
+            Self::slash_ip(&ip, share, consumer.as_ref())?;
+            Ok(())
+        }
+
+        /// Start draining an IP that wants to leave. A draining IP doesn't take on new agreements
+        /// or renewals, while its active agreements continue. Agreements at negotiation stage are
+        /// rejected. The IP is unregistered automatically, getting back its deposit, once its last
+        /// agreement is closed, i.e. the completed agreements have been rated or have expired.
+        ///
+        /// `max_agreements` must be at least the number of agreements of the IP, as the ones at
+        /// negotiation stage are rejected. The weight is refunded down to the actual number.
+        #[pallet::call_index(36)]
+        #[pallet::weight(T::WeightInfo::ip_start_draining(*max_agreements))]
+        pub fn ip_start_draining(
+            origin: OriginFor<T>,
+            max_agreements: u32,
+        ) -> DispatchResultWithPostInfo {
+            let ip = ensure_signed(origin)?;
+
+            let ip_details = Self::get_ip(&ip).ok_or(Error::<T>::IPNotFound)?;
+            ensure!(
+                ip_details.status != IPStatus::Unregistered,
+                Error::<T>::IPNotFound
+            );
+            ensure!(
+                ip_details.status != IPStatus::Draining,
+                Error::<T>::IPDraining
+            );
+
+            // Agreements at negotiation stage are rejected
+            let agreements = Self::reject_pending_agreements(&ip, max_agreements)?;
+
+            InfrastructureProviders::<T>::mutate(&ip, |ip_details| {
+                if let Some(ip_details) = ip_details {
+                    ip_details.status = IPStatus::Draining;
+                }
+            });
+            Self::deposit_event(Event::IPStatusChanged {
+                ip: ip.clone(),
+                status: IPStatus::Draining,
+            });
+
+            Self::unregister_if_drained(&ip)?;
+            Ok(Some(T::WeightInfo::ip_start_draining(agreements)).into())
+        }
+
+        /// Post an open request for the IPs to bid for, instead of requesting an agreement from a
+        /// chosen IP. The active IPs can bid for the request during `BiddingPeriod` blocks. The
+        /// bidding is closed at the block following the bidding window, so the activation block
+        /// must be after that block. The consumer service deposit is held while the request is
+        /// open.
+        ///
+        /// The consumer can accept any bid while the request is open. Otherwise, the lowest
+        /// qualifying bid is awarded the agreement when the bidding ends.
+        #[pallet::call_index(37)]
+        #[pallet::weight(T::WeightInfo::consumer_open_request())]
+        pub fn consumer_open_request(
+            origin: OriginFor<T>,
+            storage: StorageSizeMB,
+            compute: ComputeResources,
+            activation_block: BlockNumberFor<T>,
+            payment_plan: PaymentPlan<T>,
+            max_price: BalanceOf<T>,
+            min_ip_reputation: Option<u32>,
+            sla: Option<SlaTerms>,
+        ) -> DispatchResult {
+            let consumer = ensure_signed(origin)?;
+
+            let bidding_end = Self::current_block_number().saturating_add(T::BiddingPeriod::get());
+            let bidding_close = bidding_end.saturating_add(One::one());
+
+            // The agreement starts after the bidding is closed
+            ensure!(
+                activation_block > bidding_close,
+                Error::<T>::BiddingPeriodInvalid
+            );
+
+            ensure!(
+                Self::is_valid_payment_plan(&payment_plan, activation_block),
+                Error::<T>::PaymentPlanInvalid
+            );
+
+            ensure!(storage > Zero::zero(), Error::<T>::InsufficientStorage);
+
+            ensure!(
+                min_ip_reputation
+                    .map(|x| x <= Score::Five as u32 * AverageRating::SCALE)
+                    .unwrap_or(true),
+                Error::<T>::MinIPReputationInvalid
+            );
+
+            let deposit = Self::consumer_service_deposit_amount();
+            T::Currency::hold(
+                &HoldReason::ConsumerServiceDeposit.into(),
+                &consumer,
+                deposit,
+            )?;
+
+            let request_id = Self::next_agreement_id();
+            OpenRequests::<T>::insert(
+                request_id,
+                OpenRequest {
+                    consumer: consumer.clone(),
+                    storage,
+                    compute,
+                    activation_block,
+                    payment_plan,
+                    max_price,
+                    min_ip_reputation,
+                    sla,
+                    bidding_end,
+                    deposit,
+                    bids: 0,
+                },
+            );
+            Self::schedule_bidding_close(request_id, bidding_close);
+
+            Self::success_event(Event::OpenRequestCreated {
+                request_id,
+                consumer,
+                storage,
+                compute,
+                activation_block,
+                max_price,
+                bidding_end,
+            })
+        }
+
+        /// Bid for an open request, or update the prices of the bid already placed. The IP must
+        /// be active, have enough free storage and compute resources for the request and meet the
+        /// minimum reputation required by the consumer, while the consumer must meet the minimum
+        /// rating required by the IP. The total cost at the bid prices cannot exceed the maximum
+        /// price of the request.
+        ///
+        /// `BidBond` is held from the IP for a new bid until the request is closed.
+        #[pallet::call_index(38)]
+        #[pallet::weight(T::WeightInfo::ip_bid())]
+        pub fn ip_bid(
+            origin: OriginFor<T>,
+            request_id: T::AgreementId,
+            prices: Prices<T>,
+        ) -> DispatchResult {
+            let ip = ensure_signed(origin)?;
+
+            let mut request =
+                Self::get_open_request(request_id).ok_or(Error::<T>::OpenRequestNotFound)?;
+
+            let current_block = Self::current_block_number();
+            ensure!(
+                current_block <= request.bidding_end,
+                Error::<T>::BiddingClosed
+            );
+
+            let ip_details = Self::get_ip(&ip).ok_or(Error::<T>::IPNotFound)?;
+
+            ensure!(
+                ip_details.status != IPStatus::Draining,
+                Error::<T>::IPDraining
+            );
+            ensure!(
+                ip_details.status == IPStatus::Active,
+                Error::<T>::IPNotActive
+            );
+
+            ensure!(
+                request.storage <= ip_details.free_storage(),
+                Error::<T>::InsufficientStorage
+            );
+            ensure!(
+                request.compute.fits_in(&ip_details.free_compute()),
+                Error::<T>::InsufficientCompute
+            );
+
+            ensure!(
+                request
+                    .min_ip_reputation
+                    .map(|x| ip_details.reputation_score() >= x)
+                    .unwrap_or(true),
+                Error::<T>::IPReputationTooLow
+            );
+            ensure!(
+                ConsumerProfiles::<T>::get(&request.consumer)
+                    .rating
+                    .meets(ip_details.min_consumer_rating),
+                Error::<T>::ConsumerRatingTooLow
+            );
+
+            let cost = request.cost(&prices);
+            ensure!(cost <= request.max_price, Error::<T>::BidAboveMaxPrice);
+
+            let bond = match Self::get_bid(request_id, &ip) {
+                Some(bid) => bid.bond,
+                None => {
+                    ensure!(
+                        request.bids < T::MaxBidsPerRequest::get(),
+                        Error::<T>::TooManyBids
+                    );
+
+                    let bond = T::BidBond::get();
+                    T::Currency::hold(&HoldReason::IPBidBond.into(), &ip, bond)?;
+
+                    request.bids = request.bids.saturating_add(1);
+                    OpenRequests::<T>::insert(request_id, request);
+                    bond
+                }
+            };
+
+            Bids::<T>::insert(
+                request_id,
+                &ip,
+                Bid {
+                    prices,
+                    cost,
+                    bond,
+                    placed_at: current_block,
+                },
+            );
+
+            Self::success_event(Event::IPPlacedBid {
+                request_id,
+                ip,
+                cost,
+            })
+        }
+
+        /// Accept the bid of an IP for an open request. The agreement is created at the bid
+        /// prices and becomes `Active` right away. The bonds of all the bidders are released.
+        /// The bid cannot be accepted once the activation block of the request has been reached.
+        #[pallet::call_index(39)]
+        #[pallet::weight(T::WeightInfo::consumer_accept_bid())]
+        pub fn consumer_accept_bid(
+            origin: OriginFor<T>,
+            request_id: T::AgreementId,
+            ip: AccountIdLookupOf<T>,
+        ) -> DispatchResult {
+            let consumer = ensure_signed(origin)?;
+            let ip = T::Lookup::lookup(ip)?;
+
+            let request =
+                Self::get_open_request(request_id).ok_or(Error::<T>::OpenRequestNotFound)?;
+            ensure!(
+                request.consumer == consumer,
+                Error::<T>::OpenRequestNotFound
+            );
+
+            let bid = Self::get_bid(request_id, &ip).ok_or(Error::<T>::BidNotFound)?;
+
+            Self::award_open_request(request_id, request, ip, bid)
+        }
+
+        /// Cancel an open request. The consumer service deposit and the bonds of all the bidders
+        /// are released.
+        #[pallet::call_index(40)]
+        #[pallet::weight(T::WeightInfo::consumer_cancel_open_request())]
+        pub fn consumer_cancel_open_request(
+            origin: OriginFor<T>,
+            request_id: T::AgreementId,
+        ) -> DispatchResult {
+            let consumer = ensure_signed(origin)?;
+
+            let request =
+                Self::get_open_request(request_id).ok_or(Error::<T>::OpenRequestNotFound)?;
+            ensure!(
+                request.consumer == consumer,
+                Error::<T>::OpenRequestNotFound
+            );
+
+            Self::close_open_request(request_id, &request)?;
+
+            Self::success_event(Event::OpenRequestClosed {
+                request_id,
+                consumer,
+            })
+        }
+
+        /// Top up the deposit of an IP to the required deposit, e.g. after it has been slashed.
+        /// The missing amount is held from the IP. A slashed IP is pending until the network
+        /// operator activates it again.
+        #[pallet::call_index(41)]
+        #[pallet::weight(T::WeightInfo::ip_top_up_deposit())]
+        pub fn ip_top_up_deposit(origin: OriginFor<T>) -> DispatchResult {
+            let ip = ensure_signed(origin)?;
+
+            let (amount, deposit) = InfrastructureProviders::<T>::try_mutate(
+                &ip,
+                |ip_details| -> Result<_, DispatchError> {
+                    let ip_details = ip_details.as_mut().ok_or(Error::<T>::IPNotFound)?;
+                    ensure!(
+                        ip_details.status != IPStatus::Unregistered,
+                        Error::<T>::IPNotFound
+                    );
+
+                    let amount = Self::ip_deposit_amount().saturating_sub(ip_details.deposit);
+                    T::Currency::hold(&HoldReason::IPInitialDeposit.into(), &ip, amount)?;
+
+                    ip_details.deposit = ip_details.deposit.saturating_add(amount);
+                    Ok((amount, ip_details.deposit))
+                },
+            )?;
+
+            Self::success_event(Event::IPDepositToppedUp {
+                ip,
+                amount,
+                deposit,
             })
         }
     }
@@ -0,0 +1,437 @@
+// Copyright (C) NC2D Labs.
+// This file is part of Deitos Node.
+
+// Deitos Node is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Deitos Node is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Deitos Node.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Storage migrations for the Deitos pallet.
+
+use frame_support::{
+    storage::{unhashed, StoragePrefixedMap},
+    traits::OnRuntimeUpgrade,
+    Blake2_128Concat, ReversibleStorageHasher,
+};
+use frame_system::pallet_prelude::BlockNumberFor;
+#[cfg(feature = "try-runtime")]
+use sp_runtime::TryRuntimeError;
+
+use crate::*;
+
+/// Migration to storage version 1.
+///
+/// The runtime upgrade only migrates the prices and bumps the storage version. The agreements,
+/// the IPs and the consumer agreements are then migrated over the following blocks, at most
+/// `MAX_ENTRIES_PER_BLOCK` entries per block, while the pallet calls are filtered out by the
+/// runtime and the scheduled agreements are not processed. The migration:
+///
+/// - Adds the compute prices, which are zero until the network operator sets them.
+/// - Locks the current prices into the existing agreements.
+/// - Adds no compute resources to the existing IPs and agreements.
+/// - Starts the dispute records of the existing IPs empty.
+/// - Requires no minimum consumer rating from the existing IPs.
+/// - Tracks the storage allocated to the existing agreements of every IP.
+/// - Schedules the settlement of the existing active agreements, starting from the current block.
+/// - Leaves the auto-pay and auto-renew of the existing agreements disabled.
+/// - Schedules the expiration of the existing agreement requests and completed agreements,
+///   expiring the outdated ones at the current block.
+/// - Starts the count of unrated agreements of the existing IPs at zero.
+/// - Starts the reputation of the existing IPs without weight, as the value of the agreements
+///   rated before is unknown.
+/// - Starts the reliability records of the existing IPs empty.
+/// - Moves the agreement ids of the IPs and the consumers from vectors into double maps, counting
+///   the agreements of every IP and consumer.
+/// - Starts processing the bidding of open requests from the current block.
+pub mod v1 {
+    use super::*;
+
+    /// Agreements of the consumers as stored before version 1.
+    #[frame_support::storage_alias]
+    type ConsumerAgreements<T: Config> = StorageMap<
+        Pallet<T>,
+        Blake2_128Concat,
+        AccountIdOf<T>,
+        Vec<<T as Config>::AgreementId>,
+        ValueQuery,
+    >;
+
+    /// Prices as stored before version 1.
+    #[derive(Decode)]
+    pub struct OldPrices<T: Config> {
+        storage_mb_per_block: BalanceOf<T>,
+    }
+
+    /// Agreement details as stored before version 1.
+    #[derive(Decode)]
+    pub struct OldAgreementDetails<T: Config> {
+        ip: AccountIdOf<T>,
+        consumer: AccountIdOf<T>,
+        consumer_service_deposit: BalanceOf<T>,
+        consumer_security_deposit: BalanceOf<T>,
+        consumer_security_deposit_transferred: bool,
+        status: AgreementStatus,
+        storage: StorageSizeMB,
+        activation_block: BlockNumberFor<T>,
+        payment_plan: PaymentPlan<T>,
+        payment_history: PaymentHistory<T>,
+    }
+
+    /// IP details as stored before version 1.
+    #[derive(Decode)]
+    pub struct OldIPDetails<T: Config> {
+        total_storage: StorageSizeMB,
+        status: IPStatus,
+        agreements: Vec<T::AgreementId>,
+        deposit: BalanceOf<T>,
+        rating: Rating,
+    }
+
+    /// Maximum number of storage entries migrated per block.
+    pub const MAX_ENTRIES_PER_BLOCK: u32 = 100;
+
+    /// Raw storage key of the last entry migrated.
+    pub type RawKey = BoundedVec<u8, ConstU32<256>>;
+
+    /// The storage being migrated, with the raw key of the last entry migrated, if any.
+    #[derive(Clone, Encode, Decode, Eq, PartialEq, Debug, MaxEncodedLen, TypeInfo)]
+    pub enum MigrationStage {
+        /// Migrating the agreements
+        Agreements(Option<RawKey>),
+        /// Migrating the IPs. The agreements must have been migrated already, as the storage
+        /// allocated to the IPs is computed from them.
+        InfrastructureProviders(Option<RawKey>),
+        /// Moving the agreement ids of the consumers from vectors into double maps
+        ConsumerAgreements(Option<RawKey>),
+    }
+
+    /// Migrates the pallet storage from version 0 to version 1.
+    pub struct MigrateToV1<T>(sp_std::marker::PhantomData<T>);
+
+    impl<T: Config> MigrateToV1<T> {
+        /// Migrates up to `limit` storage entries, resuming from the stage left by the previous
+        /// block. The migration is finished once no entry is left.
+        ///
+        /// Returns the weight consumed.
+        pub fn step(limit: u32) -> Weight {
+            let Some(mut stage) = MigrationProgress::<T>::get() else {
+                return T::DbWeight::get().reads(1);
+            };
+
+            let mut meter = StepMeter {
+                remaining: limit,
+                reads: 1,
+                writes: 1,
+            };
+            loop {
+                let (next_key, finished) = match &stage {
+                    MigrationStage::Agreements(last_key) => Self::migrate_entries(
+                        &Agreements::<T>::final_prefix(),
+                        last_key,
+                        &mut meter,
+                        Self::migrate_agreement,
+                    ),
+                    MigrationStage::InfrastructureProviders(last_key) => Self::migrate_entries(
+                        &InfrastructureProviders::<T>::final_prefix(),
+                        last_key,
+                        &mut meter,
+                        Self::migrate_ip,
+                    ),
+                    MigrationStage::ConsumerAgreements(last_key) => Self::migrate_entries(
+                        &crate::ConsumerAgreements::<T>::final_prefix(),
+                        last_key,
+                        &mut meter,
+                        Self::migrate_consumer_agreements,
+                    ),
+                };
+
+                stage = match (stage, finished) {
+                    (MigrationStage::Agreements(_), false) => MigrationStage::Agreements(next_key),
+                    (MigrationStage::Agreements(_), true) => {
+                        MigrationStage::InfrastructureProviders(None)
+                    }
+                    (MigrationStage::InfrastructureProviders(_), false) => {
+                        MigrationStage::InfrastructureProviders(next_key)
+                    }
+                    (MigrationStage::InfrastructureProviders(_), true) => {
+                        MigrationStage::ConsumerAgreements(None)
+                    }
+                    (MigrationStage::ConsumerAgreements(_), false) => {
+                        MigrationStage::ConsumerAgreements(next_key)
+                    }
+                    (MigrationStage::ConsumerAgreements(_), true) => {
+                        MigrationProgress::<T>::kill();
+                        log::info!("pallet-deitos: migration to v1 finished");
+                        return meter.weight::<T>();
+                    }
+                };
+
+                if !finished {
+                    MigrationProgress::<T>::put(stage);
+                    return meter.weight::<T>();
+                }
+            }
+        }
+
+        /// Migrates the entries of the storage with the given prefix after the last key, while the
+        /// meter has entries left.
+        ///
+        /// Returns the raw key of the last entry migrated and whether all entries are migrated.
+        fn migrate_entries(
+            prefix: &[u8],
+            last_key: &Option<RawKey>,
+            meter: &mut StepMeter,
+            migrate: impl Fn(&[u8], &[u8], &mut StepMeter),
+        ) -> (Option<RawKey>, bool) {
+            let mut key = last_key
+                .as_ref()
+                .map(|key| key.to_vec())
+                .unwrap_or_else(|| prefix.to_vec());
+
+            while meter.remaining > 0 {
+                meter.remaining -= 1;
+                meter.reads += 1;
+
+                let Some(next_key) =
+                    sp_io::storage::next_key(&key).filter(|k| k.starts_with(prefix))
+                else {
+                    return (None, true);
+                };
+                key = next_key;
+
+                if let Some(value) = unhashed::get_raw(&key) {
+                    migrate(&key[prefix.len()..], &value, meter);
+                    meter.writes += 1;
+                }
+            }
+
+            (RawKey::try_from(key).ok(), false)
+        }
+
+        /// Migrates an agreement, scheduling it in the queue matching its status.
+        fn migrate_agreement(key: &[u8], value: &[u8], meter: &mut StepMeter) {
+            let (Ok(agreement_id), Ok(old)) = (
+                T::AgreementId::decode(&mut Blake2_128Concat::reverse(key)),
+                OldAgreementDetails::<T>::decode(&mut &value[..]),
+            ) else {
+                log::warn!("pallet-deitos: failed to decode agreement {:?}", key);
+                return;
+            };
+
+            let current_block_number = Pallet::<T>::current_block_number();
+            let agreement = AgreementDetails {
+                ip: old.ip,
+                consumer: old.consumer,
+                consumer_service_deposit: old.consumer_service_deposit,
+                consumer_security_deposit: old.consumer_security_deposit,
+                consumer_security_deposit_transferred: old.consumer_security_deposit_transferred,
+                status: old.status,
+                storage: old.storage,
+                compute: Default::default(),
+                prices: CurrentPrices::<T>::get(),
+                activation_block: old.activation_block,
+                payment_plan: old.payment_plan,
+                payment_history: old.payment_history,
+                auto_pay: false,
+                auto_renew: false,
+                sla: None,
+                sla_record: Default::default(),
+            };
+            meter.reads += 1;
+
+            match agreement.status {
+                AgreementStatus::Active => Pallet::<T>::schedule_settlement(
+                    agreement_id,
+                    agreement.next_settlement_block(current_block_number),
+                ),
+                AgreementStatus::ConsumerRequest | AgreementStatus::IPProposedPaymentPlan => {
+                    Pallet::<T>::schedule_expiration(
+                        agreement_id,
+                        agreement.activation_block.saturating_add(One::one()),
+                    )
+                }
+                AgreementStatus::Completed => Pallet::<T>::schedule_expiration(
+                    agreement_id,
+                    agreement.feedback_deadline().saturating_add(One::one()),
+                ),
+                AgreementStatus::Disputed | AgreementStatus::Suspended => {}
+            }
+            meter.reads += 1;
+            meter.writes += 1;
+
+            Agreements::<T>::insert(agreement_id, agreement);
+        }
+
+        /// Migrates an IP, computing the storage allocated to its agreements and indexing them.
+        fn migrate_ip(key: &[u8], value: &[u8], meter: &mut StepMeter) {
+            let (Ok(ip), Ok(old)) = (
+                AccountIdOf::<T>::decode(&mut Blake2_128Concat::reverse(key)),
+                OldIPDetails::<T>::decode(&mut &value[..]),
+            ) else {
+                log::warn!("pallet-deitos: failed to decode IP {:?}", key);
+                return;
+            };
+
+            let allocated_storage = old
+                .agreements
+                .iter()
+                .filter_map(|agreement_id| Agreements::<T>::get(agreement_id))
+                .filter(|agreement| agreement.status != AgreementStatus::Completed)
+                .fold(0, |total: StorageSizeMB, agreement| {
+                    total.saturating_add(agreement.storage)
+                });
+
+            for agreement_id in old.agreements.iter() {
+                IPAgreements::<T>::insert(&ip, agreement_id, ());
+            }
+            IPAgreementCount::<T>::insert(&ip, old.agreements.len() as u32);
+            meter.charge(old.agreements.len() as u64, old.agreements.len() as u64 + 1);
+
+            InfrastructureProviders::<T>::insert(
+                &ip,
+                IPDetails {
+                    total_storage: old.total_storage,
+                    allocated_storage,
+                    total_compute: Default::default(),
+                    allocated_compute: Default::default(),
+                    status: old.status,
+                    deposit: old.deposit,
+                    rating: old.rating,
+                    reputation: Reputation {
+                        last_decay_block: Pallet::<T>::current_block_number(),
+                        ..Default::default()
+                    },
+                    disputes: Default::default(),
+                    reliability: Default::default(),
+                    min_consumer_rating: None,
+                    unrated_agreements: 0,
+                },
+            );
+        }
+
+        /// Moves the agreement ids of a consumer from a vector into the double map. The old and
+        /// the new consumer agreements share the storage prefix, so the entries of the double map
+        /// are skipped.
+        fn migrate_consumer_agreements(key: &[u8], value: &[u8], meter: &mut StepMeter) {
+            let mut key_rest = Blake2_128Concat::reverse(key);
+            let Ok(consumer) = AccountIdOf::<T>::decode(&mut key_rest) else {
+                log::warn!("pallet-deitos: failed to decode consumer {:?}", key);
+                return;
+            };
+            if !key_rest.is_empty() {
+                // Already an entry of the double map
+                return;
+            }
+            let Ok(agreement_ids) = Vec::<T::AgreementId>::decode(&mut &value[..]) else {
+                log::warn!(
+                    "pallet-deitos: failed to decode agreements of {:?}",
+                    consumer
+                );
+                return;
+            };
+
+            ConsumerAgreements::<T>::remove(&consumer);
+            for agreement_id in agreement_ids.iter() {
+                crate::ConsumerAgreements::<T>::insert(&consumer, agreement_id, ());
+            }
+            ConsumerAgreementCount::<T>::insert(&consumer, agreement_ids.len() as u32);
+            meter.charge(0, agreement_ids.len() as u64 + 1);
+        }
+    }
+
+    /// Limits the entries migrated in a block and counts the database accesses.
+    pub struct StepMeter {
+        remaining: u32,
+        reads: u64,
+        writes: u64,
+    }
+
+    impl StepMeter {
+        /// Counts the database accesses of indexing entries. Every indexed entry counts towards
+        /// the entries migrated in the block.
+        fn charge(&mut self, reads: u64, writes: u64) {
+            self.reads = self.reads.saturating_add(reads);
+            self.writes = self.writes.saturating_add(writes);
+            self.remaining = self.remaining.saturating_sub(writes as u32);
+        }
+
+        fn weight<T: Config>(&self) -> Weight {
+            T::DbWeight::get().reads_writes(self.reads, self.writes)
+        }
+    }
+
+    impl<T: Config> OnRuntimeUpgrade for MigrateToV1<T> {
+        fn on_runtime_upgrade() -> Weight {
+            if Pallet::<T>::on_chain_storage_version() != 0 {
+                log::info!("pallet-deitos: skipping migration to v1");
+                return T::DbWeight::get().reads(1);
+            }
+
+            let _ = CurrentPrices::<T>::translate::<OldPrices<T>, _>(|old| {
+                old.map(|old| Prices {
+                    storage_mb_per_block: old.storage_mb_per_block,
+                    vcore_per_block: Zero::zero(),
+                    ram_mb_per_block: Zero::zero(),
+                })
+            });
+
+            let current_block_number = Pallet::<T>::current_block_number();
+            SettlementCursor::<T>::put(current_block_number);
+            AutoPayCursor::<T>::put(current_block_number);
+            ExpirationCursor::<T>::put(current_block_number);
+            BiddingCursor::<T>::put(current_block_number);
+
+            // The remaining storage is migrated over the following blocks
+            MigrationProgress::<T>::put(MigrationStage::Agreements(None));
+            StorageVersion::new(1).put::<Pallet<T>>();
+            log::info!("pallet-deitos: migration to v1 started");
+
+            T::DbWeight::get().reads_writes(2, 7)
+        }
+
+        #[cfg(feature = "try-runtime")]
+        fn pre_upgrade() -> Result<Vec<u8>, TryRuntimeError> {
+            let agreements = Agreements::<T>::iter_keys().count() as u64;
+            let ips = InfrastructureProviders::<T>::iter_keys().count() as u64;
+            let consumers = ConsumerAgreements::<T>::iter_keys().count() as u64;
+            Ok((agreements, ips, consumers).encode())
+        }
+
+        #[cfg(feature = "try-runtime")]
+        fn post_upgrade(state: Vec<u8>) -> Result<(), TryRuntimeError> {
+            let (agreements, ips, consumers) = <(u64, u64, u64)>::decode(&mut &state[..])
+                .map_err(|_| TryRuntimeError::Other("invalid pre-upgrade state"))?;
+            ensure!(
+                Pallet::<T>::on_chain_storage_version() == 1,
+                "storage version was not updated"
+            );
+
+            // Run the rest of the migration, which is spread over the following blocks on chain
+            while Pallet::<T>::is_migrating() {
+                Self::step(MAX_ENTRIES_PER_BLOCK);
+            }
+
+            ensure!(
+                Agreements::<T>::iter().count() as u64 == agreements,
+                "agreements were lost during the migration"
+            );
+            ensure!(
+                InfrastructureProviders::<T>::iter().count() as u64 == ips,
+                "IPs were lost during the migration"
+            );
+            ensure!(
+                ConsumerAgreementCount::<T>::iter().count() as u64 == consumers,
+                "consumer agreements were lost during the migration"
+            );
+            Ok(())
+        }
+    }
+}
@@ -14,11 +14,11 @@
 // You should have received a copy of the GNU General Public License
 // along with Deitos Node.  If not, see <http://www.gnu.org/licenses/>.
 
-use frame_support::traits::fungible;
+use frame_support::{assert_noop, traits::fungible};
 use frame_system::pallet_prelude::BlockNumberFor;
 
 use crate::{
-    pallet::{Agreements, ConsumerAgreements, Event, HoldReason, InfrastructureProviders},
+    pallet::{Agreements, ConsumerAgreements, Error, Event, HoldReason, InfrastructureProviders},
     types::*,
 };
 
@@ -41,8 +41,10 @@ fn test_consumer_request_agreement() {
             RuntimeOrigin::signed(CONSUMER),
             IP,
             storage,
+            ComputeResources::default(),
             activation_block,
             payment_plan.clone(),
+            None,
         ));
 
         // Assert: Verify that the agreement request is correctly stored
@@ -62,11 +64,19 @@ fn test_consumer_request_agreement() {
 
         // Verify that the IP's data is correctly updated
         let ip_details = InfrastructureProviders::<Test>::get(IP).unwrap();
-        assert_eq!(ip_details.agreements, vec![expected_agreement_id]);
+        assert_eq!(
+            Deitos::ip_agreement_ids(&IP, None, u32::MAX),
+            vec![expected_agreement_id]
+        );
+        assert_eq!(Deitos::ip_agreement_count(IP), 1);
+        assert_eq!(ip_details.allocated_storage, storage);
 
         // Verify that the consumer's data is correctly updated
-        let consumer_agreements = ConsumerAgreements::<Test>::get(CONSUMER);
-        assert_eq!(consumer_agreements, vec![expected_agreement_id]);
+        assert!(ConsumerAgreements::<Test>::contains_key(
+            CONSUMER,
+            expected_agreement_id
+        ));
+        assert_eq!(Deitos::consumer_agreement_count(CONSUMER), 1);
 
         // Assert: Verify that the consumer's balance is properly updated
         assert_eq!(
@@ -97,8 +107,10 @@ fn test_consumer_request_agreement() {
             consumer: CONSUMER,
             consumer_total_deposit: expected_consumer_deposit + CONSUMER_SERVICE_DEPOSIT,
             storage,
+            compute: ComputeResources::default(),
             activation_block,
             payment_plan,
+            sla: None,
         }));
     });
 }
@@ -118,8 +130,10 @@ fn test_ip_accept_agreement() {
             RuntimeOrigin::signed(CONSUMER),
             IP,
             storage,
+            ComputeResources::default(),
             activation_block,
             payment_plan.clone(),
+            None,
         ));
 
         // IP accepts agreement
@@ -158,8 +172,10 @@ fn test_consumer_accept_agreement() {
             RuntimeOrigin::signed(CONSUMER),
             IP,
             storage,
+            ComputeResources::default(),
             activation_block,
             payment_plan,
+            None,
         ));
 
         // IP proposes payment plan of 2 installments (100, 200 blocks)
@@ -235,8 +251,10 @@ fn test_consumer_reject_proposal() {
             RuntimeOrigin::signed(CONSUMER),
             IP,
             storage,
+            ComputeResources::default(),
             activation_block,
             payment_plan,
+            None,
         ));
 
         // IP proposes payment plan of 2 installments (100, 200 blocks)
@@ -262,11 +280,15 @@ fn test_consumer_reject_proposal() {
 
         // Verify that the IP's data is correctly updated
         let ip_details = InfrastructureProviders::<Test>::get(IP).unwrap();
-        assert_eq!(ip_details.agreements, vec![]);
+        assert_eq!(Deitos::ip_agreement_ids(&IP, None, u32::MAX), vec![]);
+        assert_eq!(ip_details.allocated_storage, 0);
 
         // Verify that the consumer's data is correctly updated
-        let consumer_agreements = ConsumerAgreements::<Test>::get(CONSUMER);
-        assert_eq!(consumer_agreements, vec![]);
+        assert_eq!(
+            Deitos::consumer_agreement_ids(&CONSUMER, None, u32::MAX),
+            vec![]
+        );
+        assert_eq!(Deitos::consumer_agreement_count(CONSUMER), 0);
 
         // Verify that the consumer's balance is properly updated
         assert_eq!(Balances::free_balance(CONSUMER), INITIAL_BALANCE);
@@ -288,3 +310,333 @@ fn test_consumer_reject_proposal() {
         }));
     });
 }
+
+#[test]
+fn test_fail_consumer_request_agreement_insufficient_free_storage() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+        let activation_block: BlockNumberFor<Test> = 100;
+        let payment_plan: PaymentPlan<Test> = vec![activation_block + 100].try_into().unwrap();
+
+        // The IP has room for exactly two agreements
+        register_and_activate_ip(IP, 2 * storage);
+
+        create_accepted_agreement(
+            IP,
+            CONSUMER,
+            storage,
+            activation_block,
+            payment_plan.clone(),
+        );
+        assert_ok!(Deitos::consumer_request_agreement(
+            RuntimeOrigin::signed(CONSUMER),
+            IP,
+            storage,
+            ComputeResources::default(),
+            activation_block,
+            payment_plan.clone(),
+            None,
+        ));
+
+        // Storage is allocated, so the third request must fail
+        assert_noop!(
+            Deitos::consumer_request_agreement(
+                RuntimeOrigin::signed(CONSUMER),
+                IP,
+                storage,
+                ComputeResources::default(),
+                activation_block,
+                payment_plan.clone(),
+                None,
+            ),
+            Error::<Test>::InsufficientStorage
+        );
+        assert_eq!(Deitos::ip_free_storage(&IP), Some(0));
+
+        // Revoking the pending request releases its storage
+        assert_ok!(Deitos::consumer_revoke_agreement(
+            RuntimeOrigin::signed(CONSUMER),
+            2,
+        ));
+        assert_eq!(Deitos::ip_free_storage(&IP), Some(storage));
+
+        assert_ok!(Deitos::consumer_request_agreement(
+            RuntimeOrigin::signed(CONSUMER),
+            IP,
+            storage,
+            ComputeResources::default(),
+            activation_block,
+            payment_plan,
+            None,
+        ));
+    });
+}
+
+#[test]
+fn test_consumer_request_agreement_with_compute() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+        let compute = ComputeResources {
+            vcores: 2,
+            ram: 4096,
+        };
+        let activation_block: BlockNumberFor<Test> = 100;
+        let payment_plan: PaymentPlan<Test> = vec![activation_block + 100, activation_block + 200]
+            .try_into()
+            .unwrap();
+
+        register_and_activate_ip(IP, storage);
+
+        assert_ok!(Deitos::consumer_request_agreement(
+            RuntimeOrigin::signed(CONSUMER),
+            IP,
+            storage,
+            compute,
+            activation_block,
+            payment_plan,
+            None,
+        ));
+
+        // The deposit covers the storage and compute resources of the last installment
+        let agreement = Agreements::<Test>::get(1).unwrap();
+        assert_eq!(agreement.compute, compute);
+        assert_eq!(
+            agreement.consumer_security_deposit,
+            100 * (storage * PRICE_STORAGE
+                + compute.vcores as u64 * PRICE_VCORE
+                + compute.ram * PRICE_RAM)
+        );
+
+        // The compute resources are allocated to the agreement
+        let ip_details = InfrastructureProviders::<Test>::get(IP).unwrap();
+        assert_eq!(ip_details.allocated_compute, compute);
+        assert_eq!(
+            ip_details.free_compute(),
+            IP_COMPUTE.saturating_sub(&compute)
+        );
+
+        // Revoking the agreement releases the compute resources
+        assert_ok!(Deitos::consumer_revoke_agreement(
+            RuntimeOrigin::signed(CONSUMER),
+            1
+        ));
+        let ip_details = InfrastructureProviders::<Test>::get(IP).unwrap();
+        assert_eq!(ip_details.allocated_compute, ComputeResources::default());
+    });
+}
+
+#[test]
+fn test_consumer_request_agreement_insufficient_compute() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+        let activation_block: BlockNumberFor<Test> = 100;
+        let payment_plan: PaymentPlan<Test> = vec![activation_block + 100].try_into().unwrap();
+
+        register_and_activate_ip(IP, storage);
+
+        assert_noop!(
+            Deitos::consumer_request_agreement(
+                RuntimeOrigin::signed(CONSUMER),
+                IP,
+                storage,
+                ComputeResources {
+                    vcores: IP_COMPUTE.vcores + 1,
+                    ram: 0,
+                },
+                activation_block,
+                payment_plan,
+                None,
+            ),
+            Error::<Test>::InsufficientCompute
+        );
+    });
+}
+
+#[test]
+fn test_ip_reject_agreement() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+        let activation_block: BlockNumberFor<Test> = 100;
+        let payment_plan: PaymentPlan<Test> = vec![activation_block + 300].try_into().unwrap();
+        let consumer_deposit = 300 * PRICE_STORAGE * storage;
+
+        register_and_activate_ip(IP, storage);
+        assert_ok!(Deitos::consumer_request_agreement(
+            RuntimeOrigin::signed(CONSUMER),
+            IP,
+            storage,
+            ComputeResources::default(),
+            activation_block,
+            payment_plan,
+            None,
+        ));
+        let agreement_id = CurrentAgreementId::<Test>::get();
+
+        // Only the IP of the agreement can reject it
+        assert_noop!(
+            Deitos::ip_reject_agreement(RuntimeOrigin::signed(CONSUMER), agreement_id),
+            Error::<Test>::AgreementNotFound
+        );
+
+        assert_ok!(Deitos::ip_reject_agreement(
+            RuntimeOrigin::signed(IP),
+            agreement_id,
+        ));
+
+        // Verify that the agreement is removed and the consumer deposits are released
+        assert_eq!(Agreements::<Test>::get(agreement_id), None);
+        let ip_details = InfrastructureProviders::<Test>::get(IP).unwrap();
+        assert_eq!(Deitos::ip_agreement_ids(&IP, None, u32::MAX), vec![]);
+        assert_eq!(ip_details.allocated_storage, 0);
+        assert_eq!(Deitos::consumer_agreement_count(CONSUMER), 0);
+        assert_eq!(Balances::free_balance(CONSUMER), INITIAL_BALANCE);
+
+        System::assert_has_event(RuntimeEvent::Deitos(Event::IPRejectedAgreement {
+            agreement_id,
+            ip: IP,
+            consumer: CONSUMER,
+            consumer_total_deposit: consumer_deposit + CONSUMER_SERVICE_DEPOSIT,
+        }));
+
+        // Accepted agreements cannot be rejected
+        let agreement_id = create_accepted_agreement(
+            IP,
+            CONSUMER,
+            storage,
+            activation_block,
+            vec![activation_block + 300].try_into().unwrap(),
+        );
+        assert_noop!(
+            Deitos::ip_reject_agreement(RuntimeOrigin::signed(IP), agreement_id),
+            Error::<Test>::AgreementInProgress
+        );
+    });
+}
+
+#[test]
+fn test_ip_cancel_agreement() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+        let activation_block: BlockNumberFor<Test> = 100;
+        let payment_plan: PaymentPlan<Test> = vec![activation_block + 100, activation_block + 200]
+            .try_into()
+            .unwrap();
+        let installment_cost = 100 * PRICE_STORAGE * storage;
+
+        register_and_activate_ip(IP, storage);
+        let agreement_id =
+            create_accepted_agreement(IP, CONSUMER, storage, activation_block, payment_plan);
+        assert_ok!(Deitos::consumer_prepay_installment(
+            RuntimeOrigin::signed(CONSUMER),
+            agreement_id,
+        ));
+
+        assert_ok!(Deitos::ip_cancel_agreement(
+            RuntimeOrigin::signed(IP),
+            agreement_id,
+        ));
+
+        // Verify that the agreement is removed and the consumer funds are released
+        assert_eq!(Agreements::<Test>::get(agreement_id), None);
+        assert_eq!(Deitos::ip_free_storage(&IP), Some(storage));
+        assert_eq!(
+            <Balances as fungible::InspectHold<_>>::total_balance_on_hold(&CONSUMER),
+            0
+        );
+
+        // The consumer receives the penalty from the IP deposit
+        let penalty = IP_INITIAL_DEPOSIT / 10;
+        assert_eq!(Balances::free_balance(CONSUMER), INITIAL_BALANCE + penalty);
+        assert_eq!(
+            InfrastructureProviders::<Test>::get(IP).unwrap().deposit,
+            IP_INITIAL_DEPOSIT - penalty
+        );
+        assert_eq!(
+            <Balances as fungible::InspectHold<_>>::balance_on_hold(
+                &HoldReason::IPInitialDeposit.into(),
+                &IP
+            ),
+            IP_INITIAL_DEPOSIT - penalty
+        );
+
+        // The IP has to be activated again once its deposit is restored
+        assert_eq!(
+            InfrastructureProviders::<Test>::get(IP).unwrap().status,
+            IPStatus::Pending
+        );
+        System::assert_has_event(RuntimeEvent::Deitos(Event::IPStatusChanged {
+            ip: IP,
+            status: IPStatus::Pending,
+        }));
+
+        System::assert_has_event(RuntimeEvent::Deitos(Event::IPCancelledAgreement {
+            agreement_id,
+            ip: IP,
+            consumer: CONSUMER,
+            refunded: 2 * installment_cost + CONSUMER_SERVICE_DEPOSIT,
+            penalty,
+        }));
+    });
+}
+
+#[test]
+fn test_ip_cancel_agreement_after_activation() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+        let activation_block: BlockNumberFor<Test> = 100;
+
+        register_and_activate_ip(IP, storage);
+        let agreement_id = create_accepted_agreement(
+            IP,
+            CONSUMER,
+            storage,
+            activation_block,
+            vec![activation_block + 100].try_into().unwrap(),
+        );
+
+        run_to_block(activation_block);
+        assert_noop!(
+            Deitos::ip_cancel_agreement(RuntimeOrigin::signed(IP), agreement_id),
+            Error::<Test>::AgreementInProgress
+        );
+    });
+}
+
+#[test]
+fn test_ip_unregister_rejects_pending_agreements() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+
+        register_and_activate_ip(IP, storage);
+        assert_ok!(Deitos::consumer_request_agreement(
+            RuntimeOrigin::signed(CONSUMER),
+            IP,
+            storage,
+            ComputeResources::default(),
+            100,
+            vec![200].try_into().unwrap(),
+            None,
+        ));
+        let agreement_id = CurrentAgreementId::<Test>::get();
+
+        // The work of rejecting the agreements is bounded by the given maximum
+        assert_noop!(
+            Deitos::ip_unregister(RuntimeOrigin::signed(IP), 0),
+            Error::<Test>::TooManyAgreements
+        );
+
+        // Agreements at negotiation stage don't prevent the IP from unregistering
+        assert_ok!(Deitos::ip_unregister(
+            RuntimeOrigin::signed(IP),
+            Deitos::ip_agreement_count(IP)
+        ));
+
+        assert_eq!(Agreements::<Test>::get(agreement_id), None);
+        assert_eq!(Deitos::consumer_agreement_count(CONSUMER), 0);
+        assert_eq!(Balances::free_balance(CONSUMER), INITIAL_BALANCE);
+        assert_eq!(
+            InfrastructureProviders::<Test>::get(IP).unwrap().status,
+            IPStatus::Unregistered
+        );
+    });
+}
@@ -0,0 +1,288 @@
+// Copyright (C) NC2D Labs.
+// This file is part of Deitos Node.
+
+// Deitos Node is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Deitos Node is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Deitos Node.  If not, see <http://www.gnu.org/licenses/>.
+
+use frame_support::{
+    assert_noop,
+    traits::{fungible, Hooks},
+    weights::Weight,
+};
+use frame_system::pallet_prelude::BlockNumberFor;
+
+use crate::{
+    pallet::{
+        Agreements, AmendmentProposals, Error, Event, HoldReason, InfrastructureProviders,
+        SettlementBlocks, SettlementQueue,
+    },
+    types::*,
+};
+
+use super::*;
+
+fn plan(blocks: Vec<BlockNumberFor<Test>>) -> PaymentPlan<Test> {
+    blocks.try_into().unwrap()
+}
+
+fn balance_on_hold(reason: HoldReason) -> Balance {
+    <Balances as fungible::InspectHold<_>>::balance_on_hold(&reason.into(), &CONSUMER)
+}
+
+#[test]
+fn test_amendment() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+        let new_storage: StorageSizeMB = 200;
+
+        register_and_activate_ip(IP, 300);
+        let agreement_id =
+            create_accepted_agreement(IP, CONSUMER, storage, 100, plan(vec![200, 300, 400]));
+        assert_ok!(Deitos::consumer_prepay_installment(
+            RuntimeOrigin::signed(CONSUMER),
+            agreement_id,
+        ));
+
+        // Only the parties of the agreement can propose an amendment
+        assert_noop!(
+            Deitos::propose_amendment(
+                RuntimeOrigin::signed(3),
+                agreement_id,
+                Some(new_storage),
+                None
+            ),
+            Error::<Test>::AgreementNotFound
+        );
+
+        assert_ok!(Deitos::propose_amendment(
+            RuntimeOrigin::signed(IP),
+            agreement_id,
+            Some(new_storage),
+            Some(plan(vec![350, 500]))
+        ));
+        System::assert_has_event(RuntimeEvent::Deitos(Event::AmendmentProposed {
+            agreement_id,
+            proposed_by: IP,
+            storage: Some(new_storage),
+            payment_plan_tail: Some(plan(vec![350, 500])),
+        }));
+
+        // The proposing party cannot accept its own proposal
+        assert_noop!(
+            Deitos::accept_amendment(RuntimeOrigin::signed(IP), agreement_id),
+            Error::<Test>::AmendmentNotFound
+        );
+
+        assert_ok!(Deitos::accept_amendment(
+            RuntimeOrigin::signed(CONSUMER),
+            agreement_id
+        ));
+
+        // The unpaid installments are replaced and the storage is updated
+        let agreement = Agreements::<Test>::get(agreement_id).unwrap();
+        assert_eq!(agreement.payment_plan, plan(vec![200, 350, 500]));
+        assert_eq!(agreement.storage, new_storage);
+        assert_eq!(AmendmentProposals::<Test>::get(agreement_id), None);
+        assert_eq!(
+            InfrastructureProviders::<Test>::get(IP)
+                .unwrap()
+                .allocated_storage,
+            new_storage
+        );
+
+        // The prepaid installment hasn't started, so it is recalculated with the new storage
+        let installment_cost = 100 * PRICE_STORAGE * new_storage;
+        assert_eq!(
+            agreement.payment_history.records[0].amount,
+            installment_cost
+        );
+        assert_eq!(
+            balance_on_hold(HoldReason::ConsumerInstallment),
+            installment_cost
+        );
+
+        // The security deposit covers the new last installment
+        let consumer_security_deposit = 150 * PRICE_STORAGE * new_storage;
+        assert_eq!(
+            agreement.consumer_security_deposit,
+            consumer_security_deposit
+        );
+        assert_eq!(
+            balance_on_hold(HoldReason::ConsumerSecurityDeposit),
+            consumer_security_deposit
+        );
+
+        System::assert_has_event(RuntimeEvent::Deitos(Event::AgreementAmended {
+            agreement_id,
+            storage: new_storage,
+            payment_plan: plan(vec![200, 350, 500]),
+            consumer_security_deposit,
+        }));
+    });
+}
+
+#[test]
+fn test_amendment_keeps_started_installments() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+        let new_storage: StorageSizeMB = 50;
+
+        register_and_activate_ip(IP, storage);
+        let agreement_id =
+            create_accepted_agreement(IP, CONSUMER, storage, 100, plan(vec![200, 300]));
+        assert_ok!(Deitos::consumer_prepay_installment(
+            RuntimeOrigin::signed(CONSUMER),
+            agreement_id,
+        ));
+
+        run_to_block(150);
+        assert_ok!(Deitos::propose_amendment(
+            RuntimeOrigin::signed(CONSUMER),
+            agreement_id,
+            Some(new_storage),
+            None
+        ));
+        assert_ok!(Deitos::accept_amendment(
+            RuntimeOrigin::signed(IP),
+            agreement_id
+        ));
+
+        // The started installment keeps its cost, the last one is recalculated
+        let agreement = Agreements::<Test>::get(agreement_id).unwrap();
+        assert_eq!(
+            agreement.payment_history.records[0].amount,
+            100 * PRICE_STORAGE * storage
+        );
+        assert_eq!(
+            agreement.consumer_security_deposit,
+            100 * PRICE_STORAGE * new_storage
+        );
+        assert_eq!(
+            InfrastructureProviders::<Test>::get(IP)
+                .unwrap()
+                .allocated_storage,
+            new_storage
+        );
+    });
+}
+
+#[test]
+fn test_amendment_with_full_settlement_queue() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+
+        register_and_activate_ip(IP, storage);
+        let agreement_id =
+            create_accepted_agreement(IP, CONSUMER, storage, 100, plan(vec![200, 300]));
+        assert_ok!(Deitos::consumer_prepay_installment(
+            RuntimeOrigin::signed(CONSUMER),
+            agreement_id,
+        ));
+
+        // The queue of the settlement of the last installment is full, so the agreement is
+        // scheduled a block later
+        let other_agreements = vec![agreement_id + 1, agreement_id + 2];
+        SettlementQueue::<Test>::insert(
+            301,
+            SettlementsVec::<Test>::truncate_from(other_agreements.clone()),
+        );
+        run_to_block(201);
+        Deitos::on_idle(201, Weight::MAX);
+        assert_eq!(
+            SettlementQueue::<Test>::get(302).into_inner(),
+            vec![agreement_id]
+        );
+        assert_eq!(SettlementBlocks::<Test>::get(agreement_id), Some(302));
+
+        assert_ok!(Deitos::propose_amendment(
+            RuntimeOrigin::signed(IP),
+            agreement_id,
+            None,
+            Some(plan(vec![350]))
+        ));
+        assert_ok!(Deitos::accept_amendment(
+            RuntimeOrigin::signed(CONSUMER),
+            agreement_id
+        ));
+
+        // The agreement is moved from the block where it was scheduled
+        assert!(SettlementQueue::<Test>::get(302).is_empty());
+        assert_eq!(
+            SettlementQueue::<Test>::get(301).into_inner(),
+            other_agreements
+        );
+        assert_eq!(
+            SettlementQueue::<Test>::get(351).into_inner(),
+            vec![agreement_id]
+        );
+        assert_eq!(SettlementBlocks::<Test>::get(agreement_id), Some(351));
+    });
+}
+
+#[test]
+fn test_amendment_invalid() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+
+        register_and_activate_ip(IP, 300);
+        let agreement_id =
+            create_accepted_agreement(IP, CONSUMER, storage, 100, plan(vec![200, 300, 400]));
+        assert_ok!(Deitos::consumer_prepay_installment(
+            RuntimeOrigin::signed(CONSUMER),
+            agreement_id,
+        ));
+
+        // The amendment must change something
+        assert_noop!(
+            Deitos::propose_amendment(RuntimeOrigin::signed(CONSUMER), agreement_id, None, None),
+            Error::<Test>::AmendmentInvalid
+        );
+
+        // The tail cannot replace prepaid installments
+        assert_noop!(
+            Deitos::propose_amendment(
+                RuntimeOrigin::signed(CONSUMER),
+                agreement_id,
+                None,
+                Some(plan(vec![150, 400]))
+            ),
+            Error::<Test>::PaymentPlanInvalid
+        );
+
+        // The IP must have enough free storage when the amendment is accepted
+        assert_ok!(Deitos::propose_amendment(
+            RuntimeOrigin::signed(CONSUMER),
+            agreement_id,
+            Some(400),
+            None
+        ));
+        assert_noop!(
+            Deitos::accept_amendment(RuntimeOrigin::signed(IP), agreement_id),
+            Error::<Test>::InsufficientStorage
+        );
+
+        // The other party can reject the amendment
+        assert_ok!(Deitos::reject_amendment(
+            RuntimeOrigin::signed(IP),
+            agreement_id
+        ));
+        System::assert_has_event(RuntimeEvent::Deitos(Event::AmendmentRejected {
+            agreement_id,
+            rejected_by: IP,
+        }));
+        assert_noop!(
+            Deitos::accept_amendment(RuntimeOrigin::signed(IP), agreement_id),
+            Error::<Test>::AmendmentNotFound
+        );
+    });
+}
@@ -0,0 +1,429 @@
+// Copyright (C) NC2D Labs.
+// This file is part of Deitos Node.
+
+// Deitos Node is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Deitos Node is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Deitos Node.  If not, see <http://www.gnu.org/licenses/>.
+
+use frame_support::{
+    assert_noop,
+    traits::{fungible, Hooks},
+    weights::Weight,
+};
+
+use crate::{
+    pallet::{Agreements, Bids, Error, Event, ExpirationQueue, OpenRequests},
+    types::*,
+};
+
+use super::*;
+
+const STORAGE: StorageSizeMB = 100;
+const ACTIVATION_BLOCK: u64 = 100;
+const MAX_PRICE: Balance = 100 * STORAGE * PRICE_STORAGE;
+const BID_BOND: Balance = 100;
+const IP_2: AccountId = 3;
+const IP_3: AccountId = 4;
+
+fn bid_prices(storage_mb_per_block: Balance) -> Prices<Test> {
+    Prices {
+        storage_mb_per_block,
+        vcore_per_block: 0,
+        ram_mb_per_block: 0,
+    }
+}
+
+fn open_request(min_ip_reputation: Option<u32>) -> AgreementId {
+    assert_ok!(Deitos::consumer_open_request(
+        RuntimeOrigin::signed(CONSUMER),
+        STORAGE,
+        ComputeResources::default(),
+        ACTIVATION_BLOCK,
+        vec![ACTIVATION_BLOCK + 100].try_into().unwrap(),
+        MAX_PRICE,
+        min_ip_reputation,
+        None,
+    ));
+
+    CurrentAgreementId::<Test>::get()
+}
+
+fn balance_on_hold(who: AccountId) -> Balance {
+    <Balances as fungible::InspectHold<_>>::total_balance_on_hold(&who)
+}
+
+#[test]
+fn test_consumer_open_request() {
+    new_test_ext().execute_with(|| {
+        // The agreement must start after the bidding is closed
+        assert_noop!(
+            Deitos::consumer_open_request(
+                RuntimeOrigin::signed(CONSUMER),
+                STORAGE,
+                ComputeResources::default(),
+                12,
+                vec![112].try_into().unwrap(),
+                MAX_PRICE,
+                None,
+                None,
+            ),
+            Error::<Test>::BiddingPeriodInvalid
+        );
+
+        let request_id = open_request(None);
+
+        let request = OpenRequests::<Test>::get(request_id).unwrap();
+        assert_eq!(request.consumer, CONSUMER);
+        assert_eq!(request.bidding_end, 11);
+        assert_eq!(request.bids, 0);
+        assert_eq!(balance_on_hold(CONSUMER), CONSUMER_SERVICE_DEPOSIT);
+
+        System::assert_has_event(RuntimeEvent::Deitos(Event::OpenRequestCreated {
+            request_id,
+            consumer: CONSUMER,
+            storage: STORAGE,
+            compute: ComputeResources::default(),
+            activation_block: ACTIVATION_BLOCK,
+            max_price: MAX_PRICE,
+            bidding_end: 11,
+        }));
+    });
+}
+
+#[test]
+fn test_ip_bid() {
+    new_test_ext().execute_with(|| {
+        register_and_activate_ip(IP, 1000);
+        register_ip(IP_2, 1000);
+        register_and_activate_ip(IP_3, STORAGE - 1);
+        let request_id = open_request(None);
+
+        // Only active IPs with enough free capacity can bid
+        assert_noop!(
+            Deitos::ip_bid(RuntimeOrigin::signed(IP_2), request_id, bid_prices(5)),
+            Error::<Test>::IPNotActive
+        );
+        assert_noop!(
+            Deitos::ip_bid(RuntimeOrigin::signed(IP_3), request_id, bid_prices(5)),
+            Error::<Test>::InsufficientStorage
+        );
+
+        // The bid cannot cost more than the maximum price
+        assert_noop!(
+            Deitos::ip_bid(
+                RuntimeOrigin::signed(IP),
+                request_id,
+                bid_prices(PRICE_STORAGE + 1)
+            ),
+            Error::<Test>::BidAboveMaxPrice
+        );
+
+        assert_ok!(Deitos::ip_bid(
+            RuntimeOrigin::signed(IP),
+            request_id,
+            bid_prices(8)
+        ));
+        assert_eq!(balance_on_hold(IP), IP_INITIAL_DEPOSIT + BID_BOND);
+
+        // Updating the bid keeps the bond
+        assert_ok!(Deitos::ip_bid(
+            RuntimeOrigin::signed(IP),
+            request_id,
+            bid_prices(6)
+        ));
+        assert_eq!(balance_on_hold(IP), IP_INITIAL_DEPOSIT + BID_BOND);
+        assert_eq!(OpenRequests::<Test>::get(request_id).unwrap().bids, 1);
+        assert_eq!(
+            Bids::<Test>::get(request_id, IP).unwrap().cost,
+            100 * STORAGE * 6
+        );
+
+        System::assert_last_event(RuntimeEvent::Deitos(Event::IPPlacedBid {
+            request_id,
+            ip: IP,
+            cost: 100 * STORAGE * 6,
+        }));
+
+        // No bids after the bidding window
+        run_to_block(12);
+        assert_noop!(
+            Deitos::ip_bid(RuntimeOrigin::signed(IP), request_id, bid_prices(5)),
+            Error::<Test>::BiddingClosed
+        );
+    });
+}
+
+#[test]
+fn test_ip_bid_limits() {
+    new_test_ext().execute_with(|| {
+        register_and_activate_ip(IP, 1000);
+        register_and_activate_ip(IP_2, 1000);
+        register_and_activate_ip(IP_3, 1000);
+
+        // A new IP has a neutral reputation
+        let request_id = open_request(Some(3 * AverageRating::SCALE + 1));
+        assert_noop!(
+            Deitos::ip_bid(RuntimeOrigin::signed(IP), request_id, bid_prices(5)),
+            Error::<Test>::IPReputationTooLow
+        );
+
+        let request_id = open_request(Some(3 * AverageRating::SCALE));
+        assert_ok!(Deitos::ip_bid(
+            RuntimeOrigin::signed(IP),
+            request_id,
+            bid_prices(5)
+        ));
+        assert_ok!(Deitos::ip_bid(
+            RuntimeOrigin::signed(IP_2),
+            request_id,
+            bid_prices(5)
+        ));
+        assert_noop!(
+            Deitos::ip_bid(RuntimeOrigin::signed(IP_3), request_id, bid_prices(5)),
+            Error::<Test>::TooManyBids
+        );
+    });
+}
+
+#[test]
+fn test_consumer_accept_bid() {
+    new_test_ext().execute_with(|| {
+        register_and_activate_ip(IP, 1000);
+        register_and_activate_ip(IP_2, 1000);
+        let request_id = open_request(None);
+
+        assert_ok!(Deitos::ip_bid(
+            RuntimeOrigin::signed(IP),
+            request_id,
+            bid_prices(5)
+        ));
+        assert_ok!(Deitos::ip_bid(
+            RuntimeOrigin::signed(IP_2),
+            request_id,
+            bid_prices(8)
+        ));
+
+        // Only the consumer of the request can accept a bid
+        assert_noop!(
+            Deitos::consumer_accept_bid(RuntimeOrigin::signed(IP_3), request_id, IP_2),
+            Error::<Test>::OpenRequestNotFound
+        );
+        assert_noop!(
+            Deitos::consumer_accept_bid(RuntimeOrigin::signed(CONSUMER), request_id, IP_3),
+            Error::<Test>::BidNotFound
+        );
+
+        // The consumer is free to pick a bid other than the lowest one
+        assert_ok!(Deitos::consumer_accept_bid(
+            RuntimeOrigin::signed(CONSUMER),
+            request_id,
+            IP_2
+        ));
+
+        let agreement_id = CurrentAgreementId::<Test>::get();
+        let agreement = Agreements::<Test>::get(agreement_id).unwrap();
+        assert_eq!(agreement.ip, IP_2);
+        assert_eq!(agreement.status, AgreementStatus::Active);
+        assert_eq!(agreement.prices, bid_prices(8));
+        assert_eq!(Deitos::ip_free_storage(&IP_2), Some(1000 - STORAGE));
+
+        // The request is closed and the bonds of all the bidders are released
+        assert_eq!(OpenRequests::<Test>::get(request_id), None);
+        assert_eq!(Bids::<Test>::iter_prefix(request_id).count(), 0);
+        assert_eq!(balance_on_hold(IP), IP_INITIAL_DEPOSIT);
+        assert_eq!(balance_on_hold(IP_2), IP_INITIAL_DEPOSIT);
+        assert_eq!(
+            balance_on_hold(CONSUMER),
+            CONSUMER_SERVICE_DEPOSIT + agreement.consumer_security_deposit
+        );
+
+        System::assert_has_event(RuntimeEvent::Deitos(Event::OpenRequestAwarded {
+            request_id,
+            agreement_id,
+            ip: IP_2,
+            consumer: CONSUMER,
+            cost: 100 * STORAGE * 8,
+        }));
+    });
+}
+
+#[test]
+fn test_lowest_bid_awarded_when_bidding_ends() {
+    new_test_ext().execute_with(|| {
+        register_and_activate_ip(IP, 1000);
+        register_and_activate_ip(IP_2, 1000);
+        let request_id = open_request(None);
+
+        assert_ok!(Deitos::ip_bid(
+            RuntimeOrigin::signed(IP),
+            request_id,
+            bid_prices(5)
+        ));
+        assert_ok!(Deitos::ip_bid(
+            RuntimeOrigin::signed(IP_2),
+            request_id,
+            bid_prices(7)
+        ));
+
+        // The lowest bidder has no longer the capacity for the agreement
+        assert_ok!(Deitos::ip_update_storage(
+            RuntimeOrigin::signed(IP),
+            STORAGE - 1
+        ));
+
+        // The bidding is not closed before the window ends
+        Deitos::on_idle(11, Weight::MAX);
+        assert!(OpenRequests::<Test>::get(request_id).is_some());
+
+        run_to_block(12);
+        Deitos::on_idle(12, Weight::MAX);
+
+        // The lowest qualifying bid wins
+        let agreement_id = CurrentAgreementId::<Test>::get();
+        let agreement = Agreements::<Test>::get(agreement_id).unwrap();
+        assert_eq!(agreement.ip, IP_2);
+        assert_eq!(agreement.status, AgreementStatus::Active);
+        assert_eq!(OpenRequests::<Test>::get(request_id), None);
+        assert_eq!(balance_on_hold(IP), IP_INITIAL_DEPOSIT);
+        assert_eq!(balance_on_hold(IP_2), IP_INITIAL_DEPOSIT);
+
+        System::assert_has_event(RuntimeEvent::Deitos(Event::OpenRequestAwarded {
+            request_id,
+            agreement_id,
+            ip: IP_2,
+            consumer: CONSUMER,
+            cost: 100 * STORAGE * 7,
+        }));
+    });
+}
+
+#[test]
+fn test_bid_awarded_at_earliest_activation_block() {
+    new_test_ext().execute_with(|| {
+        let activation_block = 13;
+        register_and_activate_ip(IP, 1000);
+
+        assert_ok!(Deitos::consumer_open_request(
+            RuntimeOrigin::signed(CONSUMER),
+            STORAGE,
+            ComputeResources::default(),
+            activation_block,
+            vec![activation_block + 100].try_into().unwrap(),
+            MAX_PRICE,
+            None,
+            None,
+        ));
+        let request_id = CurrentAgreementId::<Test>::get();
+        assert_ok!(Deitos::ip_bid(
+            RuntimeOrigin::signed(IP),
+            request_id,
+            bid_prices(5)
+        ));
+
+        // The bidding is closed right before the activation block
+        run_to_block(activation_block - 1);
+        Deitos::on_idle(activation_block - 1, Weight::MAX);
+
+        let agreement_id = CurrentAgreementId::<Test>::get();
+        let agreement = Agreements::<Test>::get(agreement_id).unwrap();
+        assert_eq!(agreement.ip, IP);
+        assert_eq!(agreement.status, AgreementStatus::Active);
+        assert_eq!(OpenRequests::<Test>::get(request_id), None);
+
+        // The active agreement is not scheduled to expire as a request
+        assert!(!ExpirationQueue::<Test>::get(activation_block + 1).contains(&agreement_id));
+    });
+}
+
+#[test]
+fn test_late_award_closes_open_request() {
+    new_test_ext().execute_with(|| {
+        let activation_block = 13;
+        register_and_activate_ip(IP, 1000);
+
+        assert_ok!(Deitos::consumer_open_request(
+            RuntimeOrigin::signed(CONSUMER),
+            STORAGE,
+            ComputeResources::default(),
+            activation_block,
+            vec![activation_block + 100].try_into().unwrap(),
+            MAX_PRICE,
+            None,
+            None,
+        ));
+        let request_id = CurrentAgreementId::<Test>::get();
+        assert_ok!(Deitos::ip_bid(
+            RuntimeOrigin::signed(IP),
+            request_id,
+            bid_prices(5)
+        ));
+
+        // The consumer cannot accept the bid once the activation block has been reached
+        run_to_block(activation_block);
+        assert_noop!(
+            Deitos::consumer_accept_bid(RuntimeOrigin::signed(CONSUMER), request_id, IP),
+            Error::<Test>::AgreementOutdated
+        );
+
+        // The bidding is closed late, so the request is closed without an agreement
+        Deitos::on_idle(activation_block, Weight::MAX);
+
+        assert_eq!(OpenRequests::<Test>::get(request_id), None);
+        assert_eq!(CurrentAgreementId::<Test>::get(), request_id);
+        assert_eq!(Deitos::ip_agreement_count(IP), 0);
+        assert_eq!(balance_on_hold(CONSUMER), 0);
+        assert_eq!(balance_on_hold(IP), IP_INITIAL_DEPOSIT);
+        System::assert_has_event(RuntimeEvent::Deitos(Event::OpenRequestClosed {
+            request_id,
+            consumer: CONSUMER,
+        }));
+    });
+}
+
+#[test]
+fn test_open_request_closed_without_agreement() {
+    new_test_ext().execute_with(|| {
+        register_and_activate_ip(IP, 1000);
+
+        // A request without bids is closed when the bidding ends
+        let request_id = open_request(None);
+        run_to_block(12);
+        Deitos::on_idle(12, Weight::MAX);
+
+        assert_eq!(OpenRequests::<Test>::get(request_id), None);
+        assert_eq!(balance_on_hold(CONSUMER), 0);
+        System::assert_has_event(RuntimeEvent::Deitos(Event::OpenRequestClosed {
+            request_id,
+            consumer: CONSUMER,
+        }));
+
+        // A cancelled request releases the bonds of the bidders
+        let request_id = open_request(None);
+        assert_ok!(Deitos::ip_bid(
+            RuntimeOrigin::signed(IP),
+            request_id,
+            bid_prices(5)
+        ));
+        assert_ok!(Deitos::consumer_cancel_open_request(
+            RuntimeOrigin::signed(CONSUMER),
+            request_id
+        ));
+
+        assert_eq!(OpenRequests::<Test>::get(request_id), None);
+        assert_eq!(balance_on_hold(CONSUMER), 0);
+        assert_eq!(balance_on_hold(IP), IP_INITIAL_DEPOSIT);
+        assert_noop!(
+            Deitos::consumer_accept_bid(RuntimeOrigin::signed(CONSUMER), request_id, IP),
+            Error::<Test>::OpenRequestNotFound
+        );
+    });
+}
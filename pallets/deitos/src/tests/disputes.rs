@@ -0,0 +1,236 @@
+// Copyright (C) NC2D Labs.
+// This file is part of Deitos Node.
+
+// Deitos Node is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Deitos Node is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Deitos Node.  If not, see <http://www.gnu.org/licenses/>.
+
+use frame_support::{assert_noop, traits::fungible};
+use frame_system::pallet_prelude::BlockNumberFor;
+use sp_runtime::{DispatchError, Perbill};
+
+use crate::{
+    pallet::{Agreements, ConsumerProfiles, Disputes, Error, Event, InfrastructureProviders},
+    types::*,
+};
+
+use super::*;
+
+fn evidence() -> EvidenceHash<Test> {
+    b"QmEvidence".to_vec().try_into().unwrap()
+}
+
+/// Creates an active agreement with the first installment prepaid and returns its id along with
+/// the total amount held from the consumer.
+fn create_prepaid_agreement() -> (AgreementId, Balance) {
+    let storage: StorageSizeMB = 100;
+    let activation_block: BlockNumberFor<Test> = 100;
+    let payment_plan: PaymentPlan<Test> = vec![activation_block + 100, activation_block + 200]
+        .try_into()
+        .unwrap();
+
+    register_and_activate_ip(IP, storage);
+    let agreement_id =
+        create_accepted_agreement(IP, CONSUMER, storage, activation_block, payment_plan);
+    assert_ok!(Deitos::consumer_prepay_installment(
+        RuntimeOrigin::signed(CONSUMER),
+        agreement_id,
+    ));
+
+    let installment = 100 * PRICE_STORAGE * storage;
+    let security_deposit = 100 * PRICE_STORAGE * storage;
+    (
+        agreement_id,
+        installment + security_deposit + CONSUMER_SERVICE_DEPOSIT,
+    )
+}
+
+#[test]
+fn test_open_dispute() {
+    new_test_ext().execute_with(|| {
+        let (agreement_id, _) = create_prepaid_agreement();
+
+        // Only the parties of the agreement can open a dispute
+        assert_noop!(
+            Deitos::open_dispute(RuntimeOrigin::signed(3), agreement_id, evidence()),
+            Error::<Test>::AgreementNotFound
+        );
+
+        assert_ok!(Deitos::open_dispute(
+            RuntimeOrigin::signed(CONSUMER),
+            agreement_id,
+            evidence()
+        ));
+
+        let agreement = Agreements::<Test>::get(agreement_id).unwrap();
+        assert_eq!(agreement.status, AgreementStatus::Disputed);
+        assert_eq!(
+            Disputes::<Test>::get(agreement_id),
+            Some(DisputeDetails {
+                opened_by: AgreementParty::Consumer,
+                evidence: evidence(),
+                opened_at: 1,
+            })
+        );
+
+        System::assert_has_event(RuntimeEvent::Deitos(Event::DisputeOpened {
+            agreement_id,
+            opened_by: CONSUMER,
+            evidence: evidence(),
+        }));
+
+        // A disputed agreement cannot be disputed again
+        assert_noop!(
+            Deitos::open_dispute(RuntimeOrigin::signed(IP), agreement_id, evidence()),
+            Error::<Test>::AgreementStatusInvalid
+        );
+    });
+}
+
+#[test]
+fn test_dispute_freezes_transfers() {
+    new_test_ext().execute_with(|| {
+        let (agreement_id, _) = create_prepaid_agreement();
+
+        assert_ok!(Deitos::open_dispute(
+            RuntimeOrigin::signed(IP),
+            agreement_id,
+            evidence()
+        ));
+
+        run_to_block(350);
+
+        assert_noop!(
+            Deitos::ip_withdraw_installments(RuntimeOrigin::signed(IP), agreement_id),
+            Error::<Test>::AgreementStatusInvalid
+        );
+        assert_noop!(
+            Deitos::ip_terminate_nonpay(RuntimeOrigin::signed(IP), agreement_id),
+            Error::<Test>::AgreementStatusInvalid
+        );
+    });
+}
+
+#[test]
+fn test_resolve_dispute_split() {
+    new_test_ext().execute_with(|| {
+        let (agreement_id, held) = create_prepaid_agreement();
+
+        assert_ok!(Deitos::open_dispute(
+            RuntimeOrigin::signed(CONSUMER),
+            agreement_id,
+            evidence()
+        ));
+
+        // Only the dispute origin can resolve disputes
+        assert_noop!(
+            Deitos::resolve_dispute(
+                RuntimeOrigin::signed(CONSUMER),
+                agreement_id,
+                Perbill::one()
+            ),
+            DispatchError::BadOrigin
+        );
+
+        let consumer_balance = Balances::free_balance(CONSUMER);
+        let ip_balance = Balances::free_balance(IP);
+
+        assert_ok!(Deitos::resolve_dispute(
+            RuntimeOrigin::root(),
+            agreement_id,
+            Perbill::from_percent(50)
+        ));
+
+        // The held funds are split in half
+        assert_eq!(
+            Balances::free_balance(CONSUMER),
+            consumer_balance + held / 2
+        );
+        assert_eq!(Balances::free_balance(IP), ip_balance + held / 2);
+        assert_eq!(
+            <Balances as fungible::InspectHold<_>>::total_balance_on_hold(&CONSUMER),
+            0
+        );
+
+        // The agreement and the dispute are removed and the resources are released
+        assert_eq!(Agreements::<Test>::get(agreement_id), None);
+        assert_eq!(Disputes::<Test>::get(agreement_id), None);
+        let ip_details = InfrastructureProviders::<Test>::get(IP).unwrap();
+        assert_eq!(ip_details.allocated_storage, 0);
+        assert_eq!(Deitos::ip_agreement_count(IP), 0);
+
+        // The outcome is recorded for both parties
+        let split = DisputeRecord {
+            won: 0,
+            lost: 0,
+            split: 1,
+        };
+        assert_eq!(ip_details.disputes, split);
+        assert_eq!(ConsumerProfiles::<Test>::get(CONSUMER).disputes, split);
+
+        System::assert_has_event(RuntimeEvent::Deitos(Event::DisputeResolved {
+            agreement_id,
+            outcome: DisputeOutcome::Split,
+            consumer_amount: held / 2,
+            ip_amount: held / 2,
+        }));
+    });
+}
+
+#[test]
+fn test_resolve_dispute_in_favour_of_ip() {
+    new_test_ext().execute_with(|| {
+        let (agreement_id, held) = create_prepaid_agreement();
+        let ip_balance = Balances::free_balance(IP);
+
+        assert_ok!(Deitos::open_dispute(
+            RuntimeOrigin::signed(IP),
+            agreement_id,
+            evidence()
+        ));
+        assert_ok!(Deitos::resolve_dispute(
+            RuntimeOrigin::root(),
+            agreement_id,
+            Perbill::zero()
+        ));
+
+        assert_eq!(Balances::free_balance(IP), ip_balance + held);
+        assert_eq!(
+            InfrastructureProviders::<Test>::get(IP).unwrap().disputes,
+            DisputeRecord {
+                won: 1,
+                lost: 0,
+                split: 0,
+            }
+        );
+        assert_eq!(
+            ConsumerProfiles::<Test>::get(CONSUMER).disputes,
+            DisputeRecord {
+                won: 0,
+                lost: 1,
+                split: 0,
+            }
+        );
+    });
+}
+
+#[test]
+fn test_resolve_dispute_not_found() {
+    new_test_ext().execute_with(|| {
+        let (agreement_id, _) = create_prepaid_agreement();
+
+        assert_noop!(
+            Deitos::resolve_dispute(RuntimeOrigin::root(), agreement_id, Perbill::one()),
+            Error::<Test>::DisputeNotFound
+        );
+    });
+}
@@ -0,0 +1,230 @@
+// Copyright (C) NC2D Labs.
+// This file is part of Deitos Node.
+
+// Deitos Node is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Deitos Node is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Deitos Node.  If not, see <http://www.gnu.org/licenses/>.
+
+use frame_support::{
+    assert_noop,
+    traits::{fungible, Hooks},
+    weights::Weight,
+};
+
+use crate::{
+    pallet::{Agreements, Error, Event, InfrastructureProviders},
+    types::*,
+};
+
+use super::*;
+
+const CLEANER: AccountId = 3;
+const CLEANUP_REWARD: Balance = 100;
+
+fn request_agreement(storage: StorageSizeMB) -> AgreementId {
+    assert_ok!(Deitos::consumer_request_agreement(
+        RuntimeOrigin::signed(CONSUMER),
+        IP,
+        storage,
+        ComputeResources::default(),
+        100,
+        vec![200].try_into().unwrap(),
+        None,
+    ));
+    CurrentAgreementId::<Test>::get()
+}
+
+#[test]
+fn test_cleanup_expired_agreement() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+
+        register_and_activate_ip(IP, storage);
+        let agreement_id = request_agreement(storage);
+
+        // The request can still be accepted up to its activation block
+        run_to_block(100);
+        assert_noop!(
+            Deitos::cleanup_expired_agreement(RuntimeOrigin::signed(CLEANER), agreement_id),
+            Error::<Test>::AgreementNotExpired
+        );
+
+        run_to_block(101);
+        assert_ok!(Deitos::cleanup_expired_agreement(
+            RuntimeOrigin::signed(CLEANER),
+            agreement_id
+        ));
+
+        // Verify that the agreement is removed and the consumer deposits are released, except
+        // for the cleanup reward
+        assert_eq!(Agreements::<Test>::get(agreement_id), None);
+        assert_eq!(Deitos::ip_agreement_count(IP), 0);
+        assert_eq!(Deitos::consumer_agreement_count(CONSUMER), 0);
+        assert_eq!(
+            Balances::free_balance(CONSUMER),
+            INITIAL_BALANCE - CLEANUP_REWARD
+        );
+        assert_eq!(
+            <Balances as fungible::InspectHold<_>>::total_balance_on_hold(&CONSUMER),
+            0
+        );
+        assert_eq!(
+            Balances::free_balance(CLEANER),
+            INITIAL_BALANCE + CLEANUP_REWARD
+        );
+
+        System::assert_has_event(RuntimeEvent::Deitos(Event::AgreementExpired {
+            agreement_id,
+            consumer_total_deposit: 100 * PRICE_STORAGE * storage + CONSUMER_SERVICE_DEPOSIT
+                - CLEANUP_REWARD,
+            cleaned_up_by: Some(CLEANER),
+            reward: CLEANUP_REWARD,
+        }));
+    });
+}
+
+#[test]
+fn test_expired_agreements_deleted_on_idle() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+
+        register_and_activate_ip(IP, 2 * storage);
+        let expired_id = request_agreement(storage);
+        let accepted_id = request_agreement(storage);
+        assert_ok!(Deitos::ip_accept_agreement(
+            RuntimeOrigin::signed(IP),
+            accepted_id
+        ));
+
+        run_to_block(101);
+        Deitos::on_idle(101, Weight::MAX);
+
+        // Only the request that was not accepted is deleted
+        assert_eq!(Agreements::<Test>::get(expired_id), None);
+        assert_eq!(
+            Agreements::<Test>::get(accepted_id).unwrap().status,
+            AgreementStatus::Active
+        );
+        assert_eq!(
+            Deitos::consumer_agreement_ids(&CONSUMER, None, u32::MAX),
+            vec![accepted_id]
+        );
+
+        System::assert_has_event(RuntimeEvent::Deitos(Event::AgreementExpired {
+            agreement_id: expired_id,
+            consumer_total_deposit: 100 * PRICE_STORAGE * storage + CONSUMER_SERVICE_DEPOSIT,
+            cleaned_up_by: None,
+            reward: 0,
+        }));
+    });
+}
+
+fn create_completed_agreement(storage: StorageSizeMB) -> AgreementId {
+    let agreement_id =
+        create_accepted_agreement(IP, CONSUMER, storage, 100, vec![200].try_into().unwrap());
+
+    run_to_block(201);
+    assert_ok!(Deitos::ip_withdraw_installments(
+        RuntimeOrigin::signed(IP),
+        agreement_id,
+    ));
+    assert_eq!(
+        Agreements::<Test>::get(agreement_id).unwrap().status,
+        AgreementStatus::Completed
+    );
+    agreement_id
+}
+
+#[test]
+fn test_cleanup_unrated_agreement() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+
+        register_and_activate_ip(IP, storage);
+        let agreement_id = create_completed_agreement(storage);
+
+        // The consumer can leave feedback up to 100 blocks after the end of the agreement
+        run_to_block(300);
+        assert_noop!(
+            Deitos::cleanup_expired_agreement(RuntimeOrigin::signed(CLEANER), agreement_id),
+            Error::<Test>::AgreementNotExpired
+        );
+
+        run_to_block(301);
+        assert_noop!(
+            Deitos::consumer_submit_feedback(
+                RuntimeOrigin::signed(CONSUMER),
+                agreement_id,
+                Score::Five,
+                Score::Five,
+                Score::Five,
+                b"Late feedback".to_vec().try_into().unwrap(),
+            ),
+            Error::<Test>::FeedbackPeriodEnded
+        );
+
+        assert_ok!(Deitos::cleanup_expired_agreement(
+            RuntimeOrigin::signed(CLEANER),
+            agreement_id
+        ));
+
+        // The agreement is archived as unrated and the IP rating is unchanged
+        assert_eq!(Agreements::<Test>::get(agreement_id), None);
+        let ip_details = InfrastructureProviders::<Test>::get(IP).unwrap();
+        assert_eq!(ip_details.unrated_agreements, 1);
+        assert_eq!(ip_details.rating.number_of_scores, 0);
+        assert_eq!(Deitos::ip_agreement_count(IP), 0);
+
+        // The service deposit is released, except for the cleanup reward
+        assert_eq!(
+            <Balances as fungible::InspectHold<_>>::total_balance_on_hold(&CONSUMER),
+            0
+        );
+        System::assert_has_event(RuntimeEvent::Deitos(Event::AgreementExpired {
+            agreement_id,
+            consumer_total_deposit: CONSUMER_SERVICE_DEPOSIT - CLEANUP_REWARD,
+            cleaned_up_by: Some(CLEANER),
+            reward: CLEANUP_REWARD,
+        }));
+    });
+}
+
+#[test]
+fn test_unrated_agreements_archived_on_idle() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+
+        register_and_activate_ip(IP, storage);
+        let agreement_id = create_completed_agreement(storage);
+
+        run_to_block(300);
+        Deitos::on_idle(300, Weight::MAX);
+        assert!(Agreements::<Test>::get(agreement_id).is_some());
+
+        run_to_block(301);
+        Deitos::on_idle(301, Weight::MAX);
+
+        assert_eq!(Agreements::<Test>::get(agreement_id), None);
+        assert_eq!(
+            InfrastructureProviders::<Test>::get(IP)
+                .unwrap()
+                .unrated_agreements,
+            1
+        );
+        System::assert_has_event(RuntimeEvent::Deitos(Event::AgreementExpired {
+            agreement_id,
+            consumer_total_deposit: CONSUMER_SERVICE_DEPOSIT,
+            cleaned_up_by: None,
+            reward: 0,
+        }));
+    });
+}
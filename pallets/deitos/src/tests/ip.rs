@@ -14,8 +14,13 @@
 // You should have received a copy of the GNU General Public License
 // along with Deitos Node.  If not, see <http://www.gnu.org/licenses/>.
 
-use frame_support::{assert_noop, traits::fungible};
+use frame_support::{
+    assert_noop,
+    traits::{fungible, Hooks},
+    weights::Weight,
+};
 use frame_system::pallet_prelude::BlockNumberFor;
+use sp_runtime::{DispatchError, Perbill};
 
 use crate::{
     pallet::{CurrentPrices, Error, Event, HoldReason, IPDepositAmount, InfrastructureProviders},
@@ -48,8 +53,10 @@ fn test_correct_ip_registration() {
             InfrastructureProviders::<Test>::get(IP).unwrap(),
             IPDetails::<Test> {
                 total_storage,
+                allocated_storage: 0,
+                total_compute: IP_COMPUTE,
+                allocated_compute: ComputeResources::default(),
                 status: IPStatus::Pending,
-                agreements: BoundedVec::new(),
                 deposit: IP_INITIAL_DEPOSIT,
                 rating: Rating {
                     cumulative_performance: 0,
@@ -57,12 +64,21 @@ fn test_correct_ip_registration() {
                     cumulative_support: 0,
                     number_of_scores: 0,
                 },
+                reputation: Reputation {
+                    last_decay_block: 1,
+                    ..Default::default()
+                },
+                disputes: DisputeRecord::default(),
+                reliability: ReliabilityRecord::default(),
+                min_consumer_rating: None,
+                unrated_agreements: 0,
             }
         );
 
         System::assert_has_event(RuntimeEvent::Deitos(Event::IPRegistered {
             ip: IP,
             total_storage,
+            total_compute: IP_COMPUTE,
         }));
     });
 }
@@ -76,7 +92,7 @@ fn test_fail_ip_registration_already_exists() {
 
         // Attempt to register the same IP again and expect failure
         assert_noop!(
-            Deitos::ip_register(RuntimeOrigin::signed(IP), total_storage),
+            Deitos::ip_register(RuntimeOrigin::signed(IP), total_storage, IP_COMPUTE),
             Error::<Test>::IPAlreadyExists
         );
     });
@@ -150,7 +166,10 @@ fn test_unregister_ip() {
         assert_eq!(initial_hold_balance, IP_INITIAL_DEPOSIT);
 
         // Act: Unregister the IP
-        assert_ok!(Deitos::ip_unregister(RuntimeOrigin::signed(IP)));
+        assert_ok!(Deitos::ip_unregister(
+            RuntimeOrigin::signed(IP),
+            Deitos::ip_agreement_count(IP)
+        ));
 
         // Assert: Check if the IP is marked as unregistered
         let ip_details = InfrastructureProviders::<Test>::get(IP).unwrap();
@@ -180,8 +199,10 @@ fn test_fail_ip_unregister_agreement_in_progress() {
             RuntimeOrigin::signed(CONSUMER),
             IP,
             storage,
+            ComputeResources::default(),
             activation_block,
             payment_plan.clone(),
+            None,
         ));
 
         // Accept agreement
@@ -193,7 +214,7 @@ fn test_fail_ip_unregister_agreement_in_progress() {
 
         // Attempt to unregister the IP and expect failure
         assert_noop!(
-            Deitos::ip_unregister(RuntimeOrigin::signed(IP)),
+            Deitos::ip_unregister(RuntimeOrigin::signed(IP), Deitos::ip_agreement_count(IP)),
             Error::<Test>::AgreementInProgress
         );
     });
@@ -226,6 +247,35 @@ fn test_update_ip_storage() {
     });
 }
 
+#[test]
+fn test_fail_update_ip_storage_below_allocated() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+        let activation_block: BlockNumberFor<Test> = 100;
+        let payment_plan: PaymentPlan<Test> = vec![activation_block + 100].try_into().unwrap();
+
+        register_and_activate_ip(IP, 1000);
+        create_accepted_agreement(IP, CONSUMER, storage, activation_block, payment_plan);
+
+        // The IP cannot shrink below the storage promised to the agreement
+        assert_noop!(
+            Deitos::ip_update_storage(RuntimeOrigin::signed(IP), storage - 1),
+            Error::<Test>::StorageBelowAllocated
+        );
+
+        // Shrinking down to the allocated storage is fine
+        assert_ok!(Deitos::ip_update_storage(
+            RuntimeOrigin::signed(IP),
+            storage
+        ));
+
+        let ip_details = InfrastructureProviders::<Test>::get(IP).unwrap();
+        assert_eq!(ip_details.total_storage, storage);
+        assert_eq!(ip_details.allocated_storage, storage);
+        assert_eq!(Deitos::ip_free_storage(&IP), Some(0));
+    });
+}
+
 #[test]
 fn test_register_unregister_register_ip() {
     new_test_ext().execute_with(|| {
@@ -238,7 +288,10 @@ fn test_register_unregister_register_ip() {
         assert_eq!(ip_details_initial.status, IPStatus::Pending);
 
         // Step 2: Unregister the IP
-        assert_ok!(Deitos::ip_unregister(RuntimeOrigin::signed(IP)));
+        assert_ok!(Deitos::ip_unregister(
+            RuntimeOrigin::signed(IP),
+            Deitos::ip_agreement_count(IP)
+        ));
 
         // Verify unregistration
         let ip_details_after_unreg = InfrastructureProviders::<Test>::get(IP).unwrap();
@@ -255,11 +308,13 @@ fn test_register_unregister_register_ip() {
         System::assert_has_event(RuntimeEvent::Deitos(Event::IPRegistered {
             ip: IP,
             total_storage: 10000000,
+            total_compute: IP_COMPUTE,
         }));
         System::assert_has_event(RuntimeEvent::Deitos(Event::IPUnregistered { ip: IP }));
         System::assert_has_event(RuntimeEvent::Deitos(Event::IPRegistered {
             ip: IP,
             total_storage: 10000000,
+            total_compute: IP_COMPUTE,
         }));
     });
 }
@@ -280,7 +335,10 @@ fn test_register_update_deposit_amount_unregister_ip() {
         IPDepositAmount::<Test>::put(updated_deposit_amount);
 
         // Step 3: Unregister the IP
-        assert_ok!(Deitos::ip_unregister(RuntimeOrigin::signed(IP)));
+        assert_ok!(Deitos::ip_unregister(
+            RuntimeOrigin::signed(IP),
+            Deitos::ip_agreement_count(IP)
+        ));
 
         // Verify that the original deposit amount is returned
         let final_balance = <Balances as fungible::Inspect<_>>::balance(&IP);
@@ -290,3 +348,343 @@ fn test_register_update_deposit_amount_unregister_ip() {
         System::assert_has_event(RuntimeEvent::Deitos(Event::IPUnregistered { ip: IP }));
     });
 }
+
+#[test]
+fn test_slash_ip_deposit() {
+    new_test_ext().execute_with(|| {
+        register_and_activate_ip(IP, 1000);
+
+        // Only the slash origin can slash IP deposits
+        assert_noop!(
+            Deitos::slash_ip_deposit(
+                RuntimeOrigin::signed(CONSUMER),
+                IP,
+                Perbill::from_percent(10),
+                Some(CONSUMER)
+            ),
+            DispatchError::BadOrigin
+        );
+
+        let consumer_balance_before = Balances::free_balance(CONSUMER);
+        assert_ok!(Deitos::slash_ip_deposit(
+            RuntimeOrigin::root(),
+            IP,
+            Perbill::from_percent(10),
+            Some(CONSUMER)
+        ));
+
+        // Half of the slashed deposit goes to the consumer and the rest to the treasury
+        let slashed = IP_INITIAL_DEPOSIT / 10;
+        assert_eq!(
+            Balances::free_balance(CONSUMER),
+            consumer_balance_before + slashed / 2
+        );
+        assert_eq!(Balances::free_balance(TREASURY), slashed / 2);
+        assert_eq!(
+            <Balances as fungible::InspectHold<_>>::balance_on_hold(
+                &HoldReason::IPInitialDeposit.into(),
+                &IP
+            ),
+            IP_INITIAL_DEPOSIT - slashed
+        );
+
+        // The IP goes back to pending because its deposit is below the required deposit
+        let ip_details = InfrastructureProviders::<Test>::get(IP).unwrap();
+        assert_eq!(ip_details.deposit, IP_INITIAL_DEPOSIT - slashed);
+        assert_eq!(ip_details.status, IPStatus::Pending);
+
+        System::assert_has_event(RuntimeEvent::Deitos(Event::IPStatusChanged {
+            ip: IP,
+            status: IPStatus::Pending,
+        }));
+        System::assert_last_event(RuntimeEvent::Deitos(Event::IPSlashed {
+            ip: IP,
+            slashed,
+            consumer: Some(CONSUMER),
+            consumer_amount: slashed / 2,
+            treasury_amount: slashed / 2,
+        }));
+
+        // Without an affected consumer, everything goes to the treasury
+        assert_ok!(<Deitos as IPPenaltyHandler<_, _>>::slash_ip(
+            &IP,
+            Perbill::from_percent(50),
+            None
+        ));
+        let slashed_again = (IP_INITIAL_DEPOSIT - slashed) / 2;
+        assert_eq!(
+            Balances::free_balance(TREASURY),
+            slashed / 2 + slashed_again
+        );
+        assert_eq!(
+            InfrastructureProviders::<Test>::get(IP).unwrap().deposit,
+            IP_INITIAL_DEPOSIT - slashed - slashed_again
+        );
+
+        // The remaining deposit is released when the IP unregisters
+        assert_ok!(Deitos::ip_unregister(
+            RuntimeOrigin::signed(IP),
+            Deitos::ip_agreement_count(IP)
+        ));
+        assert_eq!(
+            Balances::free_balance(IP),
+            INITIAL_BALANCE - slashed - slashed_again
+        );
+    });
+}
+
+#[test]
+fn test_ip_top_up_deposit() {
+    new_test_ext().execute_with(|| {
+        register_and_activate_ip(IP, 1000);
+        assert_ok!(Deitos::slash_ip_deposit(
+            RuntimeOrigin::root(),
+            IP,
+            Perbill::from_percent(10),
+            None
+        ));
+        let slashed = IP_INITIAL_DEPOSIT / 10;
+
+        // The IP cannot be activated again until its deposit is restored
+        assert_noop!(
+            Deitos::update_ip_status(RuntimeOrigin::root(), IP, IPStatus::Active),
+            Error::<Test>::IPDepositInsufficient
+        );
+
+        assert_ok!(Deitos::ip_top_up_deposit(RuntimeOrigin::signed(IP)));
+
+        let ip_details = InfrastructureProviders::<Test>::get(IP).unwrap();
+        assert_eq!(ip_details.deposit, IP_INITIAL_DEPOSIT);
+        assert_eq!(ip_details.status, IPStatus::Pending);
+        assert_eq!(
+            <Balances as fungible::InspectHold<_>>::balance_on_hold(
+                &HoldReason::IPInitialDeposit.into(),
+                &IP
+            ),
+            IP_INITIAL_DEPOSIT
+        );
+        System::assert_last_event(RuntimeEvent::Deitos(Event::IPDepositToppedUp {
+            ip: IP,
+            amount: slashed,
+            deposit: IP_INITIAL_DEPOSIT,
+        }));
+
+        assert_ok!(Deitos::update_ip_status(
+            RuntimeOrigin::root(),
+            IP,
+            IPStatus::Active
+        ));
+
+        // Unregistered IPs have to register again instead
+        assert_noop!(
+            Deitos::ip_top_up_deposit(RuntimeOrigin::signed(3)),
+            Error::<Test>::IPNotFound
+        );
+    });
+}
+
+#[test]
+fn test_ip_start_draining() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 1000;
+        let activation_block: BlockNumberFor<Test> = 100;
+        let payment_plan: PaymentPlan<Test> = vec![activation_block + 100].try_into().unwrap();
+
+        register_and_activate_ip(IP, storage);
+        let agreement_id =
+            create_accepted_agreement(IP, CONSUMER, 100, activation_block, payment_plan.clone());
+
+        // A pending request is rejected when the IP starts draining
+        assert_ok!(Deitos::consumer_request_agreement(
+            RuntimeOrigin::signed(CONSUMER),
+            IP,
+            100,
+            ComputeResources::default(),
+            activation_block,
+            payment_plan.clone(),
+            None,
+        ));
+        let pending_agreement_id = CurrentAgreementId::<Test>::get();
+
+        assert_ok!(Deitos::ip_start_draining(
+            RuntimeOrigin::signed(IP),
+            Deitos::ip_agreement_count(IP)
+        ));
+        assert_eq!(Deitos::get_agreement(pending_agreement_id), None);
+        assert_eq!(
+            InfrastructureProviders::<Test>::get(IP).unwrap().status,
+            IPStatus::Draining
+        );
+        System::assert_has_event(RuntimeEvent::Deitos(Event::IPStatusChanged {
+            ip: IP,
+            status: IPStatus::Draining,
+        }));
+
+        // A draining IP doesn't take on new agreements nor renewals
+        assert_noop!(
+            Deitos::consumer_request_agreement(
+                RuntimeOrigin::signed(CONSUMER),
+                IP,
+                100,
+                ComputeResources::default(),
+                activation_block,
+                payment_plan,
+                None,
+            ),
+            Error::<Test>::IPDraining
+        );
+        assert_noop!(
+            Deitos::propose_renewal(
+                RuntimeOrigin::signed(CONSUMER),
+                agreement_id,
+                vec![activation_block + 200].try_into().unwrap(),
+                false,
+            ),
+            Error::<Test>::IPDraining
+        );
+        assert_noop!(
+            Deitos::ip_start_draining(RuntimeOrigin::signed(IP), Deitos::ip_agreement_count(IP)),
+            Error::<Test>::IPDraining
+        );
+
+        // The IP keeps draining while its completed agreement waits for feedback
+        run_to_block(activation_block + 101);
+        Deitos::on_idle(System::block_number(), Weight::MAX);
+
+        assert_eq!(
+            Deitos::get_agreement(agreement_id).unwrap().status,
+            AgreementStatus::Completed
+        );
+        assert_eq!(
+            InfrastructureProviders::<Test>::get(IP).unwrap().status,
+            IPStatus::Draining
+        );
+        assert_eq!(
+            <Balances as fungible::InspectHold<_>>::balance_on_hold(
+                &HoldReason::IPInitialDeposit.into(),
+                &IP
+            ),
+            IP_INITIAL_DEPOSIT
+        );
+
+        // The IP is unregistered once its last agreement is closed
+        assert_ok!(Deitos::consumer_submit_feedback(
+            RuntimeOrigin::signed(CONSUMER),
+            agreement_id,
+            Score::Five,
+            Score::Five,
+            Score::Five,
+            Default::default(),
+        ));
+        assert_eq!(
+            InfrastructureProviders::<Test>::get(IP).unwrap().status,
+            IPStatus::Unregistered
+        );
+        assert_eq!(
+            <Balances as fungible::InspectHold<_>>::balance_on_hold(
+                &HoldReason::IPInitialDeposit.into(),
+                &IP
+            ),
+            0
+        );
+        System::assert_has_event(RuntimeEvent::Deitos(Event::IPUnregistered { ip: IP }));
+    });
+}
+
+#[test]
+fn test_ip_start_draining_without_agreements() {
+    new_test_ext().execute_with(|| {
+        register_and_activate_ip(IP, 1000);
+
+        // An IP without agreements is unregistered straight away
+        assert_ok!(Deitos::ip_start_draining(
+            RuntimeOrigin::signed(IP),
+            Deitos::ip_agreement_count(IP)
+        ));
+        assert_eq!(
+            InfrastructureProviders::<Test>::get(IP).unwrap().status,
+            IPStatus::Unregistered
+        );
+        assert_eq!(Balances::free_balance(IP), INITIAL_BALANCE);
+        System::assert_last_event(RuntimeEvent::Deitos(Event::IPUnregistered { ip: IP }));
+    });
+}
+
+#[test]
+fn test_ip_update_compute() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+        let compute = ComputeResources {
+            vcores: 4,
+            ram: 8192,
+        };
+        let activation_block: BlockNumberFor<Test> = 100;
+
+        register_and_activate_ip(IP, storage);
+        assert_ok!(Deitos::consumer_request_agreement(
+            RuntimeOrigin::signed(CONSUMER),
+            IP,
+            storage,
+            compute,
+            activation_block,
+            vec![activation_block + 100].try_into().unwrap(),
+            None,
+        ));
+
+        // The IP cannot shrink below the allocated compute resources
+        assert_noop!(
+            Deitos::ip_update_compute(
+                RuntimeOrigin::signed(IP),
+                ComputeResources {
+                    vcores: 2,
+                    ram: 8192,
+                }
+            ),
+            Error::<Test>::ComputeBelowAllocated
+        );
+
+        assert_ok!(Deitos::ip_update_compute(
+            RuntimeOrigin::signed(IP),
+            compute
+        ));
+        assert_eq!(
+            InfrastructureProviders::<Test>::get(IP)
+                .unwrap()
+                .total_compute,
+            compute
+        );
+
+        System::assert_has_event(RuntimeEvent::Deitos(Event::IPComputeUpdated {
+            ip: IP,
+            total_compute: compute,
+        }));
+    });
+}
+
+#[test]
+fn test_update_compute_cost_per_unit() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(Deitos::update_compute_cost_per_unit(
+            RuntimeOrigin::root(),
+            2000,
+            3,
+        ));
+
+        // The storage price is kept
+        let prices = CurrentPrices::<Test>::get();
+        assert_eq!(prices.storage_mb_per_block, PRICE_STORAGE);
+        assert_eq!(prices.vcore_per_block, 2000);
+        assert_eq!(prices.ram_mb_per_block, 3);
+
+        System::assert_has_event(RuntimeEvent::Deitos(Event::ComputePriceUnitUpdated {
+            price_vcore_per_block: 2000,
+            price_ram_mb_per_block: 3,
+        }));
+
+        // Only the network operator can update the prices
+        assert_noop!(
+            Deitos::update_compute_cost_per_unit(RuntimeOrigin::signed(IP), 1, 1),
+            sp_runtime::DispatchError::BadOrigin
+        );
+    });
+}
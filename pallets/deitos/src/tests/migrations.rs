@@ -0,0 +1,154 @@
+// Copyright (C) NC2D Labs.
+// This file is part of Deitos Node.
+
+// Deitos Node is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Deitos Node is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Deitos Node.  If not, see <http://www.gnu.org/licenses/>.
+
+use frame_support::{
+    storage::{unhashed, StoragePrefixedMap},
+    traits::{GetStorageVersion, OnRuntimeUpgrade, StorageVersion},
+    Blake2_128Concat, StorageHasher,
+};
+use parity_scale_codec::Encode;
+
+use crate::{
+    migrations::v1::MigrateToV1,
+    pallet::{
+        Agreements, ConsumerAgreements, CurrentPrices, ExpirationQueue, IPAgreements,
+        InfrastructureProviders,
+    },
+    types::*,
+};
+
+use super::*;
+
+fn put_old_agreement(
+    agreement_id: AgreementId,
+    status: AgreementStatus,
+    storage: StorageSizeMB,
+    activation_block: u64,
+) {
+    let payment_plan: PaymentPlan<Test> = vec![activation_block + 100].try_into().unwrap();
+    let payment_history = PaymentHistory::<Test> {
+        records: Default::default(),
+        next_transfer_installment_index: 0,
+    };
+    unhashed::put(
+        &Agreements::<Test>::hashed_key_for(agreement_id),
+        &(
+            IP,
+            CONSUMER,
+            CONSUMER_SERVICE_DEPOSIT,
+            CONSUMER_SERVICE_DEPOSIT,
+            false,
+            status,
+            storage,
+            activation_block,
+            payment_plan,
+            payment_history,
+        ),
+    );
+}
+
+fn put_old_state() {
+    StorageVersion::new(0).put::<Deitos>();
+    unhashed::put(&CurrentPrices::<Test>::hashed_key(), &PRICE_STORAGE);
+
+    put_old_agreement(1, AgreementStatus::Active, 100, 5);
+    put_old_agreement(2, AgreementStatus::ConsumerRequest, 50, 20);
+    put_old_agreement(3, AgreementStatus::Completed, 30, 5);
+
+    let rating = Rating {
+        cumulative_performance: 0,
+        cumulative_stability: 0,
+        cumulative_support: 0,
+        number_of_scores: 0,
+    };
+    unhashed::put(
+        &InfrastructureProviders::<Test>::hashed_key_for(IP),
+        &(
+            1000 as StorageSizeMB,
+            IPStatus::Active,
+            vec![1 as AgreementId, 2, 3],
+            IP_INITIAL_DEPOSIT,
+            rating,
+        ),
+    );
+
+    // The agreements of the consumers were stored in a vector under the same prefix
+    let mut key = ConsumerAgreements::<Test>::final_prefix().to_vec();
+    key.extend(Blake2_128Concat::hash(&CONSUMER.encode()));
+    unhashed::put(&key, &vec![1 as AgreementId, 2, 3]);
+}
+
+#[test]
+fn test_migrate_to_v1_over_several_blocks() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(10);
+        put_old_state();
+
+        // The runtime upgrade only migrates the prices
+        MigrateToV1::<Test>::on_runtime_upgrade();
+        assert_eq!(Deitos::on_chain_storage_version(), 1);
+        assert!(Deitos::is_migrating());
+        assert_eq!(
+            CurrentPrices::<Test>::get(),
+            Prices {
+                storage_mb_per_block: PRICE_STORAGE,
+                vcore_per_block: 0,
+                ram_mb_per_block: 0,
+            }
+        );
+
+        // The entries are migrated a few at a time
+        MigrateToV1::<Test>::step(2);
+        assert!(Deitos::is_migrating());
+        assert_eq!(
+            (1..=3)
+                .filter(|agreement_id| Agreements::<Test>::get(agreement_id).is_some())
+                .count(),
+            2
+        );
+
+        let mut blocks = 0;
+        while Deitos::is_migrating() {
+            MigrateToV1::<Test>::step(2);
+            blocks += 1;
+            assert!(blocks < 20, "the migration does not progress");
+        }
+
+        // The prices are locked into the agreements and the requests are scheduled to expire
+        let agreement = Agreements::<Test>::get(1).unwrap();
+        assert_eq!(agreement.prices.storage_mb_per_block, PRICE_STORAGE);
+        assert_eq!(agreement.compute, ComputeResources::default());
+        assert!(ExpirationQueue::<Test>::get(21).contains(&2));
+
+        // The IP tracks the storage of its agreements that have not completed
+        let ip_details = InfrastructureProviders::<Test>::get(IP).unwrap();
+        assert_eq!(ip_details.allocated_storage, 150);
+        assert_eq!(ip_details.status, IPStatus::Active);
+        assert_eq!(ip_details.deposit, IP_INITIAL_DEPOSIT);
+        assert_eq!(IPAgreements::<Test>::iter_prefix(IP).count(), 3);
+        assert_eq!(Deitos::ip_agreement_count(IP), 3);
+
+        // The agreements of the consumer are moved into the double map
+        let mut agreement_ids = Deitos::consumer_agreement_ids(&CONSUMER, None, u32::MAX);
+        agreement_ids.sort();
+        assert_eq!(agreement_ids, vec![1, 2, 3]);
+        assert_eq!(Deitos::consumer_agreement_count(CONSUMER), 3);
+
+        // The migration is not started again
+        MigrateToV1::<Test>::on_runtime_upgrade();
+        assert!(!Deitos::is_migrating());
+    });
+}
@@ -15,20 +15,34 @@
 // along with Deitos Node.  If not, see <http://www.gnu.org/licenses/>.
 
 use frame_support::{assert_ok, pallet_prelude::*, parameter_types, traits::ConstU64, PalletId};
+use frame_system::EnsureRoot;
 use sp_core::H256;
 use sp_keystore::{testing::MemoryKeystore, KeystoreExt};
 use sp_runtime::{
     traits::{BlakeTwo256, IdentityLookup},
-    BuildStorage,
+    BuildStorage, Perbill,
 };
 
 use crate as pallet_deitos;
-use crate::{CurrentAgreementId, IPStatus, PaymentPlan, StorageSizeMB};
+use crate::{
+    ComputeResources, CurrentAgreementId, CurrentOfferId, IPStatus, PaymentPlan, StorageSizeMB,
+};
 
 mod agreements;
+mod amendments;
+mod bidding;
+mod disputes;
+mod expiration;
 mod ip;
+mod migrations;
+mod offers;
 mod payments;
 mod rating;
+mod reliability;
+mod renewal;
+mod runtime_api;
+mod settlement;
+mod sla;
 
 type Block = frame_system::mocking::MockBlock<Test>;
 
@@ -44,18 +58,31 @@ frame_support::construct_runtime!(
 
 parameter_types! {
     pub const DeitosPalletId: PalletId = PalletId(*b"DeitosId");
+    pub const ConsumerTerminationPenalty: Perbill = Perbill::from_percent(10);
+    pub const IPCancellationPenalty: Perbill = Perbill::from_percent(10);
+    pub const LateFee: Perbill = Perbill::from_percent(10);
+    pub const SlashedDepositConsumerShare: Perbill = Perbill::from_percent(50);
+    pub const ReputationRetainedWeight: Perbill = Perbill::from_percent(50);
 }
 
 type AccountId = u64;
 type Balance = u64;
 type AgreementId = u32;
+type OfferId = u32;
 
 pub const IP_INITIAL_DEPOSIT: Balance = 1_000_000;
 pub const CONSUMER_SERVICE_DEPOSIT: Balance = 1_000;
 pub const PRICE_STORAGE: Balance = 10;
+pub const PRICE_VCORE: Balance = 1_000;
+pub const PRICE_RAM: Balance = 1;
+pub const IP_COMPUTE: ComputeResources = ComputeResources {
+    vcores: 8,
+    ram: 16_384,
+};
 pub const INITIAL_BALANCE: Balance = 1_000_000_000;
 pub const IP: AccountId = 1;
 pub const CONSUMER: AccountId = 2;
+pub const TREASURY: AccountId = 5;
 
 impl frame_system::Config for Test {
     type RuntimeEvent = RuntimeEvent;
@@ -106,9 +133,29 @@ impl pallet_deitos::Config for Test {
     type Currency = Balances;
     type RuntimeHoldReason = RuntimeHoldReason;
     type AgreementId = AgreementId;
+    type OfferId = OfferId;
     type PaymentPlanLimit = ConstU32<500>;
-    type IPAgreementsLimit = ConstU32<500>;
-    type ConsumerAgreementsLimit = ConstU32<500>;
+    type IPOffersLimit = ConstU32<100>;
+    type MaxEvidenceLength = ConstU32<128>;
+    type MaxCommentLength = ConstU32<256>;
+    type MaxSettlementsPerBlock = ConstU32<2>;
+    type AutoPayLeadBlocks = ConstU64<10>;
+    type ConsumerTerminationPenalty = ConsumerTerminationPenalty;
+    type IPCancellationPenalty = IPCancellationPenalty;
+    type PaymentGracePeriod = ConstU64<10>;
+    type LateFee = LateFee;
+    type FeedbackPeriod = ConstU64<100>;
+    type ReputationDecayPeriod = ConstU64<1000>;
+    type ReputationRetainedWeight = ReputationRetainedWeight;
+    type ReputationPriorWeight = ConstU64<1000>;
+    type ExpiredAgreementCleanupReward = ConstU64<100>;
+    type SlashedDepositConsumerShare = SlashedDepositConsumerShare;
+    type BiddingPeriod = ConstU64<10>;
+    type BidBond = ConstU64<100>;
+    type MaxBidsPerRequest = ConstU32<2>;
+    type TreasuryAccount = ConstU64<TREASURY>;
+    type DisputeOrigin = EnsureRoot<AccountId>;
+    type SlashOrigin = EnsureRoot<AccountId>;
     type PalletId = DeitosPalletId;
 }
 
@@ -133,6 +180,8 @@ pub fn new_test_ext() -> sp_io::TestExternalities {
         ip_initial_deposit: IP_INITIAL_DEPOSIT,
         consumer_service_deposit: CONSUMER_SERVICE_DEPOSIT,
         price_storage_mb_per_block: PRICE_STORAGE,
+        price_vcore_per_block: PRICE_VCORE,
+        price_ram_mb_per_block: PRICE_RAM,
     }
     .assimilate_storage(&mut t)
     .unwrap();
@@ -156,7 +205,8 @@ pub fn run_to_block(n: u64) {
 pub fn register_ip(ip: AccountId, total_storage: StorageSizeMB) {
     assert_ok!(Deitos::ip_register(
         RuntimeOrigin::signed(ip),
-        total_storage
+        total_storage,
+        IP_COMPUTE,
     ));
 }
 
@@ -181,8 +231,10 @@ fn create_accepted_agreement(
         RuntimeOrigin::signed(consumer),
         ip,
         storage,
+        ComputeResources::default(),
         activation_block,
         payment_plan,
+        None,
     ));
 
     let agreement_id = CurrentAgreementId::<Test>::get();
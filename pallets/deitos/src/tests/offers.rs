@@ -0,0 +1,305 @@
+// Copyright (C) NC2D Labs.
+// This file is part of Deitos Node.
+
+// Deitos Node is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Deitos Node is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Deitos Node.  If not, see <http://www.gnu.org/licenses/>.
+
+use frame_support::assert_noop;
+use frame_system::pallet_prelude::BlockNumberFor;
+
+use crate::{
+    pallet::{Agreements, Error, Event, IPOffers, Offers},
+    types::*,
+};
+
+use super::*;
+
+const OFFER_PRICE_STORAGE: Balance = 5;
+
+fn offer_terms(auto_accept: bool) -> OfferTerms<Test> {
+    OfferTerms {
+        prices: Prices {
+            storage_mb_per_block: OFFER_PRICE_STORAGE,
+            vcore_per_block: PRICE_VCORE,
+            ram_mb_per_block: PRICE_RAM,
+        },
+        min_storage: 10,
+        max_storage: 100,
+        min_duration: 100,
+        max_installment_period: 100,
+        auto_accept,
+    }
+}
+
+fn create_offer(ip: AccountId, terms: OfferTerms<Test>) -> OfferId {
+    assert_ok!(Deitos::ip_create_offer(RuntimeOrigin::signed(ip), terms));
+    CurrentOfferId::<Test>::get()
+}
+
+#[test]
+fn test_ip_create_offer() {
+    new_test_ext().execute_with(|| {
+        register_ip(IP, 1000);
+
+        let terms = offer_terms(false);
+        let offer_id = create_offer(IP, terms.clone());
+
+        assert_eq!(
+            Offers::<Test>::get(offer_id),
+            Some(OfferDetails {
+                ip: IP,
+                terms: terms.clone(),
+            })
+        );
+        assert_eq!(IPOffers::<Test>::get(IP), vec![offer_id]);
+
+        System::assert_has_event(RuntimeEvent::Deitos(Event::IPOfferCreated {
+            offer_id,
+            ip: IP,
+            terms,
+        }));
+    });
+}
+
+#[test]
+fn test_ip_create_offer_fails() {
+    new_test_ext().execute_with(|| {
+        // The IP must be registered
+        assert_noop!(
+            Deitos::ip_create_offer(RuntimeOrigin::signed(IP), offer_terms(false)),
+            Error::<Test>::IPNotFound
+        );
+
+        register_ip(IP, 1000);
+
+        // The storage range must not be empty
+        let mut terms = offer_terms(false);
+        terms.min_storage = terms.max_storage + 1;
+        assert_noop!(
+            Deitos::ip_create_offer(RuntimeOrigin::signed(IP), terms),
+            Error::<Test>::OfferInvalid
+        );
+
+        // Installments must last at least one block
+        let mut terms = offer_terms(false);
+        terms.max_installment_period = 0;
+        assert_noop!(
+            Deitos::ip_create_offer(RuntimeOrigin::signed(IP), terms),
+            Error::<Test>::OfferInvalid
+        );
+    });
+}
+
+#[test]
+fn test_ip_update_and_remove_offer() {
+    new_test_ext().execute_with(|| {
+        register_ip(IP, 1000);
+        let offer_id = create_offer(IP, offer_terms(false));
+
+        // Only the IP that published the offer can update or remove it
+        assert_noop!(
+            Deitos::ip_update_offer(RuntimeOrigin::signed(CONSUMER), offer_id, offer_terms(true)),
+            Error::<Test>::OfferNotFound
+        );
+        assert_noop!(
+            Deitos::ip_remove_offer(RuntimeOrigin::signed(CONSUMER), offer_id),
+            Error::<Test>::OfferNotFound
+        );
+
+        assert_ok!(Deitos::ip_update_offer(
+            RuntimeOrigin::signed(IP),
+            offer_id,
+            offer_terms(true)
+        ));
+        assert!(Offers::<Test>::get(offer_id).unwrap().terms.auto_accept);
+
+        assert_ok!(Deitos::ip_remove_offer(RuntimeOrigin::signed(IP), offer_id));
+        assert_eq!(Offers::<Test>::get(offer_id), None);
+        assert!(IPOffers::<Test>::get(IP).is_empty());
+
+        System::assert_has_event(RuntimeEvent::Deitos(Event::IPOfferRemoved {
+            offer_id,
+            ip: IP,
+        }));
+    });
+}
+
+#[test]
+fn test_ip_unregister_removes_offers() {
+    new_test_ext().execute_with(|| {
+        register_ip(IP, 1000);
+        let offer_id = create_offer(IP, offer_terms(false));
+
+        assert_ok!(Deitos::ip_unregister(
+            RuntimeOrigin::signed(IP),
+            Deitos::ip_agreement_count(IP)
+        ));
+
+        assert_eq!(Offers::<Test>::get(offer_id), None);
+        assert!(IPOffers::<Test>::get(IP).is_empty());
+    });
+}
+
+#[test]
+fn test_consumer_request_offer_agreement() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 50;
+        let activation_block: BlockNumberFor<Test> = 100;
+        let payment_plan: PaymentPlan<Test> = vec![activation_block + 100, activation_block + 200]
+            .try_into()
+            .unwrap();
+
+        register_and_activate_ip(IP, 1000);
+        let offer_id = create_offer(IP, offer_terms(false));
+
+        assert_ok!(Deitos::consumer_request_offer_agreement(
+            RuntimeOrigin::signed(CONSUMER),
+            offer_id,
+            storage,
+            ComputeResources::default(),
+            activation_block,
+            payment_plan,
+            None,
+        ));
+
+        // The offer prices are locked in the agreement, which waits for the IP
+        let agreement_id = CurrentAgreementId::<Test>::get();
+        let agreement = Agreements::<Test>::get(agreement_id).unwrap();
+        assert_eq!(agreement.status, AgreementStatus::ConsumerRequest);
+        assert_eq!(agreement.prices.storage_mb_per_block, OFFER_PRICE_STORAGE);
+        assert_eq!(
+            agreement.consumer_security_deposit,
+            100 * storage * OFFER_PRICE_STORAGE
+        );
+    });
+}
+
+#[test]
+fn test_consumer_request_offer_agreement_auto_accept() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 50;
+        let activation_block: BlockNumberFor<Test> = 100;
+        let payment_plan: PaymentPlan<Test> = vec![activation_block + 100, activation_block + 200]
+            .try_into()
+            .unwrap();
+
+        register_and_activate_ip(IP, 1000);
+        let offer_id = create_offer(IP, offer_terms(true));
+
+        assert_ok!(Deitos::consumer_request_offer_agreement(
+            RuntimeOrigin::signed(CONSUMER),
+            offer_id,
+            storage,
+            ComputeResources::default(),
+            activation_block,
+            payment_plan,
+            None,
+        ));
+
+        // The agreement is active without the IP accepting it
+        let agreement_id = CurrentAgreementId::<Test>::get();
+        let agreement = Agreements::<Test>::get(agreement_id).unwrap();
+        assert_eq!(agreement.status, AgreementStatus::Active);
+
+        System::assert_has_event(RuntimeEvent::Deitos(Event::IPAcceptedAgreement {
+            agreement_id,
+            ip: IP,
+            consumer: CONSUMER,
+        }));
+
+        // The consumer can prepay at the offer price
+        assert_ok!(Deitos::consumer_prepay_installment(
+            RuntimeOrigin::signed(CONSUMER),
+            agreement_id,
+        ));
+        assert_eq!(
+            Agreements::<Test>::get(agreement_id)
+                .unwrap()
+                .payment_history
+                .records,
+            vec![PaymentRecord {
+                amount: 100 * storage * OFFER_PRICE_STORAGE,
+                transferred: false
+            }]
+        );
+    });
+}
+
+#[test]
+fn test_consumer_request_offer_agreement_terms_not_met() {
+    new_test_ext().execute_with(|| {
+        let activation_block: BlockNumberFor<Test> = 100;
+        let payment_plan: PaymentPlan<Test> = vec![activation_block + 100, activation_block + 200]
+            .try_into()
+            .unwrap();
+
+        register_and_activate_ip(IP, 1000);
+        let offer_id = create_offer(IP, offer_terms(true));
+
+        // Storage out of the offer range
+        assert_noop!(
+            Deitos::consumer_request_offer_agreement(
+                RuntimeOrigin::signed(CONSUMER),
+                offer_id,
+                500,
+                ComputeResources::default(),
+                activation_block,
+                payment_plan.clone(),
+                None,
+            ),
+            Error::<Test>::OfferTermsNotMet
+        );
+
+        // Agreement shorter than the minimum duration
+        assert_noop!(
+            Deitos::consumer_request_offer_agreement(
+                RuntimeOrigin::signed(CONSUMER),
+                offer_id,
+                50,
+                ComputeResources::default(),
+                activation_block,
+                vec![activation_block + 50].try_into().unwrap(),
+                None,
+            ),
+            Error::<Test>::OfferTermsNotMet
+        );
+
+        // Installment longer than allowed
+        assert_noop!(
+            Deitos::consumer_request_offer_agreement(
+                RuntimeOrigin::signed(CONSUMER),
+                offer_id,
+                50,
+                ComputeResources::default(),
+                activation_block,
+                vec![activation_block + 200].try_into().unwrap(),
+                None,
+            ),
+            Error::<Test>::OfferTermsNotMet
+        );
+
+        // Unknown offer
+        assert_noop!(
+            Deitos::consumer_request_offer_agreement(
+                RuntimeOrigin::signed(CONSUMER),
+                offer_id + 1,
+                50,
+                ComputeResources::default(),
+                activation_block,
+                payment_plan,
+                None,
+            ),
+            Error::<Test>::OfferNotFound
+        );
+    });
+}
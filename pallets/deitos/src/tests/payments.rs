@@ -14,7 +14,11 @@
 // You should have received a copy of the GNU General Public License
 // along with Deitos Node.  If not, see <http://www.gnu.org/licenses/>.
 
-use frame_support::{assert_noop, traits::fungible};
+use frame_support::{
+    assert_noop,
+    traits::{fungible, Hooks},
+    weights::Weight,
+};
 use frame_system::pallet_prelude::BlockNumberFor;
 
 use crate::{
@@ -84,6 +88,42 @@ fn test_consumer_prepay_installment() {
     });
 }
 
+#[test]
+fn test_no_overdue_installments_before_activation() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+        let activation_block: BlockNumberFor<Test> = 100;
+        let payment_plan: PaymentPlan<Test> = vec![
+            activation_block + 100,
+            activation_block + 200,
+            activation_block + 300,
+        ]
+        .try_into()
+        .unwrap();
+
+        register_and_activate_ip(IP, storage);
+        let agreement_id =
+            create_accepted_agreement(IP, CONSUMER, storage, activation_block, payment_plan);
+
+        // Nothing is due before activation
+        let agreement = Agreements::<Test>::get(agreement_id).unwrap();
+        assert!(!agreement.has_overdue_installments(1));
+        assert!(!agreement.has_overdue_installments(activation_block - 1));
+        assert!(agreement.has_overdue_installments(activation_block));
+        assert!(!Deitos::is_agreement_suspended(&agreement_id));
+
+        // The first installment can be prepaid before activation
+        assert_ok!(Deitos::consumer_prepay_installment(
+            RuntimeOrigin::signed(CONSUMER),
+            agreement_id,
+        ));
+        let agreement = Agreements::<Test>::get(agreement_id).unwrap();
+        assert_eq!(agreement.payment_history.records.len(), 1);
+        assert!(!agreement.has_overdue_installments(activation_block + 100));
+        assert!(agreement.has_overdue_installments(activation_block + 101));
+    });
+}
+
 #[test]
 fn test_consumer_prepay_multiple() {
     new_test_ext().execute_with(|| {
@@ -296,6 +336,9 @@ fn test_ip_withdraw_completely() {
         assert!(stored_agreement.consumer_security_deposit_transferred);
         assert_eq!(stored_agreement.status, AgreementStatus::Completed);
 
+        // Verify that the storage of the completed agreement is released
+        assert_eq!(Deitos::ip_free_storage(&IP), Some(storage));
+
         // Verify that the IP's balance is properly updated
         let total_cost = 600 * PRICE_STORAGE * storage;
         assert_eq!(Balances::free_balance(IP), balance_before + total_cost);
@@ -347,15 +390,17 @@ fn test_ip_terminate_nonpay() {
 
         let balance_before = Balances::free_balance(IP);
 
-        // IP terminates the agreement because the second installment is not prepaid
-        run_to_block(activation_block + 101);
+        // IP terminates the agreement because the second installment is not prepaid once the
+        // grace period is over
+        run_to_block(activation_block + 111);
         assert_ok!(Deitos::ip_terminate_nonpay(
             RuntimeOrigin::signed(IP),
             agreement_id,
         ));
 
-        // Verify that the agreement is removed
+        // Verify that the agreement is removed and its storage is released
         assert_eq!(Agreements::<Test>::get(agreement_id), None);
+        assert_eq!(Deitos::ip_free_storage(&IP), Some(storage));
 
         // Verify that the IP's balance is properly updated
         let transferred = 400 * PRICE_STORAGE * storage + CONSUMER_SERVICE_DEPOSIT;
@@ -369,3 +414,213 @@ fn test_ip_terminate_nonpay() {
         }));
     });
 }
+
+#[test]
+fn test_grace_period_and_late_fee() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+        let activation_block: BlockNumberFor<Test> = 100;
+        let payment_plan: PaymentPlan<Test> = vec![
+            activation_block + 100,
+            activation_block + 200,
+            activation_block + 300,
+        ]
+        .try_into()
+        .unwrap();
+
+        register_and_activate_ip(IP, storage);
+        let agreement_id =
+            create_accepted_agreement(IP, CONSUMER, storage, activation_block, payment_plan);
+
+        // Consumer prepays the first installment only
+        assert_ok!(Deitos::consumer_prepay_installment(
+            RuntimeOrigin::signed(CONSUMER),
+            agreement_id,
+        ));
+
+        // The agreement is suspended when the first installment is settled
+        run_to_block(activation_block + 101);
+        Deitos::on_idle(System::block_number(), Weight::MAX);
+        assert_eq!(
+            Agreements::<Test>::get(agreement_id).unwrap().status,
+            AgreementStatus::Suspended
+        );
+        assert!(Deitos::is_agreement_suspended(&agreement_id));
+
+        // The IP can't terminate the agreement within the grace period
+        run_to_block(activation_block + 110);
+        assert_noop!(
+            Deitos::ip_terminate_nonpay(RuntimeOrigin::signed(IP), agreement_id),
+            Error::<Test>::GracePeriodNotExpired
+        );
+
+        // Consumer pays the overdue installment together with the late fee
+        let ip_balance_before = Balances::free_balance(IP);
+        assert_ok!(Deitos::consumer_prepay_installment(
+            RuntimeOrigin::signed(CONSUMER),
+            agreement_id,
+        ));
+
+        let late_fee = 100 * PRICE_STORAGE * storage / 10;
+        assert_eq!(Balances::free_balance(IP), ip_balance_before + late_fee);
+        System::assert_has_event(RuntimeEvent::Deitos(Event::ConsumerPaidLateFee {
+            agreement_id,
+            consumer: CONSUMER,
+            late_fee,
+        }));
+
+        // The agreement is resumed, so the IP can no longer terminate it
+        assert_eq!(
+            Agreements::<Test>::get(agreement_id).unwrap().status,
+            AgreementStatus::Active
+        );
+        assert!(!Deitos::is_agreement_suspended(&agreement_id));
+
+        run_to_block(activation_block + 111);
+        assert_noop!(
+            Deitos::ip_terminate_nonpay(RuntimeOrigin::signed(IP), agreement_id),
+            Error::<Test>::NoUnpaidInstallments
+        );
+    });
+}
+
+#[test]
+fn test_consumer_terminate_agreement() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+        let activation_block: BlockNumberFor<Test> = 100;
+        let payment_plan: PaymentPlan<Test> = vec![
+            activation_block + 100,
+            activation_block + 200,
+            activation_block + 300,
+        ]
+        .try_into()
+        .unwrap();
+
+        register_and_activate_ip(IP, storage);
+        let agreement_id = create_accepted_agreement(
+            IP,
+            CONSUMER,
+            storage,
+            activation_block,
+            payment_plan.clone(),
+        );
+
+        // Only active agreements can be terminated, and only by the consumer
+        assert_noop!(
+            Deitos::consumer_terminate_agreement(RuntimeOrigin::signed(IP), agreement_id),
+            Error::<Test>::AgreementNotFound
+        );
+
+        // Consumer prepays the first two installments
+        for _ in 0..2 {
+            assert_ok!(Deitos::consumer_prepay_installment(
+                RuntimeOrigin::signed(CONSUMER),
+                agreement_id,
+            ));
+        }
+
+        let ip_balance_before = Balances::free_balance(IP);
+        let consumer_balance_before = Balances::free_balance(CONSUMER);
+
+        // Consumer terminates the agreement in the middle of the first installment
+        run_to_block(activation_block + 50);
+        assert_ok!(Deitos::consumer_terminate_agreement(
+            RuntimeOrigin::signed(CONSUMER),
+            agreement_id,
+        ));
+
+        // Verify that the agreement is removed and its storage is released
+        assert_eq!(Agreements::<Test>::get(agreement_id), None);
+        assert_eq!(Deitos::consumer_agreement_count(CONSUMER), 0);
+        assert_eq!(Deitos::ip_free_storage(&IP), Some(storage));
+
+        // The IP gets half of the first installment and 10% of the security deposit
+        let installment_cost = 100 * PRICE_STORAGE * storage;
+        let transferred = installment_cost / 2 + installment_cost / 10;
+        let refunded = 3 * installment_cost + CONSUMER_SERVICE_DEPOSIT - transferred;
+        assert_eq!(Balances::free_balance(IP), ip_balance_before + transferred);
+        assert_eq!(
+            Balances::free_balance(CONSUMER),
+            consumer_balance_before + refunded
+        );
+        assert_eq!(
+            <Balances as fungible::InspectHold<_>>::total_balance_on_hold(&CONSUMER),
+            0
+        );
+
+        System::assert_has_event(RuntimeEvent::Deitos(Event::ConsumerTerminatedAgreement {
+            agreement_id,
+            ip: IP,
+            consumer: CONSUMER,
+            transferred,
+            refunded,
+        }));
+    });
+}
+
+#[test]
+fn test_consumer_terminate_agreement_not_active() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+
+        register_and_activate_ip(IP, storage);
+        assert_ok!(Deitos::consumer_request_agreement(
+            RuntimeOrigin::signed(CONSUMER),
+            IP,
+            storage,
+            ComputeResources::default(),
+            100,
+            vec![200].try_into().unwrap(),
+            None,
+        ));
+        let agreement_id = CurrentAgreementId::<Test>::get();
+
+        assert_noop!(
+            Deitos::consumer_terminate_agreement(RuntimeOrigin::signed(CONSUMER), agreement_id),
+            Error::<Test>::AgreementStatusInvalid
+        );
+    });
+}
+
+#[test]
+fn test_price_update_does_not_affect_existing_agreement() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+        let activation_block: BlockNumberFor<Test> = 100;
+        let payment_plan: PaymentPlan<Test> = vec![activation_block + 100, activation_block + 300]
+            .try_into()
+            .unwrap();
+
+        register_and_activate_ip(IP, storage);
+        let agreement_id = create_accepted_agreement(
+            IP,
+            CONSUMER,
+            storage,
+            activation_block,
+            payment_plan.clone(),
+        );
+
+        // The network operator doubles the storage price
+        assert_ok!(Deitos::update_storage_cost_per_unit(
+            RuntimeOrigin::root(),
+            PRICE_STORAGE * 2,
+        ));
+
+        // Consumer prepays the first installment at the locked price
+        assert_ok!(Deitos::consumer_prepay_installment(
+            RuntimeOrigin::signed(CONSUMER),
+            agreement_id,
+        ));
+
+        let stored_agreement = Agreements::<Test>::get(agreement_id).unwrap();
+        assert_eq!(stored_agreement.prices.storage_mb_per_block, PRICE_STORAGE);
+        assert_eq!(
+            stored_agreement.payment_history.records,
+            vec![PaymentRecord {
+                amount: 100 * PRICE_STORAGE * storage,
+                transferred: false
+            }]
+        );
+    });
+}
@@ -14,11 +14,17 @@
 // You should have received a copy of the GNU General Public License
 // along with Deitos Node.  If not, see <http://www.gnu.org/licenses/>.
 
-use frame_support::traits::fungible;
+use frame_support::{
+    assert_noop,
+    traits::{fungible, Hooks},
+    weights::Weight,
+};
 use frame_system::pallet_prelude::BlockNumberFor;
 
 use crate::{
-    pallet::{Agreements, Event, HoldReason},
+    pallet::{
+        Agreements, ConsumerProfiles, Error, Event, HoldReason, PendingConsumerRatings, Reviews,
+    },
     types::*,
     InfrastructureProviders,
 };
@@ -48,14 +54,14 @@ fn test_consumer_submit_feedback() {
         ));
 
         // Consumer submits feedback
-        let comment = "Good service";
+        let comment: Comment<Test> = b"Good service".to_vec().try_into().unwrap();
         assert_ok!(Deitos::consumer_submit_feedback(
             RuntimeOrigin::signed(CONSUMER),
             agreement_id,
             Score::One,
             Score::Three,
             Score::Five,
-            comment.to_owned(),
+            comment.clone(),
         ));
 
         // Verify that the agreement is removed
@@ -72,6 +78,25 @@ fn test_consumer_submit_feedback() {
                 number_of_scores: 1
             }
         );
+        assert_eq!(
+            ip_details.reputation.total_weight,
+            (100 * PRICE_STORAGE * storage) as u128
+        );
+
+        // Verify that the review is stored in the review log of the IP
+        assert_eq!(Deitos::review_count(IP), 1);
+        assert_eq!(
+            Reviews::<Test>::get(IP, 0),
+            Some(Review {
+                agreement_id,
+                consumer: CONSUMER,
+                score_performance: Score::One,
+                score_stability: Score::Three,
+                score_support: Score::Five,
+                comment,
+                block_number: activation_block + 101,
+            })
+        );
 
         // Verify that the consumer is refunded
         assert_eq!(
@@ -95,7 +120,297 @@ fn test_consumer_submit_feedback() {
             score_performance: Score::One,
             score_stability: Score::Three,
             score_support: Score::Five,
-            comment: comment.to_owned(),
+            review_index: 0,
         }));
     });
 }
+
+#[test]
+fn test_ip_reputation_weighted_and_decayed() {
+    new_test_ext().execute_with(|| {
+        let activation_block: BlockNumberFor<Test> = 100;
+        let payment_plan: PaymentPlan<Test> = vec![activation_block + 100].try_into().unwrap();
+
+        register_and_activate_ip(IP, 200);
+        // A cheap agreement rated with the lowest scores and a valuable one rated with the
+        // highest scores
+        let cheap_agreement_id =
+            create_accepted_agreement(IP, CONSUMER, 1, activation_block, payment_plan.clone());
+        let valuable_agreement_id =
+            create_accepted_agreement(IP, 3, 100, activation_block, payment_plan);
+
+        run_to_block(activation_block + 101);
+        for (consumer, agreement_id, score) in [
+            (CONSUMER, cheap_agreement_id, Score::One),
+            (3, valuable_agreement_id, Score::Five),
+        ] {
+            assert_ok!(Deitos::ip_withdraw_installments(
+                RuntimeOrigin::signed(IP),
+                agreement_id,
+            ));
+            assert_ok!(Deitos::consumer_submit_feedback(
+                RuntimeOrigin::signed(consumer),
+                agreement_id,
+                score,
+                score,
+                score,
+                Default::default(),
+            ));
+        }
+
+        // The scores are weighted by the amount paid for the agreements
+        let ip_details = InfrastructureProviders::<Test>::get(IP).unwrap();
+        assert_eq!(ip_details.reputation.total_weight, 101_000);
+        assert_eq!(ip_details.rating.average().unwrap().performance, 300);
+        assert_eq!(
+            ip_details.current_reputation().average(2),
+            Some(AverageRating {
+                performance: 496,
+                stability: 496,
+                support: 496,
+                number_of_scores: 2,
+            })
+        );
+        assert_eq!(ip_details.reputation_score(), 494);
+
+        // After a decay period only half of the weights are retained, so the reputation score
+        // moves towards neutral
+        run_to_block(1001);
+        let ip_details = InfrastructureProviders::<Test>::get(IP).unwrap();
+        assert_eq!(ip_details.current_reputation().total_weight, 50_500);
+        assert_eq!(ip_details.reputation_score(), 492);
+    });
+}
+
+/// Creates an agreement that is never paid and lets the IP terminate it due to non-payment.
+fn create_terminated_agreement(ip: AccountId, consumer: AccountId) -> AgreementId {
+    let storage: StorageSizeMB = 100;
+    let activation_block = System::block_number() + 100;
+    let payment_plan: PaymentPlan<Test> = vec![activation_block + 100, activation_block + 200]
+        .try_into()
+        .unwrap();
+
+    let agreement_id =
+        create_accepted_agreement(ip, consumer, storage, activation_block, payment_plan);
+
+    run_to_block(activation_block + 11);
+    assert_ok!(Deitos::ip_terminate_nonpay(
+        RuntimeOrigin::signed(ip),
+        agreement_id,
+    ));
+
+    agreement_id
+}
+
+#[test]
+fn test_ip_rate_consumer_completed() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+        let activation_block: BlockNumberFor<Test> = 100;
+        let payment_plan: PaymentPlan<Test> = vec![activation_block + 100].try_into().unwrap();
+
+        register_and_activate_ip(IP, storage);
+        let agreement_id =
+            create_accepted_agreement(IP, CONSUMER, storage, activation_block, payment_plan);
+
+        // The consumer cannot be rated before the agreement is completed
+        assert_noop!(
+            Deitos::ip_rate_consumer(RuntimeOrigin::signed(IP), agreement_id, Score::Four),
+            Error::<Test>::ConsumerRatingNotFound
+        );
+
+        run_to_block(activation_block + 101);
+        assert_ok!(Deitos::ip_withdraw_installments(
+            RuntimeOrigin::signed(IP),
+            agreement_id,
+        ));
+
+        // Only the IP of the agreement can rate the consumer
+        assert_noop!(
+            Deitos::ip_rate_consumer(RuntimeOrigin::signed(3), agreement_id, Score::Four),
+            Error::<Test>::ConsumerRatingNotFound
+        );
+
+        assert_ok!(Deitos::ip_rate_consumer(
+            RuntimeOrigin::signed(IP),
+            agreement_id,
+            Score::Four
+        ));
+
+        // Verify that the rating is stored
+        let profile = ConsumerProfiles::<Test>::get(CONSUMER);
+        assert_eq!(
+            profile.rating,
+            ConsumerRating {
+                cumulative_score: 4,
+                number_of_scores: 1
+            }
+        );
+        assert_eq!(profile.rating.average(), Some(400));
+        assert_eq!(profile.nonpay_terminations, 0);
+        assert_eq!(PendingConsumerRatings::<Test>::get(agreement_id), None);
+
+        // The consumer can be rated only once per agreement
+        assert_noop!(
+            Deitos::ip_rate_consumer(RuntimeOrigin::signed(IP), agreement_id, Score::One),
+            Error::<Test>::ConsumerRatingNotFound
+        );
+
+        // Check for the correct event emission
+        System::assert_has_event(RuntimeEvent::Deitos(Event::IPRatedConsumer {
+            agreement_id,
+            ip: IP,
+            consumer: CONSUMER,
+            score: Score::Four,
+        }));
+    });
+}
+
+#[test]
+fn test_ip_rate_consumer_terminated_nonpay() {
+    new_test_ext().execute_with(|| {
+        register_and_activate_ip(IP, 100);
+        let agreement_id = create_terminated_agreement(IP, CONSUMER);
+
+        // The termination is recorded in the consumer profile
+        assert_eq!(
+            ConsumerProfiles::<Test>::get(CONSUMER).nonpay_terminations,
+            1
+        );
+
+        assert_ok!(Deitos::ip_rate_consumer(
+            RuntimeOrigin::signed(IP),
+            agreement_id,
+            Score::One
+        ));
+        assert_eq!(
+            ConsumerProfiles::<Test>::get(CONSUMER).rating.average(),
+            Some(100)
+        );
+    });
+}
+
+#[test]
+fn test_consumer_rating_expires() {
+    new_test_ext().execute_with(|| {
+        register_and_activate_ip(IP, 100);
+        let agreement_id = create_terminated_agreement(IP, CONSUMER);
+
+        // The feedback window of a terminated agreement starts at termination
+        let deadline = System::block_number() + 100;
+        assert_eq!(
+            PendingConsumerRatings::<Test>::get(agreement_id)
+                .unwrap()
+                .deadline,
+            deadline
+        );
+
+        run_to_block(deadline + 1);
+        assert_noop!(
+            Deitos::ip_rate_consumer(RuntimeOrigin::signed(IP), agreement_id, Score::One),
+            Error::<Test>::FeedbackPeriodEnded
+        );
+
+        // The pending rating is removed once the feedback window closes
+        Deitos::on_idle(deadline + 1, Weight::MAX);
+        assert_eq!(PendingConsumerRatings::<Test>::get(agreement_id), None);
+
+        // The pending rating of a completed agreement expires with the agreement
+        let activation_block = System::block_number() + 10;
+        let payment_plan: PaymentPlan<Test> = vec![activation_block + 100].try_into().unwrap();
+        let agreement_id =
+            create_accepted_agreement(IP, CONSUMER, 100, activation_block, payment_plan);
+        run_to_block(activation_block + 101);
+        assert_ok!(Deitos::ip_withdraw_installments(
+            RuntimeOrigin::signed(IP),
+            agreement_id,
+        ));
+
+        let deadline = Agreements::<Test>::get(agreement_id)
+            .unwrap()
+            .feedback_deadline();
+        run_to_block(deadline + 1);
+        Deitos::on_idle(deadline + 1, Weight::MAX);
+        assert_eq!(Agreements::<Test>::get(agreement_id), None);
+        assert_eq!(PendingConsumerRatings::<Test>::get(agreement_id), None);
+    });
+}
+
+#[test]
+fn test_min_consumer_rating() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+        register_and_activate_ip(IP, 2 * storage);
+
+        // The minimum rating cannot exceed the highest score
+        assert_noop!(
+            Deitos::ip_set_min_consumer_rating(RuntimeOrigin::signed(IP), Some(501)),
+            Error::<Test>::MinConsumerRatingInvalid
+        );
+
+        let agreement_id = create_terminated_agreement(IP, CONSUMER);
+        assert_ok!(Deitos::ip_rate_consumer(
+            RuntimeOrigin::signed(IP),
+            agreement_id,
+            Score::Two
+        ));
+
+        assert_ok!(Deitos::ip_set_min_consumer_rating(
+            RuntimeOrigin::signed(IP),
+            Some(300)
+        ));
+        assert_eq!(
+            InfrastructureProviders::<Test>::get(IP)
+                .unwrap()
+                .min_consumer_rating,
+            Some(300)
+        );
+        System::assert_has_event(RuntimeEvent::Deitos(Event::IPMinConsumerRatingUpdated {
+            ip: IP,
+            min_consumer_rating: Some(300),
+        }));
+
+        let activation_block = System::block_number() + 100;
+        let payment_plan: PaymentPlan<Test> = vec![activation_block + 100].try_into().unwrap();
+
+        // The consumer rating is below the minimum
+        assert_noop!(
+            Deitos::consumer_request_agreement(
+                RuntimeOrigin::signed(CONSUMER),
+                IP,
+                storage,
+                ComputeResources::default(),
+                activation_block,
+                payment_plan.clone(),
+                None,
+            ),
+            Error::<Test>::ConsumerRatingTooLow
+        );
+
+        // Consumers that have not been rated yet are accepted
+        assert_ok!(Deitos::consumer_request_agreement(
+            RuntimeOrigin::signed(3),
+            IP,
+            storage,
+            ComputeResources::default(),
+            activation_block,
+            payment_plan.clone(),
+            None,
+        ));
+
+        // Removing the requirement accepts any consumer
+        assert_ok!(Deitos::ip_set_min_consumer_rating(
+            RuntimeOrigin::signed(IP),
+            None
+        ));
+        assert_ok!(Deitos::consumer_request_agreement(
+            RuntimeOrigin::signed(CONSUMER),
+            IP,
+            storage,
+            ComputeResources::default(),
+            activation_block,
+            payment_plan,
+            None,
+        ));
+    });
+}
@@ -0,0 +1,139 @@
+// Copyright (C) NC2D Labs.
+// This file is part of Deitos Node.
+
+// Deitos Node is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Deitos Node is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Deitos Node.  If not, see <http://www.gnu.org/licenses/>.
+
+use sp_runtime::Perbill;
+
+use crate::{types::*, InfrastructureProviders};
+
+use super::*;
+
+fn reliable_ips(filter: ReliabilityFilter) -> Vec<AccountId> {
+    let mut ips: Vec<_> = Deitos::reliable_ips_info(&filter)
+        .into_iter()
+        .map(|ip_info| ip_info.ip)
+        .collect();
+    ips.sort();
+    ips
+}
+
+#[test]
+fn test_reliability_record() {
+    new_test_ext().execute_with(|| {
+        let activation_block = 100;
+
+        register_and_activate_ip(IP, 1000);
+        let completed_agreement_id = create_accepted_agreement(
+            IP,
+            CONSUMER,
+            100,
+            activation_block,
+            vec![activation_block + 100].try_into().unwrap(),
+        );
+        let terminated_agreement_id = create_accepted_agreement(
+            IP,
+            3,
+            100,
+            activation_block,
+            vec![activation_block + 100, activation_block + 200]
+                .try_into()
+                .unwrap(),
+        );
+
+        // The integrity checks and the file conflicts are recorded
+        Deitos::record_integrity_check(completed_agreement_id, true);
+        Deitos::record_integrity_check(completed_agreement_id, true);
+        Deitos::record_integrity_check(completed_agreement_id, false);
+        Deitos::record_file_conflict(completed_agreement_id);
+
+        // The agreements ended by non-payment are recorded
+        run_to_block(activation_block + 11);
+        assert_ok!(Deitos::ip_terminate_nonpay(
+            RuntimeOrigin::signed(IP),
+            terminated_agreement_id,
+        ));
+
+        // The completed agreements are recorded
+        run_to_block(activation_block + 101);
+        assert_ok!(Deitos::ip_withdraw_installments(
+            RuntimeOrigin::signed(IP),
+            completed_agreement_id,
+        ));
+
+        let reliability = InfrastructureProviders::<Test>::get(IP)
+            .unwrap()
+            .reliability;
+        assert_eq!(
+            reliability,
+            ReliabilityRecord {
+                integrity_checks_passed: 2,
+                integrity_checks_failed: 1,
+                file_conflicts: 1,
+                completed_agreements: 1,
+                nonpay_terminations: 1,
+                sla_terminations: 0,
+            }
+        );
+        assert_eq!(
+            reliability.integrity_pass_rate(),
+            Some(Perbill::from_rational(2u32, 3u32))
+        );
+        assert_eq!(
+            reliability.completion_rate(),
+            Some(Perbill::from_percent(50))
+        );
+    });
+}
+
+#[test]
+fn test_filter_ips_by_reliability() {
+    new_test_ext().execute_with(|| {
+        let activation_block = 100;
+
+        register_and_activate_ip(IP, 1000);
+        register_and_activate_ip(4, 1000);
+        let agreement_id = create_accepted_agreement(
+            IP,
+            CONSUMER,
+            100,
+            activation_block,
+            vec![activation_block + 100].try_into().unwrap(),
+        );
+        Deitos::record_integrity_check(agreement_id, true);
+        Deitos::record_integrity_check(agreement_id, false);
+        Deitos::record_file_conflict(agreement_id);
+
+        // Every IP meets an empty filter
+        assert_eq!(reliable_ips(ReliabilityFilter::default()), vec![IP, 4]);
+
+        // IPs without integrity checks meet any minimum pass rate
+        let filter = ReliabilityFilter {
+            min_integrity_pass_rate: Some(Perbill::from_percent(50)),
+            ..Default::default()
+        };
+        assert_eq!(reliable_ips(filter), vec![IP, 4]);
+        let filter = ReliabilityFilter {
+            min_integrity_pass_rate: Some(Perbill::from_percent(51)),
+            ..Default::default()
+        };
+        assert_eq!(reliable_ips(filter), vec![4]);
+
+        let filter = ReliabilityFilter {
+            max_file_conflicts: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(reliable_ips(filter), vec![4]);
+    });
+}
@@ -0,0 +1,266 @@
+// Copyright (C) NC2D Labs.
+// This file is part of Deitos Node.
+
+// Deitos Node is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Deitos Node is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Deitos Node.  If not, see <http://www.gnu.org/licenses/>.
+
+use frame_support::{
+    assert_noop,
+    traits::{fungible, Hooks},
+    weights::Weight,
+};
+use frame_system::pallet_prelude::BlockNumberFor;
+
+use crate::{
+    pallet::{Agreements, Error, Event, HoldReason, RenewalProposals},
+    types::*,
+};
+
+use super::*;
+
+fn plan(blocks: Vec<BlockNumberFor<Test>>) -> PaymentPlan<Test> {
+    blocks.try_into().unwrap()
+}
+
+fn balance_on_hold(reason: HoldReason) -> Balance {
+    <Balances as fungible::InspectHold<_>>::balance_on_hold(&reason.into(), &CONSUMER)
+}
+
+#[test]
+fn test_renewal() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+        let activation_block: BlockNumberFor<Test> = 100;
+
+        register_and_activate_ip(IP, storage);
+        let agreement_id = create_accepted_agreement(
+            IP,
+            CONSUMER,
+            storage,
+            activation_block,
+            plan(vec![200, 300]),
+        );
+        assert_ok!(Deitos::consumer_prepay_installment(
+            RuntimeOrigin::signed(CONSUMER),
+            agreement_id,
+        ));
+
+        // Only the parties of the agreement can propose a renewal
+        assert_noop!(
+            Deitos::propose_renewal(
+                RuntimeOrigin::signed(3),
+                agreement_id,
+                plan(vec![400]),
+                false
+            ),
+            Error::<Test>::AgreementNotFound
+        );
+
+        assert_ok!(Deitos::propose_renewal(
+            RuntimeOrigin::signed(IP),
+            agreement_id,
+            plan(vec![400, 600]),
+            false
+        ));
+        System::assert_has_event(RuntimeEvent::Deitos(Event::RenewalProposed {
+            agreement_id,
+            proposed_by: IP,
+            extension: plan(vec![400, 600]),
+            auto_renew: false,
+        }));
+
+        // The proposing party cannot accept its own proposal
+        assert_noop!(
+            Deitos::accept_renewal(RuntimeOrigin::signed(IP), agreement_id),
+            Error::<Test>::RenewalNotFound
+        );
+
+        assert_ok!(Deitos::accept_renewal(
+            RuntimeOrigin::signed(CONSUMER),
+            agreement_id
+        ));
+
+        // The payment plan is extended and the agreement keeps its id
+        let agreement = Agreements::<Test>::get(agreement_id).unwrap();
+        assert_eq!(agreement.payment_plan, plan(vec![200, 300, 400, 600]));
+        assert_eq!(agreement.status, AgreementStatus::Active);
+        assert_eq!(RenewalProposals::<Test>::get(agreement_id), None);
+
+        // The security deposit pays for the former last installment and a new one is held for the
+        // new last installment
+        let installment_cost = 100 * PRICE_STORAGE * storage;
+        assert_eq!(agreement.payment_history.records.len(), 2);
+        assert_eq!(
+            balance_on_hold(HoldReason::ConsumerInstallment),
+            2 * installment_cost
+        );
+        assert_eq!(agreement.consumer_security_deposit, 2 * installment_cost);
+        assert_eq!(
+            balance_on_hold(HoldReason::ConsumerSecurityDeposit),
+            2 * installment_cost
+        );
+
+        System::assert_has_event(RuntimeEvent::Deitos(Event::AgreementRenewed {
+            agreement_id,
+            payment_plan: plan(vec![200, 300, 400, 600]),
+            consumer_security_deposit: 2 * installment_cost,
+            auto_renew: false,
+        }));
+
+        // A new proposal is required to renew again
+        assert_noop!(
+            Deitos::accept_renewal(RuntimeOrigin::signed(CONSUMER), agreement_id),
+            Error::<Test>::RenewalNotFound
+        );
+    });
+}
+
+#[test]
+fn test_propose_renewal_invalid() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+
+        register_and_activate_ip(IP, storage);
+        let agreement_id =
+            create_accepted_agreement(IP, CONSUMER, storage, 100, plan(vec![200, 300]));
+
+        // The extension must start after the end of the payment plan
+        assert_noop!(
+            Deitos::propose_renewal(
+                RuntimeOrigin::signed(CONSUMER),
+                agreement_id,
+                plan(vec![250]),
+                false
+            ),
+            Error::<Test>::PaymentPlanInvalid
+        );
+
+        // The extension must not be empty
+        assert_noop!(
+            Deitos::propose_renewal(
+                RuntimeOrigin::signed(CONSUMER),
+                agreement_id,
+                plan(vec![]),
+                true
+            ),
+            Error::<Test>::PaymentPlanInvalid
+        );
+    });
+}
+
+#[test]
+fn test_auto_renew() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+        let installment_cost = 100 * PRICE_STORAGE * storage;
+
+        register_and_activate_ip(IP, storage);
+        let agreement_id = create_accepted_agreement(IP, CONSUMER, storage, 100, plan(vec![200]));
+
+        assert_ok!(Deitos::propose_renewal(
+            RuntimeOrigin::signed(CONSUMER),
+            agreement_id,
+            plan(vec![300]),
+            true
+        ));
+        assert_ok!(Deitos::accept_renewal(
+            RuntimeOrigin::signed(IP),
+            agreement_id
+        ));
+
+        let balance_before = Balances::free_balance(IP);
+
+        // The last installment has started, so the agreement is renewed for the same period
+        run_to_block(201);
+        Deitos::on_idle(201, Weight::MAX);
+
+        let agreement = Agreements::<Test>::get(agreement_id).unwrap();
+        assert_eq!(agreement.status, AgreementStatus::Active);
+        assert_eq!(agreement.payment_plan, plan(vec![200, 300, 400]));
+        assert_eq!(
+            Balances::free_balance(IP),
+            balance_before + installment_cost
+        );
+
+        // Once the auto-renewal is cancelled, the agreement ends with its payment plan
+        assert_ok!(Deitos::cancel_auto_renew(
+            RuntimeOrigin::signed(IP),
+            agreement_id
+        ));
+        System::assert_has_event(RuntimeEvent::Deitos(Event::AutoRenewCancelled {
+            agreement_id,
+            cancelled_by: IP,
+        }));
+
+        run_to_block(301);
+        Deitos::on_idle(301, Weight::MAX);
+        run_to_block(401);
+        Deitos::on_idle(401, Weight::MAX);
+
+        let agreement = Agreements::<Test>::get(agreement_id).unwrap();
+        assert_eq!(agreement.status, AgreementStatus::Completed);
+        assert_eq!(agreement.payment_plan, plan(vec![200, 300, 400]));
+        assert_eq!(
+            Balances::free_balance(IP),
+            balance_before + 3 * installment_cost
+        );
+
+        // The auto-renewal of an agreement that has ended cannot be cancelled
+        assert_noop!(
+            Deitos::cancel_auto_renew(RuntimeOrigin::signed(IP), agreement_id),
+            Error::<Test>::AgreementStatusInvalid
+        );
+    });
+}
+
+#[test]
+fn test_auto_renew_failed() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+
+        register_and_activate_ip(IP, storage);
+        let agreement_id =
+            create_accepted_agreement(IP, CONSUMER, storage, 100, plan(vec![200, 300]));
+        assert_ok!(Deitos::consumer_prepay_installment(
+            RuntimeOrigin::signed(CONSUMER),
+            agreement_id,
+        ));
+        assert_ok!(Deitos::propose_renewal(
+            RuntimeOrigin::signed(CONSUMER),
+            agreement_id,
+            plan(vec![400]),
+            true
+        ));
+        assert_ok!(Deitos::accept_renewal(
+            RuntimeOrigin::signed(IP),
+            agreement_id
+        ));
+
+        // The consumer cannot afford the security deposit of the next renewal
+        assert_ok!(Balances::force_set_balance(
+            RuntimeOrigin::root(),
+            CONSUMER,
+            1_000
+        ));
+
+        run_to_block(301);
+        Deitos::on_idle(301, Weight::MAX);
+
+        let agreement = Agreements::<Test>::get(agreement_id).unwrap();
+        assert!(!agreement.auto_renew);
+        assert_eq!(agreement.payment_plan, plan(vec![200, 300, 400]));
+        System::assert_has_event(RuntimeEvent::Deitos(Event::AutoRenewFailed {
+            agreement_id,
+        }));
+    });
+}
@@ -0,0 +1,285 @@
+// Copyright (C) NC2D Labs.
+// This file is part of Deitos Node.
+
+// Deitos Node is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Deitos Node is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Deitos Node.  If not, see <http://www.gnu.org/licenses/>.
+
+use frame_system::pallet_prelude::BlockNumberFor;
+
+use crate::types::*;
+
+use super::*;
+
+#[test]
+fn test_ips_info() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+        let activation_block: BlockNumberFor<Test> = 100;
+        let payment_plan: PaymentPlan<Test> = vec![activation_block + 100].try_into().unwrap();
+
+        register_and_activate_ip(IP, 1000);
+        let agreement_id =
+            create_accepted_agreement(IP, CONSUMER, storage, activation_block, payment_plan);
+
+        run_to_block(activation_block + 101);
+        assert_ok!(Deitos::ip_withdraw_installments(
+            RuntimeOrigin::signed(IP),
+            agreement_id,
+        ));
+        assert_ok!(Deitos::consumer_submit_feedback(
+            RuntimeOrigin::signed(CONSUMER),
+            agreement_id,
+            Score::Four,
+            Score::Five,
+            Score::Three,
+            b"Good service".to_vec().try_into().unwrap(),
+        ));
+
+        assert_eq!(
+            Deitos::ips_info(),
+            vec![IPInfo {
+                ip: IP,
+                status: IPStatus::Active,
+                total_storage: 1000,
+                free_storage: 1000,
+                total_compute: IP_COMPUTE,
+                free_compute: IP_COMPUTE,
+                average_rating: Some(AverageRating {
+                    performance: 400,
+                    stability: 500,
+                    support: 300,
+                    number_of_scores: 1,
+                }),
+                weighted_rating: Some(AverageRating {
+                    performance: 400,
+                    stability: 500,
+                    support: 300,
+                    number_of_scores: 1,
+                }),
+                // The prior weight pulls the average of 4 slightly towards the neutral 3
+                reputation: 399,
+                disputes: DisputeRecord::default(),
+                reliability: ReliabilityRecord {
+                    completed_agreements: 1,
+                    ..Default::default()
+                },
+                min_consumer_rating: None,
+                unrated_agreements: 0,
+            }]
+        );
+
+        let review = ReviewInfo {
+            index: 0,
+            agreement_id,
+            consumer: CONSUMER,
+            score_performance: Score::Four,
+            score_stability: Score::Five,
+            score_support: Score::Three,
+            comment: b"Good service".to_vec(),
+            block_number: activation_block + 101,
+        };
+        assert_eq!(Deitos::ip_reviews(&IP, 0, 10), vec![review]);
+        assert_eq!(Deitos::ip_reviews(&IP, 1, 10), vec![]);
+        assert_eq!(Deitos::ip_reviews(&IP, 0, 0), vec![]);
+    });
+}
+
+#[test]
+fn test_agreement_info() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+        let activation_block: BlockNumberFor<Test> = 100;
+        let payment_plan: PaymentPlan<Test> = vec![
+            activation_block + 100,
+            activation_block + 300,
+            activation_block + 600,
+        ]
+        .try_into()
+        .unwrap();
+
+        register_and_activate_ip(IP, storage);
+        let agreement_id = create_accepted_agreement(
+            IP,
+            CONSUMER,
+            storage,
+            activation_block,
+            payment_plan.clone(),
+        );
+
+        assert_eq!(Deitos::ip_agreement_ids(&IP, None, 10), vec![agreement_id]);
+        assert_eq!(
+            Deitos::consumer_agreement_ids(&CONSUMER, None, 10),
+            vec![agreement_id]
+        );
+
+        // The first installment is due at the activation block
+        let info = Deitos::agreement_info(agreement_id).unwrap();
+        assert_eq!(info.payment_plan, payment_plan.into_inner());
+        assert_eq!(info.paid_installments, vec![]);
+        assert!(!info.overdue);
+        assert_eq!(
+            info.next_installment,
+            Some(InstallmentInfo {
+                index: 0,
+                due_block: activation_block,
+                end_block: activation_block + 100,
+                cost: 100 * PRICE_STORAGE * storage,
+            })
+        );
+
+        // After the first installment is prepaid, the second one is due
+        assert_ok!(Deitos::consumer_prepay_installment(
+            RuntimeOrigin::signed(CONSUMER),
+            agreement_id,
+        ));
+        let info = Deitos::agreement_info(agreement_id).unwrap();
+        assert_eq!(info.paid_installments, vec![100 * PRICE_STORAGE * storage]);
+        assert_eq!(
+            info.next_installment,
+            Some(InstallmentInfo {
+                index: 1,
+                due_block: activation_block + 100,
+                end_block: activation_block + 300,
+                cost: 200 * PRICE_STORAGE * storage,
+            })
+        );
+
+        // The second installment is overdue once it starts
+        run_to_block(activation_block + 101);
+        assert!(Deitos::agreement_info(agreement_id).unwrap().overdue);
+
+        // The last installment is paid with the security deposit
+        assert_ok!(Deitos::consumer_prepay_installment(
+            RuntimeOrigin::signed(CONSUMER),
+            agreement_id,
+        ));
+        let info = Deitos::agreement_info(agreement_id).unwrap();
+        assert_eq!(info.next_installment, None);
+        assert!(!info.overdue);
+    });
+}
+
+#[test]
+fn test_agreement_info_before_activation() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+        let activation_block: BlockNumberFor<Test> = 100;
+        let payment_plan: PaymentPlan<Test> = vec![activation_block + 100, activation_block + 200]
+            .try_into()
+            .unwrap();
+
+        register_and_activate_ip(IP, storage);
+        let agreement_id =
+            create_accepted_agreement(IP, CONSUMER, storage, activation_block, payment_plan);
+
+        // An accepted agreement that has not started yet has nothing overdue
+        let info = Deitos::agreement_info(agreement_id).unwrap();
+        assert_eq!(info.status, AgreementStatus::Active);
+        assert_eq!(info.ip, IP);
+        assert_eq!(info.consumer, CONSUMER);
+        assert!(!info.overdue);
+
+        // The unpaid first installment is overdue from the activation block
+        run_to_block(activation_block);
+        assert!(Deitos::agreement_info(agreement_id).unwrap().overdue);
+    });
+}
+
+#[test]
+fn test_agreement_ids_pagination() {
+    new_test_ext().execute_with(|| {
+        let activation_block: BlockNumberFor<Test> = 100;
+        let payment_plan: PaymentPlan<Test> = vec![activation_block + 100].try_into().unwrap();
+
+        register_and_activate_ip(IP, 1000);
+        let agreement_ids: Vec<_> = (0..5)
+            .map(|_| {
+                create_accepted_agreement(IP, CONSUMER, 10, activation_block, payment_plan.clone())
+            })
+            .collect();
+        assert_eq!(Deitos::ip_agreement_count(IP), 5);
+        assert_eq!(Deitos::consumer_agreement_count(CONSUMER), 5);
+
+        // Walk the agreements of the IP in pages of two
+        let mut pages = vec![];
+        let mut start_after = None;
+        loop {
+            let page = Deitos::ip_agreement_ids(&IP, start_after, 2);
+            if page.is_empty() {
+                break;
+            }
+            start_after = page.last().copied();
+            pages.push(page);
+        }
+        assert_eq!(
+            pages.iter().map(Vec::len).collect::<Vec<_>>(),
+            vec![2, 2, 1]
+        );
+
+        let mut listed: Vec<_> = pages.into_iter().flatten().collect();
+        listed.sort();
+        assert_eq!(listed, agreement_ids);
+
+        // The pages of the consumer match the pages of the IP
+        let first_page = Deitos::consumer_agreement_ids(&CONSUMER, None, 3);
+        assert_eq!(first_page.len(), 3);
+        assert_eq!(
+            Deitos::consumer_agreement_ids(&CONSUMER, first_page.last().copied(), 3).len(),
+            2
+        );
+    });
+}
+
+#[test]
+fn test_quote_agreement() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+        let compute = ComputeResources {
+            vcores: 2,
+            ram: 1024,
+        };
+        let activation_block: BlockNumberFor<Test> = 100;
+        let cost_per_block =
+            PRICE_STORAGE * storage + PRICE_VCORE * compute.vcores as u64 + PRICE_RAM * compute.ram;
+
+        assert_eq!(
+            Deitos::quote_agreement(
+                storage,
+                compute,
+                activation_block,
+                vec![activation_block + 100, activation_block + 300],
+            ),
+            Some(AgreementQuote {
+                total_cost: 300 * cost_per_block,
+                security_deposit: 200 * cost_per_block,
+                service_deposit: CONSUMER_SERVICE_DEPOSIT,
+                installments: vec![100 * cost_per_block, 200 * cost_per_block],
+            })
+        );
+
+        // Invalid payment plans cannot be quoted
+        assert_eq!(
+            Deitos::quote_agreement(storage, compute, activation_block, vec![]),
+            None
+        );
+        assert_eq!(
+            Deitos::quote_agreement(
+                storage,
+                compute,
+                activation_block,
+                vec![activation_block + 300, activation_block + 100],
+            ),
+            None
+        );
+    });
+}
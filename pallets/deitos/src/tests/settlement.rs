@@ -0,0 +1,490 @@
+// Copyright (C) NC2D Labs.
+// This file is part of Deitos Node.
+
+// Deitos Node is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Deitos Node is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Deitos Node.  If not, see <http://www.gnu.org/licenses/>.
+
+use frame_support::{
+    assert_noop,
+    traits::{fungible, tokens::Precision::Exact, Hooks},
+    weights::Weight,
+};
+use frame_system::pallet_prelude::BlockNumberFor;
+
+use crate::{
+    pallet::{
+        Agreements, Event, HoldReason, PendingConsumerRatings, SettlementCursor, SettlementQueue,
+    },
+    types::*,
+    WeightInfo,
+};
+
+use super::*;
+
+#[test]
+fn test_on_idle_settles_due_installments() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+        let activation_block: BlockNumberFor<Test> = 100;
+        let payment_plan: PaymentPlan<Test> = vec![activation_block + 100, activation_block + 200]
+            .try_into()
+            .unwrap();
+
+        register_and_activate_ip(IP, storage);
+        let agreement_id =
+            create_accepted_agreement(IP, CONSUMER, storage, activation_block, payment_plan);
+        assert_ok!(Deitos::consumer_prepay_installment(
+            RuntimeOrigin::signed(CONSUMER),
+            agreement_id,
+        ));
+
+        // The settlement is scheduled after the end of the first installment
+        assert_eq!(
+            SettlementQueue::<Test>::get(activation_block + 101).into_inner(),
+            vec![agreement_id]
+        );
+
+        let installment_cost = 100 * PRICE_STORAGE * storage;
+        let balance_before = Balances::free_balance(IP);
+
+        // Nothing is due before the end of the first installment
+        run_to_block(activation_block + 100);
+        Deitos::on_idle(activation_block + 100, Weight::MAX);
+        assert_eq!(Balances::free_balance(IP), balance_before);
+
+        run_to_block(activation_block + 101);
+        Deitos::on_idle(activation_block + 101, Weight::MAX);
+
+        // The first installment is transferred to the IP
+        assert_eq!(
+            Balances::free_balance(IP),
+            balance_before + installment_cost
+        );
+        System::assert_has_event(RuntimeEvent::Deitos(Event::IPWithdrewInstallments {
+            agreement_id,
+            ip: IP,
+            transferred: installment_cost,
+        }));
+
+        // The settlement of the last installment is scheduled
+        assert!(SettlementQueue::<Test>::get(activation_block + 101).is_empty());
+        assert_eq!(
+            SettlementQueue::<Test>::get(activation_block + 201).into_inner(),
+            vec![agreement_id]
+        );
+        assert_eq!(SettlementCursor::<Test>::get(), activation_block + 102);
+
+        run_to_block(activation_block + 201);
+        Deitos::on_idle(activation_block + 201, Weight::MAX);
+
+        // The security deposit is transferred and the agreement is completed
+        assert_eq!(
+            Balances::free_balance(IP),
+            balance_before + 2 * installment_cost
+        );
+        let agreement = Agreements::<Test>::get(agreement_id).unwrap();
+        assert_eq!(agreement.status, AgreementStatus::Completed);
+        assert_eq!(Deitos::ip_free_storage(&IP), Some(storage));
+        assert!(PendingConsumerRatings::<Test>::get(agreement_id).is_some());
+        assert!(SettlementQueue::<Test>::get(activation_block + 201).is_empty());
+    });
+}
+
+#[test]
+fn test_on_idle_skips_withdrawn_installments() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+        let activation_block: BlockNumberFor<Test> = 100;
+        let payment_plan: PaymentPlan<Test> = vec![activation_block + 100].try_into().unwrap();
+
+        register_and_activate_ip(IP, storage);
+        let agreement_id =
+            create_accepted_agreement(IP, CONSUMER, storage, activation_block, payment_plan);
+
+        // The IP withdraws the installments before the settlement
+        run_to_block(activation_block + 101);
+        assert_ok!(Deitos::ip_withdraw_installments(
+            RuntimeOrigin::signed(IP),
+            agreement_id,
+        ));
+        let balance_before = Balances::free_balance(IP);
+        System::reset_events();
+
+        Deitos::on_idle(activation_block + 101, Weight::MAX);
+
+        // Nothing else is transferred
+        assert_eq!(Balances::free_balance(IP), balance_before);
+        assert!(System::events().is_empty());
+        assert!(SettlementQueue::<Test>::get(activation_block + 101).is_empty());
+    });
+}
+
+#[test]
+fn test_on_idle_weight_limit() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+        let activation_block: BlockNumberFor<Test> = 100;
+        let payment_plan: PaymentPlan<Test> = vec![activation_block + 100].try_into().unwrap();
+        let due_block = activation_block + 101;
+
+        register_and_activate_ip(IP, 3 * storage);
+        let agreement_ids: Vec<_> = [CONSUMER, 3, 4]
+            .into_iter()
+            .map(|consumer| {
+                create_accepted_agreement(
+                    IP,
+                    consumer,
+                    storage,
+                    activation_block,
+                    payment_plan.clone(),
+                )
+            })
+            .collect();
+
+        // The queue of the due block is full, so the last agreement is scheduled at the next block
+        assert_eq!(
+            SettlementQueue::<Test>::get(due_block).into_inner(),
+            agreement_ids[..2].to_vec()
+        );
+        assert_eq!(
+            SettlementQueue::<Test>::get(due_block + 1).into_inner(),
+            agreement_ids[2..].to_vec()
+        );
+
+        run_to_block(due_block + 1);
+
+        // There is weight to settle only one agreement
+        let weight = <() as WeightInfo>::settle_agreement();
+        assert_eq!(Deitos::on_idle(due_block + 1, weight), weight);
+
+        let status = |agreement_id| Agreements::<Test>::get(agreement_id).unwrap().status;
+        assert_eq!(status(agreement_ids[0]), AgreementStatus::Completed);
+        assert_eq!(status(agreement_ids[1]), AgreementStatus::Active);
+        assert_eq!(
+            SettlementQueue::<Test>::get(due_block).into_inner(),
+            agreement_ids[1..2].to_vec()
+        );
+        assert_eq!(SettlementCursor::<Test>::get(), due_block);
+
+        // The rest is settled with enough weight
+        Deitos::on_idle(due_block + 1, Weight::MAX);
+        assert!(agreement_ids
+            .iter()
+            .all(|agreement_id| status(*agreement_id) == AgreementStatus::Completed));
+        assert_eq!(SettlementCursor::<Test>::get(), due_block + 2);
+    });
+}
+
+#[test]
+fn test_consumer_set_auto_pay() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+        let activation_block: BlockNumberFor<Test> = 100;
+        let payment_plan: PaymentPlan<Test> = vec![activation_block + 100, activation_block + 200]
+            .try_into()
+            .unwrap();
+
+        register_and_activate_ip(IP, storage);
+        assert_ok!(Deitos::consumer_request_agreement(
+            RuntimeOrigin::signed(CONSUMER),
+            IP,
+            storage,
+            ComputeResources::default(),
+            activation_block,
+            payment_plan,
+            None,
+        ));
+        let agreement_id = CurrentAgreementId::<Test>::get();
+
+        // The agreement must be active
+        assert_noop!(
+            Deitos::consumer_set_auto_pay(RuntimeOrigin::signed(CONSUMER), agreement_id, true),
+            Error::<Test>::AgreementStatusInvalid
+        );
+
+        assert_ok!(Deitos::ip_accept_agreement(
+            RuntimeOrigin::signed(IP),
+            agreement_id,
+        ));
+
+        // Only the consumer can enable auto-pay
+        assert_noop!(
+            Deitos::consumer_set_auto_pay(RuntimeOrigin::signed(IP), agreement_id, true),
+            Error::<Test>::AgreementNotFound
+        );
+
+        assert_ok!(Deitos::consumer_set_auto_pay(
+            RuntimeOrigin::signed(CONSUMER),
+            agreement_id,
+            true
+        ));
+
+        // The first installment is prepaid `AutoPayLeadBlocks` blocks before the activation
+        assert!(Agreements::<Test>::get(agreement_id).unwrap().auto_pay);
+        assert_eq!(
+            AutoPayQueue::<Test>::get(activation_block - 10).into_inner(),
+            vec![agreement_id]
+        );
+        System::assert_has_event(RuntimeEvent::Deitos(Event::ConsumerAutoPayUpdated {
+            agreement_id,
+            consumer: CONSUMER,
+            enabled: true,
+        }));
+    });
+}
+
+#[test]
+fn test_auto_pay_prepays_installments() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+        let activation_block: BlockNumberFor<Test> = 100;
+        let payment_plan: PaymentPlan<Test> = vec![
+            activation_block + 100,
+            activation_block + 200,
+            activation_block + 300,
+        ]
+        .try_into()
+        .unwrap();
+
+        register_and_activate_ip(IP, storage);
+        let agreement_id =
+            create_accepted_agreement(IP, CONSUMER, storage, activation_block, payment_plan);
+        assert_ok!(Deitos::consumer_set_auto_pay(
+            RuntimeOrigin::signed(CONSUMER),
+            agreement_id,
+            true
+        ));
+
+        let installment_cost = 100 * PRICE_STORAGE * storage;
+        let balance_before = Balances::free_balance(CONSUMER);
+
+        run_to_block(activation_block - 10);
+        Deitos::on_idle(activation_block - 10, Weight::MAX);
+
+        // The first installment is prepaid
+        assert_eq!(
+            Balances::free_balance(CONSUMER),
+            balance_before - installment_cost
+        );
+        System::assert_has_event(RuntimeEvent::Deitos(Event::ConsumerPrepaidInstallment {
+            agreement_id,
+            consumer: CONSUMER,
+            cost: installment_cost,
+        }));
+        assert_eq!(
+            AutoPayQueue::<Test>::get(activation_block + 90).into_inner(),
+            vec![agreement_id]
+        );
+
+        run_to_block(activation_block + 90);
+        Deitos::on_idle(activation_block + 90, Weight::MAX);
+
+        // The second installment is prepaid, the last one is paid with the security deposit
+        let agreement = Agreements::<Test>::get(agreement_id).unwrap();
+        assert_eq!(agreement.payment_history.records.len(), 2);
+        assert_eq!(agreement.next_auto_pay_block(), None);
+        assert_eq!(
+            Balances::free_balance(CONSUMER),
+            balance_before - 2 * installment_cost
+        );
+    });
+}
+
+#[test]
+fn test_auto_pay_first_installment_on_idle() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+        let activation_block: BlockNumberFor<Test> = 100;
+        let payment_plan: PaymentPlan<Test> = vec![activation_block + 100, activation_block + 200]
+            .try_into()
+            .unwrap();
+
+        register_and_activate_ip(IP, storage);
+        let agreement_id =
+            create_accepted_agreement(IP, CONSUMER, storage, activation_block, payment_plan);
+        assert_ok!(Deitos::consumer_set_auto_pay(
+            RuntimeOrigin::signed(CONSUMER),
+            agreement_id,
+            true
+        ));
+
+        // The first installment is prepaid ahead of activation, without a late fee
+        run_to_block(activation_block - 10);
+        Deitos::on_idle(activation_block - 10, Weight::MAX);
+
+        let installment_cost = 100 * PRICE_STORAGE * storage;
+        let agreement = Agreements::<Test>::get(agreement_id).unwrap();
+        assert_eq!(
+            agreement.payment_history.records,
+            vec![PaymentRecord {
+                amount: installment_cost,
+                transferred: false
+            }]
+        );
+        assert_eq!(agreement.status, AgreementStatus::Active);
+        assert!(!System::events().iter().any(|record| matches!(
+            record.event,
+            RuntimeEvent::Deitos(Event::ConsumerPaidLateFee { .. })
+        )));
+    });
+}
+
+#[test]
+fn test_auto_pay_insufficient_balance() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+        let activation_block: BlockNumberFor<Test> = 100;
+        let payment_plan: PaymentPlan<Test> = vec![activation_block + 100, activation_block + 200]
+            .try_into()
+            .unwrap();
+
+        register_and_activate_ip(IP, storage);
+        let agreement_id =
+            create_accepted_agreement(IP, CONSUMER, storage, activation_block, payment_plan);
+        assert_ok!(Deitos::consumer_set_auto_pay(
+            RuntimeOrigin::signed(CONSUMER),
+            agreement_id,
+            true
+        ));
+
+        let installment_cost = 100 * PRICE_STORAGE * storage;
+        assert_ok!(Balances::force_set_balance(
+            RuntimeOrigin::root(),
+            CONSUMER,
+            installment_cost / 2
+        ));
+
+        run_to_block(activation_block - 10);
+        Deitos::on_idle(activation_block - 10, Weight::MAX);
+
+        // The consumer is warned and the prepayment is retried before the installment is due
+        System::assert_has_event(RuntimeEvent::Deitos(Event::AutoPayInsufficientBalance {
+            agreement_id,
+            consumer: CONSUMER,
+            cost: installment_cost,
+            due_block: activation_block,
+        }));
+        assert!(Agreements::<Test>::get(agreement_id)
+            .unwrap()
+            .payment_history
+            .records
+            .is_empty());
+        assert_eq!(
+            AutoPayQueue::<Test>::get(activation_block - 1).into_inner(),
+            vec![agreement_id]
+        );
+
+        // The consumer tops up the balance in time
+        assert_ok!(Balances::force_set_balance(
+            RuntimeOrigin::root(),
+            CONSUMER,
+            INITIAL_BALANCE
+        ));
+
+        run_to_block(activation_block - 1);
+        Deitos::on_idle(activation_block - 1, Weight::MAX);
+
+        assert_eq!(
+            Agreements::<Test>::get(agreement_id)
+                .unwrap()
+                .payment_history
+                .records
+                .len(),
+            1
+        );
+    });
+}
+
+#[test]
+fn test_auto_pay_disabled() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+        let activation_block: BlockNumberFor<Test> = 100;
+        let payment_plan: PaymentPlan<Test> = vec![activation_block + 100, activation_block + 200]
+            .try_into()
+            .unwrap();
+
+        register_and_activate_ip(IP, storage);
+        let agreement_id =
+            create_accepted_agreement(IP, CONSUMER, storage, activation_block, payment_plan);
+        assert_ok!(Deitos::consumer_set_auto_pay(
+            RuntimeOrigin::signed(CONSUMER),
+            agreement_id,
+            true
+        ));
+        assert_ok!(Deitos::consumer_set_auto_pay(
+            RuntimeOrigin::signed(CONSUMER),
+            agreement_id,
+            false
+        ));
+
+        run_to_block(activation_block - 10);
+        Deitos::on_idle(activation_block - 10, Weight::MAX);
+
+        // Nothing is prepaid
+        assert!(Agreements::<Test>::get(agreement_id)
+            .unwrap()
+            .payment_history
+            .records
+            .is_empty());
+        assert!(AutoPayQueue::<Test>::get(activation_block - 10).is_empty());
+    });
+}
+
+#[test]
+fn test_failed_settlement_is_retried() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+        let activation_block: BlockNumberFor<Test> = 100;
+        let payment_plan: PaymentPlan<Test> = vec![activation_block + 100, activation_block + 200]
+            .try_into()
+            .unwrap();
+
+        register_and_activate_ip(IP, storage);
+        let agreement_id =
+            create_accepted_agreement(IP, CONSUMER, storage, activation_block, payment_plan);
+        assert_ok!(Deitos::consumer_prepay_installment(
+            RuntimeOrigin::signed(CONSUMER),
+            agreement_id,
+        ));
+
+        // The prepaid installment is no longer on hold, so the transfer to the IP fails
+        let installment_cost = 100 * PRICE_STORAGE * storage;
+        assert_ok!(<Balances as fungible::MutateHold<_>>::release(
+            &HoldReason::ConsumerInstallment.into(),
+            &CONSUMER,
+            installment_cost,
+            Exact,
+        ));
+
+        run_to_block(activation_block + 101);
+        Deitos::on_idle(activation_block + 101, Weight::MAX);
+
+        // The settlement is retried at the next block
+        let agreement = Agreements::<Test>::get(agreement_id).unwrap();
+        assert_eq!(agreement.status, AgreementStatus::Active);
+        assert_eq!(agreement.payment_history.next_transfer_installment_index, 0);
+        assert_eq!(
+            SettlementQueue::<Test>::get(activation_block + 102).into_inner(),
+            vec![agreement_id]
+        );
+        assert!(System::events().iter().any(|record| matches!(
+            record.event,
+            RuntimeEvent::Deitos(Event::SettlementFailed {
+                agreement_id: id,
+                retry_block,
+                ..
+            }) if id == agreement_id && retry_block == activation_block + 102
+        )));
+    });
+}
@@ -0,0 +1,170 @@
+// Copyright (C) NC2D Labs.
+// This file is part of Deitos Node.
+
+// Deitos Node is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Deitos Node is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Deitos Node.  If not, see <http://www.gnu.org/licenses/>.
+
+use frame_support::traits::fungible;
+use sp_runtime::Perbill;
+
+use crate::{
+    pallet::{Agreements, Event, HoldReason},
+    types::*,
+};
+
+use super::*;
+
+const SLA: SlaTerms = SlaTerms {
+    max_failed_checks: 1,
+    refund_per_breach: Perbill::from_percent(10),
+    termination_threshold: 2,
+};
+
+fn request_sla_agreement(storage: StorageSizeMB, sla: Option<SlaTerms>) -> AgreementId {
+    assert_ok!(Deitos::consumer_request_agreement(
+        RuntimeOrigin::signed(CONSUMER),
+        IP,
+        storage,
+        ComputeResources::default(),
+        100,
+        vec![200, 300].try_into().unwrap(),
+        sla,
+    ));
+    let agreement_id = CurrentAgreementId::<Test>::get();
+    assert_ok!(Deitos::ip_accept_agreement(
+        RuntimeOrigin::signed(IP),
+        agreement_id,
+    ));
+    agreement_id
+}
+
+fn held_installments() -> Balance {
+    <Balances as fungible::InspectHold<_>>::balance_on_hold(
+        &HoldReason::ConsumerInstallment.into(),
+        &CONSUMER,
+    )
+}
+
+#[test]
+fn test_sla_breach_refunds_installment() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+        let installment_cost = 100 * PRICE_STORAGE * storage;
+
+        register_and_activate_ip(IP, storage);
+        let agreement_id = request_sla_agreement(storage, Some(SLA));
+        assert_ok!(Deitos::consumer_prepay_installment(
+            RuntimeOrigin::signed(CONSUMER),
+            agreement_id,
+        ));
+
+        // The first failed check of the installment is allowed
+        run_to_block(150);
+        assert_ok!(Deitos::report_failed_integrity_check(agreement_id));
+        assert_eq!(held_installments(), installment_cost);
+
+        // The second one breaches the SLA terms and refunds a share of the installment
+        let consumer_balance_before = Balances::free_balance(CONSUMER);
+        assert_ok!(Deitos::report_failed_integrity_check(agreement_id));
+
+        let refunded = installment_cost / 10;
+        assert_eq!(held_installments(), installment_cost - refunded);
+        assert_eq!(
+            Balances::free_balance(CONSUMER),
+            consumer_balance_before + refunded
+        );
+        let agreement = Agreements::<Test>::get(agreement_id).unwrap();
+        assert_eq!(
+            agreement.payment_history.records[0].amount,
+            installment_cost - refunded
+        );
+        assert_eq!(agreement.sla_record.breaches, 1);
+        System::assert_last_event(RuntimeEvent::Deitos(Event::SlaBreached {
+            agreement_id,
+            consumer: CONSUMER,
+            breaches: 1,
+            refunded,
+        }));
+
+        // The failed checks are counted again for the last installment, which is refunded from
+        // the security deposit
+        run_to_block(250);
+        assert_ok!(Deitos::report_failed_integrity_check(agreement_id));
+        assert_ok!(Deitos::report_failed_integrity_check(agreement_id));
+
+        let agreement = Agreements::<Test>::get(agreement_id).unwrap();
+        assert_eq!(agreement.sla_record.failed_checks, 2);
+        assert_eq!(agreement.sla_record.breaches, 2);
+        assert_eq!(
+            agreement.consumer_security_deposit,
+            installment_cost - refunded
+        );
+    });
+}
+
+#[test]
+fn test_sla_breach_terminates_agreement() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+        let installment_cost = 100 * PRICE_STORAGE * storage;
+
+        register_and_activate_ip(IP, storage);
+        let agreement_id = request_sla_agreement(storage, Some(SLA));
+        assert_ok!(Deitos::consumer_prepay_installment(
+            RuntimeOrigin::signed(CONSUMER),
+            agreement_id,
+        ));
+
+        // Two breaches are refunded and the third one terminates the agreement
+        run_to_block(150);
+        for _ in 0..4 {
+            assert_ok!(Deitos::report_failed_integrity_check(agreement_id));
+        }
+
+        let refunded = 2 * installment_cost / 10;
+        assert_eq!(Agreements::<Test>::get(agreement_id), None);
+        assert_eq!(Deitos::ip_free_storage(&IP), Some(storage));
+        assert_eq!(held_installments(), 0);
+        assert_eq!(Balances::free_balance(CONSUMER), INITIAL_BALANCE);
+        assert_eq!(
+            Balances::free_balance(IP),
+            INITIAL_BALANCE - IP_INITIAL_DEPOSIT
+        );
+        System::assert_last_event(RuntimeEvent::Deitos(Event::AgreementTerminatedSlaBreach {
+            agreement_id,
+            ip: IP,
+            consumer: CONSUMER,
+            refunded: 2 * installment_cost + CONSUMER_SERVICE_DEPOSIT - refunded,
+        }));
+    });
+}
+
+#[test]
+fn test_failed_checks_without_sla() {
+    new_test_ext().execute_with(|| {
+        let storage: StorageSizeMB = 100;
+
+        register_and_activate_ip(IP, storage);
+        let agreement_id = request_sla_agreement(storage, None);
+
+        run_to_block(150);
+        for _ in 0..4 {
+            assert_ok!(Deitos::report_failed_integrity_check(agreement_id));
+        }
+
+        // Agreements without SLA terms are not affected
+        let agreement = Agreements::<Test>::get(agreement_id).unwrap();
+        assert_eq!(agreement.status, AgreementStatus::Active);
+        assert_eq!(agreement.sla_record, SlaRecord::default());
+    });
+}
@@ -18,10 +18,14 @@ use core::cmp::Ordering;
 
 use frame_support::traits::tokens::{
     Fortitude::{Force, Polite},
+    Preservation::Preserve,
     Restriction::Free,
 };
 use frame_system::pallet_prelude::BlockNumberFor;
 use scale_info::TypeInfo;
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sp_runtime::traits::AtLeast32BitUnsigned;
 
 use crate::*;
 
@@ -38,26 +42,99 @@ pub type AccountIdLookupOf<T> = <<T as frame_system::Config>::Lookup as StaticLo
 /// Size of the storage in MB.
 pub type StorageSizeMB = u64;
 
+/// Number of virtual CPU cores.
+pub type VCores = u32;
+
+/// Size of the RAM in MB.
+pub type RamSizeMB = u64;
+
+/// Compute resources an IP provides or an agreement covers:
+/// - `vcores` - the number of virtual CPU cores
+/// - `ram` - the amount of RAM in MB
+#[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, Debug, Default, MaxEncodedLen, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct ComputeResources {
+    /// Number of virtual CPU cores
+    pub vcores: VCores,
+    /// Amount of RAM in MB
+    pub ram: RamSizeMB,
+}
+
+impl ComputeResources {
+    /// Checks that every resource fits in the given resources.
+    pub fn fits_in(&self, other: &Self) -> bool {
+        self.vcores <= other.vcores && self.ram <= other.ram
+    }
+
+    /// Adds the resources, saturating at the numeric bounds.
+    pub fn saturating_add(&self, other: &Self) -> Self {
+        Self {
+            vcores: self.vcores.saturating_add(other.vcores),
+            ram: self.ram.saturating_add(other.ram),
+        }
+    }
+
+    /// Subtracts the resources, saturating at zero.
+    pub fn saturating_sub(&self, other: &Self) -> Self {
+        Self {
+            vcores: self.vcores.saturating_sub(other.vcores),
+            ram: self.ram.saturating_sub(other.ram),
+        }
+    }
+}
+
+/// Service level agreement (SLA) terms of an agreement. Failed integrity checks of the agreement
+/// files are counted per installment, and every failed check over the limit is a breach:
+/// - `max_failed_checks` - the number of failed checks allowed per installment
+/// - `refund_per_breach` - the share of the current installment refunded to the consumer per breach
+/// - `termination_threshold` - the number of breaches after which the agreement is terminated
+#[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, Debug, MaxEncodedLen, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct SlaTerms {
+    /// Number of failed integrity checks allowed per installment
+    pub max_failed_checks: u32,
+    /// Share of the current installment refunded to the consumer per breach
+    pub refund_per_breach: Perbill,
+    /// Number of breaches after which the agreement is terminated
+    pub termination_threshold: u32,
+}
+
+/// Failed integrity checks of an agreement with SLA terms:
+/// - `installment_index` - the installment the failed checks are counted for
+/// - `failed_checks` - the number of failed checks during the installment
+/// - `breaches` - the total number of breaches of the SLA terms
+#[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, Debug, Default, MaxEncodedLen, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct SlaRecord {
+    /// Installment the failed checks are counted for
+    pub installment_index: u32,
+    /// Number of failed checks during the installment
+    pub failed_checks: u32,
+    /// Total number of breaches of the SLA terms
+    pub breaches: u32,
+}
+
 /// Payment plan for the agreement. The payment plan is a vector of block numbers. The first
 /// element is the block number when the first installment is due. The last element is the block
 /// number when the agreement ends. The difference between two consecutive elements is the length
 /// of the installment in blocks.
 pub type PaymentPlan<T> = BoundedVec<BlockNumberFor<T>, <T as Config>::PaymentPlanLimit>;
 
-/// The vector of all the agreements for a single IP. The vector is bounded by the maximum number
-/// of agreements per IP (IPAgreementsLimit).
-pub type IPAgreementsVec<T> =
-    BoundedVec<<T as Config>::AgreementId, <T as Config>::IPAgreementsLimit>;
+/// The vector of all the offers published by a single IP. The vector is bounded by the maximum
+/// number of offers per IP (IPOffersLimit).
+pub type IPOffersVec<T> = BoundedVec<<T as Config>::OfferId, <T as Config>::IPOffersLimit>;
 
-/// The vector of all the agreements for a single consumer. The vector is bounded by the maximum
-/// number of agreements per consumer (ConsumerAgreementsLimit).
-pub type ConsumerAgreementsVec<T> =
-    BoundedVec<<T as Config>::AgreementId, <T as Config>::ConsumerAgreementsLimit>;
+/// The vector of the agreements to be settled or auto-paid at a single block. The vector is bounded
+/// by the maximum number of settlements per block (MaxSettlementsPerBlock).
+pub type SettlementsVec<T> =
+    BoundedVec<<T as Config>::AgreementId, <T as Config>::MaxSettlementsPerBlock>;
 
 /// The statuses an IP can have. When an IP is registered it has the status `Pending`. Then the IP
 /// can be activated by the network operator and the status changes to `Active`. The IP can deactivate itself
-/// and the status changes to `Unregistered`.
+/// and the status changes to `Unregistered`, either straight away or after `Draining` until all
+/// its agreements are closed.
 #[derive(Copy, Clone, Encode, Decode, Eq, PartialEq, MaxEncodedLen, TypeInfo, Debug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 pub enum IPStatus {
     /// IP is registered but not activated yet
     Pending,
@@ -65,11 +142,14 @@ pub enum IPStatus {
     Active,
     /// IP is deactivated
     Unregistered,
+    /// IP doesn't take on new agreements and is deactivated when its agreements are closed
+    Draining,
 }
 
 /// The score used to for ranking. The score is a number between 1 and 5.
 #[repr(u8)]
 #[derive(Copy, Clone, Encode, Decode, Eq, PartialEq, MaxEncodedLen, TypeInfo, Debug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 pub enum Score {
     /// One. Lowest score.
     One = 1,
@@ -100,35 +180,227 @@ pub struct Rating {
     pub number_of_scores: u32,
 }
 
+impl Rating {
+    /// Returns the average scores of the rating, or `None` if no scores have been given yet.
+    pub fn average(&self) -> Option<AverageRating> {
+        let average = |cumulative: u32| {
+            (cumulative as u64 * AverageRating::SCALE as u64 / self.number_of_scores as u64) as u32
+        };
+
+        (self.number_of_scores > 0).then(|| AverageRating {
+            performance: average(self.cumulative_performance),
+            stability: average(self.cumulative_stability),
+            support: average(self.cumulative_support),
+            number_of_scores: self.number_of_scores,
+        })
+    }
+}
+
+/// The average scores of an IP. The averages are multiplied by `AverageRating::SCALE`,
+/// e.g. `450` means an average score of 4.5.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, Debug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct AverageRating {
+    /// Average performance score
+    pub performance: u32,
+    /// Average stability score
+    pub stability: u32,
+    /// Average support score
+    pub support: u32,
+    /// Number of scores
+    pub number_of_scores: u32,
+}
+
+impl AverageRating {
+    /// The factor the average scores are multiplied by.
+    pub const SCALE: u32 = 100;
+}
+
+/// The reputation of an IP. Every score is weighted by the amount paid for the agreement it rates,
+/// and the weights decay over time, so that recent and valuable agreements count the most. The
+/// reputation has:
+/// - `weighted_performance` - is a sum of all the performance scores multiplied by their weights
+/// - `weighted_stability` - is a sum of all the stability scores multiplied by their weights
+/// - `weighted_support` - is a sum of all the support scores multiplied by their weights
+/// - `total_weight` - is a sum of all the weights
+/// - `last_decay_block` - the block the weights were last decayed at
+#[derive(Clone, Encode, Decode, Eq, PartialEq, Debug, Default, MaxEncodedLen, TypeInfo)]
+pub struct Reputation<BlockNumber> {
+    /// Weighted performance score
+    pub weighted_performance: u128,
+    /// Weighted stability score
+    pub weighted_stability: u128,
+    /// Weighted support score
+    pub weighted_support: u128,
+    /// Sum of the weights
+    pub total_weight: u128,
+    /// The block the weights were last decayed at
+    pub last_decay_block: BlockNumber,
+}
+
+impl<BlockNumber: AtLeast32BitUnsigned + Copy> Reputation<BlockNumber> {
+    /// Decays the weights for every full period elapsed since the last decay. Every period only
+    /// the `retained` share of the weights is kept.
+    pub fn decay(&mut self, now: BlockNumber, period: BlockNumber, retained: Perbill) {
+        if period.is_zero() {
+            return;
+        }
+
+        let periods = now.saturating_sub(self.last_decay_block) / period;
+        if periods.is_zero() {
+            return;
+        }
+
+        let factor = retained.saturating_pow(periods.saturated_into::<u32>() as usize);
+        self.weighted_performance = factor * self.weighted_performance;
+        self.weighted_stability = factor * self.weighted_stability;
+        self.weighted_support = factor * self.weighted_support;
+        self.total_weight = factor * self.total_weight;
+        self.last_decay_block = self
+            .last_decay_block
+            .saturating_add(periods.saturating_mul(period));
+    }
+
+    /// Adds the scores with the given weight.
+    pub fn add_score(
+        &mut self,
+        performance: Score,
+        stability: Score,
+        support: Score,
+        weight: u128,
+    ) {
+        let weighted = |score: Score| (score as u128).saturating_mul(weight);
+
+        self.weighted_performance = self
+            .weighted_performance
+            .saturating_add(weighted(performance));
+        self.weighted_stability = self.weighted_stability.saturating_add(weighted(stability));
+        self.weighted_support = self.weighted_support.saturating_add(weighted(support));
+        self.total_weight = self.total_weight.saturating_add(weight);
+    }
+
+    /// Returns the weighted average scores, or `None` if there is no weight left. The number of
+    /// scores is not tracked by the reputation, so it has to be provided.
+    pub fn average(&self, number_of_scores: u32) -> Option<AverageRating> {
+        let average = |weighted: u128| {
+            (weighted.saturating_mul(AverageRating::SCALE as u128) / self.total_weight) as u32
+        };
+
+        (!self.total_weight.is_zero()).then(|| AverageRating {
+            performance: average(self.weighted_performance),
+            stability: average(self.weighted_stability),
+            support: average(self.weighted_support),
+            number_of_scores,
+        })
+    }
+
+    /// Returns the reputation score used to rank the IPs, multiplied by `AverageRating::SCALE`.
+    /// The score is the weighted average of all the scores, where a neutral score of three with
+    /// the `prior_weight` is added to the scores. This way the score of an IP with little weight
+    /// stays close to neutral, and farming a high score requires agreements of high value.
+    pub fn score(&self, prior_weight: u128) -> u32 {
+        let total_weight = self.total_weight.saturating_add(prior_weight);
+        if total_weight.is_zero() {
+            return 0;
+        }
+
+        let weighted_scores = self
+            .weighted_performance
+            .saturating_add(self.weighted_stability)
+            .saturating_add(self.weighted_support)
+            .saturating_add(prior_weight.saturating_mul(3 * Score::Three as u128));
+        (weighted_scores.saturating_mul(AverageRating::SCALE as u128)
+            / total_weight.saturating_mul(3)) as u32
+    }
+}
+
+/// The rating of a consumer given by the IPs it had agreements with. The consumer rating has:
+/// - `cumulative_score` - is a sum of all the scores given
+/// - `number_of_scores` - the number of all the scores given
+#[derive(Clone, Encode, Decode, Eq, PartialEq, Debug, Default, MaxEncodedLen, TypeInfo)]
+pub struct ConsumerRating {
+    /// Cumulative score
+    pub cumulative_score: u32,
+    /// Number of scores
+    pub number_of_scores: u32,
+}
+
+impl ConsumerRating {
+    /// Returns the average score multiplied by `AverageRating::SCALE`, or `None` if no scores
+    /// have been given yet.
+    pub fn average(&self) -> Option<u32> {
+        (self.number_of_scores > 0).then(|| {
+            (self.cumulative_score as u64 * AverageRating::SCALE as u64
+                / self.number_of_scores as u64) as u32
+        })
+    }
+
+    /// Checks if the rating meets the minimum rating. Consumers that have not been rated yet meet
+    /// any minimum rating.
+    pub fn meets(&self, min_rating: Option<u32>) -> bool {
+        match (min_rating, self.average()) {
+            (Some(min_rating), Some(average)) => average >= min_rating,
+            _ => true,
+        }
+    }
+}
+
 /// The details of an IP. The IP has:
 /// - `total_storage` - the total storage the IP has
+/// - `allocated_storage` - the storage promised to agreements that are not completed yet
+/// - `total_compute` - the total compute resources the IP has
+/// - `allocated_compute` - the compute resources promised to agreements that are not completed yet
 /// - `status` - the current status of the IP
-/// - `agreements` - the vector of all the agreements for this IP
 /// - `deposit` - the deposit the IP has payed during the registration process
 /// - `rating` - the rating of the IP
+/// - `reputation` - the rating of the IP weighted by the value and the age of the agreements
+/// - `disputes` - the outcomes of the disputes the IP has been involved in
+/// - `reliability` - the record of the integrity checks, file conflicts and agreement outcomes
+/// - `min_consumer_rating` - the minimum average rating consumers must have to request agreements
+/// - `unrated_agreements` - the number of completed agreements whose consumer left no feedback
 #[derive(Clone, Encode, Decode, Eq, PartialEq, Debug, MaxEncodedLen, TypeInfo)]
 #[scale_info(skip_type_params(T))]
 #[codec(mel_bound(T: pallet::Config))]
 pub struct IPDetails<T: pallet::Config> {
     /// Total IP storage
     pub total_storage: StorageSizeMB,
+    /// Storage allocated to agreements that are not completed yet
+    pub allocated_storage: StorageSizeMB,
+    /// Total IP compute resources
+    pub total_compute: ComputeResources,
+    /// Compute resources allocated to agreements that are not completed yet
+    pub allocated_compute: ComputeResources,
     /// IP Status
     pub status: IPStatus,
-    /// Track of active agreements
-    pub agreements: IPAgreementsVec<T>,
     /// Deposit funds
     pub deposit: BalanceOf<T>,
     /// IP rating
     pub rating: Rating,
+    /// IP reputation
+    pub reputation: Reputation<BlockNumberFor<T>>,
+    /// IP dispute outcomes
+    pub disputes: DisputeRecord,
+    /// IP reliability record
+    pub reliability: ReliabilityRecord,
+    /// Minimum average consumer rating, multiplied by `AverageRating::SCALE`
+    pub min_consumer_rating: Option<u32>,
+    /// Completed agreements archived without feedback from the consumer
+    pub unrated_agreements: u32,
 }
 
 impl<T: pallet::Config> IPDetails<T> {
     /// Create a new IP with the status `Pending`.
-    pub fn new(total_storage: StorageSizeMB, deposit: BalanceOf<T>) -> Self {
+    pub fn new(
+        total_storage: StorageSizeMB,
+        total_compute: ComputeResources,
+        deposit: BalanceOf<T>,
+    ) -> Self {
         Self {
             total_storage,
+            allocated_storage: 0,
+            total_compute,
+            allocated_compute: Default::default(),
             status: IPStatus::Pending,
-            agreements: BoundedVec::new(),
             deposit,
             rating: Rating {
                 cumulative_performance: 0,
@@ -136,25 +408,181 @@ impl<T: pallet::Config> IPDetails<T> {
                 cumulative_support: 0,
                 number_of_scores: 0,
             },
+            reputation: Reputation {
+                last_decay_block: frame_system::Pallet::<T>::block_number(),
+                ..Default::default()
+            },
+            disputes: Default::default(),
+            reliability: Default::default(),
+            min_consumer_rating: None,
+            unrated_agreements: 0,
         }
     }
 }
 
 impl<T: pallet::Config> IPDetails<T> {
-    /// Updates the rating of the IP.
-    pub fn add_score(&mut self, performance: Score, stability: Score, support: Score) {
+    /// Updates the rating and the reputation of the IP. The scores are weighted by the given
+    /// weight, usually the amount paid for the rated agreement.
+    pub fn add_score(
+        &mut self,
+        performance: Score,
+        stability: Score,
+        support: Score,
+        weight: BalanceOf<T>,
+    ) {
         self.rating.cumulative_performance += performance as u32;
         self.rating.cumulative_stability += stability as u32;
         self.rating.cumulative_support += support as u32;
         self.rating.number_of_scores += 1;
+
+        self.reputation.decay(
+            frame_system::Pallet::<T>::block_number(),
+            T::ReputationDecayPeriod::get(),
+            T::ReputationRetainedWeight::get(),
+        );
+        self.reputation
+            .add_score(performance, stability, support, weight.saturated_into());
+    }
+
+    /// Returns the reputation of the IP decayed up to the current block.
+    pub fn current_reputation(&self) -> Reputation<BlockNumberFor<T>> {
+        let mut reputation = self.reputation.clone();
+        reputation.decay(
+            frame_system::Pallet::<T>::block_number(),
+            T::ReputationDecayPeriod::get(),
+            T::ReputationRetainedWeight::get(),
+        );
+        reputation
+    }
+
+    /// Returns the reputation score of the IP used for ranking, multiplied by
+    /// `AverageRating::SCALE`.
+    pub fn reputation_score(&self) -> u32 {
+        self.current_reputation()
+            .score(T::ReputationPriorWeight::get().saturated_into())
+    }
+
+    /// Returns the storage that is not allocated to any agreement yet.
+    pub fn free_storage(&self) -> StorageSizeMB {
+        self.total_storage.saturating_sub(self.allocated_storage)
+    }
+
+    /// Allocates storage for an agreement. Fails if the IP doesn't have enough free storage.
+    pub fn allocate_storage(&mut self, storage: StorageSizeMB) -> DispatchResult {
+        ensure!(
+            storage <= self.free_storage(),
+            Error::<T>::InsufficientStorage
+        );
+
+        self.allocated_storage = self.allocated_storage.saturating_add(storage);
+        Ok(())
+    }
+
+    /// Releases storage previously allocated for an agreement.
+    pub fn release_storage(&mut self, storage: StorageSizeMB) {
+        self.allocated_storage = self.allocated_storage.saturating_sub(storage);
+    }
+
+    /// Returns the compute resources that are not allocated to any agreement yet.
+    pub fn free_compute(&self) -> ComputeResources {
+        self.total_compute.saturating_sub(&self.allocated_compute)
+    }
+
+    /// Allocates compute resources for an agreement. Fails if the IP doesn't have enough free
+    /// vCores or RAM.
+    pub fn allocate_compute(&mut self, compute: ComputeResources) -> DispatchResult {
+        ensure!(
+            compute.fits_in(&self.free_compute()),
+            Error::<T>::InsufficientCompute
+        );
+
+        self.allocated_compute = self.allocated_compute.saturating_add(&compute);
+        Ok(())
+    }
+
+    /// Releases compute resources previously allocated for an agreement.
+    pub fn release_compute(&mut self, compute: ComputeResources) {
+        self.allocated_compute = self.allocated_compute.saturating_sub(&compute);
+    }
+}
+
+/// The profile of a consumer. It has:
+/// - `disputes` - the outcomes of the disputes the consumer has been involved in
+/// - `rating` - the rating of the consumer given by the IPs
+/// - `nonpay_terminations` - the number of agreements terminated by the IPs due to non-payment
+#[derive(Clone, Encode, Decode, Eq, PartialEq, Debug, Default, MaxEncodedLen, TypeInfo)]
+pub struct ConsumerProfile {
+    /// Consumer dispute outcomes
+    pub disputes: DisputeRecord,
+    /// Consumer rating
+    pub rating: ConsumerRating,
+    /// Number of agreements terminated due to non-payment
+    pub nonpay_terminations: u32,
+}
+
+impl ConsumerProfile {
+    /// Updates the rating of the consumer.
+    pub fn add_score(&mut self, score: Score) {
+        self.rating.cumulative_score += score as u32;
+        self.rating.number_of_scores += 1;
     }
 }
 
+/// The comment of a feedback. The comment is bounded by the maximum comment length
+/// (MaxCommentLength).
+pub type Comment<T> = BoundedVec<u8, <T as Config>::MaxCommentLength>;
+
+/// A review of an IP, stored in the review log of the IP when a consumer submits feedback for a
+/// completed agreement. The review has:
+/// - `agreement_id` - the reviewed agreement
+/// - `consumer` - the consumer that submitted the feedback
+/// - `score_performance` - the performance score
+/// - `score_stability` - the stability score
+/// - `score_support` - the support score
+/// - `comment` - the comment of the feedback
+/// - `block_number` - the block the feedback was submitted at
+#[derive(Clone, Encode, Decode, Eq, PartialEq, Debug, MaxEncodedLen, TypeInfo)]
+#[scale_info(skip_type_params(T))]
+#[codec(mel_bound(T: pallet::Config))]
+pub struct Review<T: pallet::Config> {
+    /// The reviewed agreement
+    pub agreement_id: T::AgreementId,
+    /// The consumer that submitted the feedback
+    pub consumer: AccountIdOf<T>,
+    /// The performance score
+    pub score_performance: Score,
+    /// The stability score
+    pub score_stability: Score,
+    /// The support score
+    pub score_support: Score,
+    /// The comment of the feedback
+    pub comment: Comment<T>,
+    /// The block the feedback was submitted at
+    pub block_number: BlockNumberFor<T>,
+}
+
+/// An agreement whose consumer can be rated by the IP. Created when the agreement is completed or
+/// terminated, and removed once the IP rates the consumer or the feedback window closes.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, Debug, MaxEncodedLen, TypeInfo)]
+#[scale_info(skip_type_params(T))]
+#[codec(mel_bound(T: pallet::Config))]
+pub struct PendingConsumerRating<T: pallet::Config> {
+    /// IP that can rate the consumer
+    pub ip: AccountIdOf<T>,
+    /// Consumer to be rated
+    pub consumer: AccountIdOf<T>,
+    /// Last block when the IP can rate the consumer
+    pub deadline: BlockNumberFor<T>,
+}
+
 /// The statuses an agreement can have. When a consumer requests an agreement the status is
 /// `ConsumerRequest`. The IP can agree to the agreement and the status changes to `Active`, or
 /// the IP can propose a payment plan and the status changes to `IPProposedPaymentPlan`. If the
-/// consumer accepts the payment plan the status changes to `Active`.
+/// consumer accepts the payment plan the status changes to `Active`. Either party can dispute an
+/// active agreement and the status changes to `Disputed` until the dispute is resolved. If an
+/// installment is overdue, the status changes to `Suspended` until the consumer pays it.
 #[derive(Copy, Clone, Encode, Decode, Eq, PartialEq, MaxEncodedLen, TypeInfo, Debug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 pub enum AgreementStatus {
     /// Consumer requested an agreement
     ConsumerRequest,
@@ -164,6 +592,12 @@ pub enum AgreementStatus {
     Active,
     /// Agreement is completed, meaning that the IP has received all the payments
     Completed,
+    /// Agreement is disputed, meaning that no funds can be transferred until the dispute is
+    /// resolved
+    Disputed,
+    /// Agreement is suspended because an installment is overdue, meaning that the IP may stop
+    /// serving the consumer data and the files are read-only until the installment is paid
+    Suspended,
 }
 
 /// An item of the payment history.
@@ -213,9 +647,15 @@ impl<T: pallet::Config> PaymentHistory<T> {
 /// - `consumer_security_deposit_transferred` - flag indicating if the consumer security deposit is transferred to the IP
 /// - `status` - the current status of the agreement
 /// - `storage` - the amount of storage covered by the agreement
+/// - `compute` - the compute resources covered by the agreement
+/// - `prices` - the prices locked when the agreement was requested
 /// - `activation_block` - the block number when the rental starts
 /// - `payment_plan` - the payment plan for the agreement
 /// - `payment_history` - the payment history for the agreement
+/// - `auto_pay` - flag indicating if the installments are prepaid automatically
+/// - `auto_renew` - flag indicating if the payment plan is extended automatically when it ends
+/// - `sla` - the SLA terms of the agreement, if any
+/// - `sla_record` - the failed integrity checks counted against the SLA terms
 #[derive(Clone, Encode, Decode, Eq, PartialEq, Debug, MaxEncodedLen, TypeInfo)]
 #[scale_info(skip_type_params(T))]
 #[codec(mel_bound(T: pallet::Config))]
@@ -234,12 +674,24 @@ pub struct AgreementDetails<T: pallet::Config> {
     pub status: AgreementStatus,
     /// The amount of storage covered by the agreement
     pub storage: StorageSizeMB,
+    /// The compute resources covered by the agreement
+    pub compute: ComputeResources,
+    /// The prices locked when the agreement was requested
+    pub prices: Prices<T>,
     /// The block number when the rental starts
     pub activation_block: BlockNumberFor<T>,
     /// The payment plan for the agreement
     pub payment_plan: PaymentPlan<T>,
     /// The payment history for the agreement
     pub payment_history: PaymentHistory<T>,
+    /// Flag indicating if the installments are prepaid automatically
+    pub auto_pay: bool,
+    /// Flag indicating if the payment plan is extended automatically when it ends
+    pub auto_renew: bool,
+    /// SLA terms of the agreement, if any
+    pub sla: Option<SlaTerms>,
+    /// Failed integrity checks counted against the SLA terms
+    pub sla_record: SlaRecord,
 }
 
 impl<T: pallet::Config> AgreementDetails<T> {
@@ -263,7 +715,8 @@ impl<T: pallet::Config> AgreementDetails<T> {
     }
 
     /// Calculate the cost of an installment. The cost of the installment is the length of the
-    /// installment multiplied by the storage cost per block multiplied by the amount of MB requested in the agreement.
+    /// installment multiplied by the cost per block of the storage and compute resources requested in the agreement.
+    /// The prices are the ones locked in the agreement, so price updates don't affect it.
     ///
     /// The installment index is the index of the installment in the payment plan. The first
     /// installment has index 0.
@@ -271,11 +724,9 @@ impl<T: pallet::Config> AgreementDetails<T> {
     /// Returns `None` if the installment index is out of bounds.
     fn calculate_installment_cost(&self, installment_index: usize) -> Option<BalanceOf<T>> {
         let installment_length = self.calculate_installment_length(installment_index)?;
-        let cost = CurrentPrices::<T>::get()
-            .storage_mb_per_block
-            .saturating_mul(BalanceOf::<T>::saturated_from(
-                installment_length.saturated_into::<u128>() * self.storage as u128,
-            ));
+        let cost = self
+            .prices
+            .cost(self.storage, self.compute, installment_length);
 
         Some(cost)
     }
@@ -287,14 +738,18 @@ impl<T: pallet::Config> AgreementDetails<T> {
             .expect("empty payment plan is not allowed")
     }
 
-    /// Create a new agreement with the status `ConsumerRequest`. The deposit is not calculated
-    /// here, but it is calculated when the deposit is held.
+    /// Create a new agreement with the status `ConsumerRequest`. The given prices are locked in
+    /// the agreement. The deposit is not calculated here, but it is calculated when the deposit
+    /// is held.
     pub fn new_consumer_request(
         ip: AccountIdOf<T>,
         consumer: AccountIdOf<T>,
         storage: StorageSizeMB,
+        compute: ComputeResources,
+        prices: Prices<T>,
         activation_block: BlockNumberFor<T>,
         payment_plan: PaymentPlan<T>,
+        sla: Option<SlaTerms>,
     ) -> Self {
         Self {
             ip,
@@ -304,9 +759,27 @@ impl<T: pallet::Config> AgreementDetails<T> {
             consumer_security_deposit_transferred: false,
             status: AgreementStatus::ConsumerRequest,
             storage,
+            compute,
+            prices,
             activation_block,
             payment_plan,
             payment_history: PaymentHistory::new(),
+            auto_pay: false,
+            auto_renew: false,
+            sla,
+            sla_record: SlaRecord::default(),
+        }
+    }
+
+    /// Returns the party of the agreement the account is, or `None` if the account is not a party
+    /// of the agreement.
+    pub fn party(&self, who: &AccountIdOf<T>) -> Option<AgreementParty> {
+        if self.ip == *who {
+            Some(AgreementParty::IP)
+        } else if self.consumer == *who {
+            Some(AgreementParty::Consumer)
+        } else {
+            None
         }
     }
 
@@ -377,6 +850,53 @@ impl<T: pallet::Config> AgreementDetails<T> {
         Ok(deposit.saturating_add(service_deposit))
     }
 
+    /// Transfers up to `amount` from the consumer service deposit to the given account.
+    ///
+    /// Returns the amount transferred.
+    pub fn pay_from_consumer_service_deposit(
+        &mut self,
+        to: &AccountIdOf<T>,
+        amount: BalanceOf<T>,
+    ) -> Result<BalanceOf<T>, DispatchError> {
+        let amount = amount.min(self.consumer_service_deposit);
+        T::Currency::transfer_on_hold(
+            &HoldReason::ConsumerServiceDeposit.into(),
+            &self.consumer,
+            to,
+            amount,
+            Exact,
+            Free,
+            Force,
+        )?;
+
+        self.consumer_service_deposit = self.consumer_service_deposit.saturating_sub(amount);
+        Ok(amount)
+    }
+
+    /// Releases the installments prepaid by the consumer and not transferred to the IP yet.
+    ///
+    /// Returns the amount released.
+    pub fn release_installments(&mut self) -> Result<BalanceOf<T>, DispatchError> {
+        let installments = self
+            .payment_history
+            .records
+            .iter_mut()
+            .filter(|record| !record.transferred)
+            .fold(BalanceOf::<T>::zero(), |total, record| {
+                record.transferred = true;
+                total.saturating_add(record.amount)
+            });
+
+        T::Currency::release(
+            &HoldReason::ConsumerInstallment.into(),
+            &self.consumer,
+            installments,
+            Exact,
+        )?;
+
+        Ok(installments)
+    }
+
     /// Adjusts the consumer security deposit for the agreement. This is called when the payment plan is
     /// changed. The deposit amount currently held is adjusted to the new deposit amount.
     /// The new deposit amount is calculated based on the new payment plan and stored in the
@@ -442,6 +962,175 @@ impl<T: pallet::Config> AgreementDetails<T> {
         Ok(self.consumer_service_deposit)
     }
 
+    /// Splits the funds held for the agreement between the consumer and the IP. The consumer
+    /// gets `consumer_share` of the untransferred installments, the security deposit and the
+    /// service deposit. The rest is transferred to the IP.
+    ///
+    /// Returns the amounts released to the consumer and transferred to the IP.
+    pub fn split_held_funds(
+        &mut self,
+        consumer_share: Perbill,
+    ) -> Result<(BalanceOf<T>, BalanceOf<T>), DispatchError> {
+        let installments = self
+            .payment_history
+            .records
+            .iter_mut()
+            .filter(|record| !record.transferred)
+            .fold(BalanceOf::<T>::zero(), |total, record| {
+                record.transferred = true;
+                total.saturating_add(record.amount)
+            });
+        let security_deposit = if self.consumer_security_deposit_transferred {
+            BalanceOf::<T>::zero()
+        } else {
+            self.consumer_security_deposit
+        };
+
+        let mut consumer_total = BalanceOf::<T>::zero();
+        let mut ip_total = BalanceOf::<T>::zero();
+        for (reason, amount) in [
+            (HoldReason::ConsumerInstallment, installments),
+            (HoldReason::ConsumerSecurityDeposit, security_deposit),
+            (
+                HoldReason::ConsumerServiceDeposit,
+                self.consumer_service_deposit,
+            ),
+        ] {
+            let consumer_amount = consumer_share.mul_floor(amount);
+            let ip_amount = amount.saturating_sub(consumer_amount);
+
+            T::Currency::transfer_on_hold(
+                &reason.into(),
+                &self.consumer,
+                &self.ip,
+                ip_amount,
+                Exact,
+                Free,
+                Force,
+            )?;
+            T::Currency::release(&reason.into(), &self.consumer, consumer_amount, Exact)?;
+
+            consumer_total = consumer_total.saturating_add(consumer_amount);
+            ip_total = ip_total.saturating_add(ip_amount);
+        }
+
+        self.consumer_security_deposit_transferred = true;
+        self.consumer_service_deposit = BalanceOf::<T>::zero();
+        Ok((consumer_total, ip_total))
+    }
+
+    /// Returns the amount earned by the IP up to the given block. Each installment is earned pro
+    /// rata to the blocks elapsed since its start. Prepaid installments are valued at the amount
+    /// held, the rest at their cost.
+    pub fn earned_amount(&self, block_number: BlockNumberFor<T>) -> BalanceOf<T> {
+        let mut start_block = self.activation_block;
+        let mut earned = BalanceOf::<T>::zero();
+
+        for (index, end_block) in self.payment_plan.iter().enumerate() {
+            if block_number <= start_block {
+                break;
+            }
+
+            let cost = self
+                .payment_history
+                .records
+                .get(index)
+                .map(|record| record.amount)
+                .or_else(|| self.calculate_installment_cost(index))
+                .unwrap_or_default();
+            let elapsed = block_number.min(*end_block).saturating_sub(start_block);
+            let length = end_block.saturating_sub(start_block);
+
+            earned = earned.saturating_add(
+                Perbill::from_rational(
+                    elapsed.saturated_into::<u64>(),
+                    length.saturated_into::<u64>(),
+                )
+                .mul_floor(cost),
+            );
+            start_block = *end_block;
+        }
+
+        earned
+    }
+
+    /// Terminates the agreement early at the given block. The IP receives the amount earned and not
+    /// transferred yet from the held installments and, after them, from the security deposit,
+    /// together with the `penalty` share of the security deposit. The rest of the held funds and
+    /// the service deposit are released to the consumer.
+    ///
+    /// Returns the amounts transferred to the IP and released to the consumer.
+    pub fn terminate_early(
+        &mut self,
+        block_number: BlockNumberFor<T>,
+        penalty: Perbill,
+    ) -> Result<(BalanceOf<T>, BalanceOf<T>), DispatchError> {
+        let transferred = self
+            .payment_history
+            .records
+            .iter()
+            .filter(|record| record.transferred)
+            .fold(BalanceOf::<T>::zero(), |total, record| {
+                total.saturating_add(record.amount)
+            });
+        let installments = self
+            .payment_history
+            .records
+            .iter_mut()
+            .filter(|record| !record.transferred)
+            .fold(BalanceOf::<T>::zero(), |total, record| {
+                record.transferred = true;
+                total.saturating_add(record.amount)
+            });
+
+        let mut owed = self.earned_amount(block_number).saturating_sub(transferred);
+        let mut ip_total = BalanceOf::<T>::zero();
+        let mut consumer_total = BalanceOf::<T>::zero();
+        for (reason, amount, extra) in [
+            (
+                HoldReason::ConsumerInstallment,
+                installments,
+                BalanceOf::<T>::zero(),
+            ),
+            (
+                HoldReason::ConsumerSecurityDeposit,
+                self.consumer_security_deposit,
+                penalty.mul_floor(self.consumer_security_deposit),
+            ),
+        ] {
+            let due = owed.saturating_add(extra);
+            let ip_amount = due.min(amount);
+            let consumer_amount = amount.saturating_sub(ip_amount);
+
+            T::Currency::transfer_on_hold(
+                &reason.into(),
+                &self.consumer,
+                &self.ip,
+                ip_amount,
+                Exact,
+                Free,
+                Force,
+            )?;
+            T::Currency::release(&reason.into(), &self.consumer, consumer_amount, Exact)?;
+
+            owed = owed.saturating_sub(ip_amount);
+            ip_total = ip_total.saturating_add(ip_amount);
+            consumer_total = consumer_total.saturating_add(consumer_amount);
+        }
+
+        T::Currency::release(
+            &HoldReason::ConsumerServiceDeposit.into(),
+            &self.consumer,
+            self.consumer_service_deposit,
+            Exact,
+        )?;
+        consumer_total = consumer_total.saturating_add(self.consumer_service_deposit);
+
+        self.consumer_security_deposit_transferred = true;
+        self.consumer_service_deposit = BalanceOf::<T>::zero();
+        Ok((ip_total, consumer_total))
+    }
+
     /// Holds the next installment for the agreement. The installment is calculated based on the
     /// payment plan and stored in the agreement's payment history.
     ///
@@ -524,32 +1213,927 @@ impl<T: pallet::Config> AgreementDetails<T> {
         Ok(total)
     }
 
+    /// Returns the block when the next installment of the agreement can be transferred to the IP,
+    /// i.e. the block following the end of the first untransferred installment that has not ended
+    /// before `block_number`.
+    pub fn next_settlement_block(&self, block_number: BlockNumberFor<T>) -> BlockNumberFor<T> {
+        self.payment_plan
+            .iter()
+            .skip(self.payment_history.next_transfer_installment_index as usize)
+            .find(|end_block| **end_block >= block_number)
+            .copied()
+            .unwrap_or(block_number)
+            .saturating_add(One::one())
+    }
+
+    /// Returns the payment plan extended with the given installments. The extension must not be
+    /// empty, it must start after the end of the payment plan and be strictly increasing, and the
+    /// extended payment plan must not exceed the payment plan limit.
+    pub fn extended_payment_plan(
+        &self,
+        extension: &[BlockNumberFor<T>],
+    ) -> Result<PaymentPlan<T>, DispatchError> {
+        self.payment_plan_with_tail(self.payment_plan.len(), extension)
+    }
+
+    /// Returns the payment plan with its unpaid installments replaced by the given tail. The tail
+    /// must not be empty, it must end in the future, start after the end of the last prepaid
+    /// installment and be strictly increasing, and the amended payment plan must not exceed the
+    /// payment plan limit.
+    pub fn amended_payment_plan(
+        &self,
+        tail: &[BlockNumberFor<T>],
+        block_number: BlockNumberFor<T>,
+    ) -> Result<PaymentPlan<T>, DispatchError> {
+        ensure!(
+            tail.first()
+                .map_or(false, |end_block| *end_block > block_number),
+            Error::<T>::PaymentPlanInvalid
+        );
+        self.payment_plan_with_tail(self.payment_history.records.len(), tail)
+    }
+
+    /// Returns the first `kept` installments of the payment plan followed by the given tail.
+    fn payment_plan_with_tail(
+        &self,
+        kept: usize,
+        tail: &[BlockNumberFor<T>],
+    ) -> Result<PaymentPlan<T>, DispatchError> {
+        let mut payment_plan = self.payment_plan.clone();
+        payment_plan.truncate(kept);
+        for end_block in tail {
+            payment_plan
+                .try_push(*end_block)
+                .map_err(|_| Error::<T>::PaymentPlanInvalid)?;
+        }
+
+        ensure!(
+            !tail.is_empty()
+                && payment_plan[0] > self.activation_block
+                && crate::is_strictly_increasing(&payment_plan),
+            Error::<T>::PaymentPlanInvalid
+        );
+        Ok(payment_plan)
+    }
+
+    /// Amends the storage and/or the unpaid installments of the agreement. The prepaid
+    /// installments that have not started yet are recalculated with the new terms, holding or
+    /// releasing the difference from the consumer. The started installments keep their cost. The
+    /// security deposit is then adjusted to the cost of the new last installment.
+    ///
+    /// Returns the new security deposit amount.
+    pub fn amend(
+        &mut self,
+        storage: Option<StorageSizeMB>,
+        payment_plan_tail: Option<&[BlockNumberFor<T>]>,
+        block_number: BlockNumberFor<T>,
+    ) -> Result<BalanceOf<T>, DispatchError> {
+        if let Some(tail) = payment_plan_tail {
+            self.payment_plan = self.amended_payment_plan(tail, block_number)?;
+        }
+        if let Some(storage) = storage {
+            self.storage = storage;
+        }
+
+        let first_untransferred = self.payment_history.next_transfer_installment_index as usize;
+        for index in first_untransferred..self.payment_history.records.len() {
+            let start_block = index
+                .checked_sub(1)
+                .map(|i| self.payment_plan[i])
+                .unwrap_or(self.activation_block);
+            if start_block <= block_number {
+                continue;
+            }
+
+            let current_cost = self.payment_history.records[index].amount;
+            let new_cost = self
+                .calculate_installment_cost(index)
+                .expect("prepaid installments are in the payment plan");
+
+            match current_cost.cmp(&new_cost) {
+                Ordering::Less => T::Currency::hold(
+                    &HoldReason::ConsumerInstallment.into(),
+                    &self.consumer,
+                    new_cost - current_cost,
+                ),
+                Ordering::Greater => T::Currency::release(
+                    &HoldReason::ConsumerInstallment.into(),
+                    &self.consumer,
+                    current_cost - new_cost,
+                    Exact,
+                )
+                .map(|_| ()),
+                Ordering::Equal => Ok(()),
+            }?;
+
+            self.payment_history.records[index].amount = new_cost;
+        }
+
+        self.adjust_consumer_security_deposit()
+    }
+
+    /// Extends the payment plan with the given installments. If all the installments but the last
+    /// one are prepaid, the security deposit becomes the payment of the last one. The security
+    /// deposit is then adjusted to the cost of the new last installment.
+    ///
+    /// Returns the new security deposit amount.
+    pub fn extend_payment_plan(
+        &mut self,
+        extension: &[BlockNumberFor<T>],
+    ) -> Result<BalanceOf<T>, DispatchError> {
+        let payment_plan = self.extended_payment_plan(extension)?;
+
+        if self.payment_history.records.len() == self.payment_plan.len() - 1 {
+            let amount = self.consumer_security_deposit;
+            T::Currency::release(
+                &HoldReason::ConsumerSecurityDeposit.into(),
+                &self.consumer,
+                amount,
+                Exact,
+            )?;
+            T::Currency::hold(
+                &HoldReason::ConsumerInstallment.into(),
+                &self.consumer,
+                amount,
+            )?;
+
+            self.payment_history
+                .records
+                .try_push(PaymentRecord {
+                    amount,
+                    transferred: false,
+                })
+                .map_err(|_| ())
+                .expect("payment history should never exceed the payment plan");
+            self.consumer_security_deposit = BalanceOf::<T>::zero();
+        }
+
+        self.payment_plan = payment_plan;
+        self.adjust_consumer_security_deposit()
+    }
+
+    /// Returns the end block of the installment that auto-renew appends to the payment plan, or
+    /// `None` if the agreement is not flagged to auto-renew or its last installment has not
+    /// started yet. The appended installment has the same length as the last one.
+    pub fn auto_renew_end_block(
+        &self,
+        block_number: BlockNumberFor<T>,
+    ) -> Option<BlockNumberFor<T>> {
+        let last_index = self.payment_plan.len() - 1;
+        let last_start = last_index
+            .checked_sub(1)
+            .map(|i| self.payment_plan[i])
+            .unwrap_or(self.activation_block);
+
+        (self.auto_renew && block_number > last_start).then(|| {
+            self.payment_plan[last_index].saturating_add(
+                self.calculate_installment_length(last_index)
+                    .unwrap_or_default(),
+            )
+        })
+    }
+
+    /// Returns the block when auto-pay prepays the next installment, `AutoPayLeadBlocks` blocks
+    /// before it is due, or `None` if all installments are paid.
+    pub fn next_auto_pay_block(&self) -> Option<BlockNumberFor<T>> {
+        self.next_due_installment().map(|installment| {
+            installment
+                .due_block
+                .saturating_sub(T::AutoPayLeadBlocks::get())
+        })
+    }
+
+    /// Returns the next installment the consumer has to prepay, or `None` if all installments
+    /// are paid. The last installment is not included, as it is paid with the consumer deposit.
+    pub fn next_due_installment(&self) -> Option<InstallmentInfo<BalanceOf<T>, BlockNumberFor<T>>> {
+        let index = self.payment_history.records.len();
+        if index + 1 >= self.payment_plan.len() {
+            return None;
+        }
+
+        let due_block = index
+            .checked_sub(1)
+            .map(|i| self.payment_plan[i])
+            .unwrap_or(self.activation_block);
+
+        Some(InstallmentInfo {
+            index: index as u32,
+            due_block,
+            end_block: self.payment_plan[index],
+            cost: self.calculate_installment_cost(index)?,
+        })
+    }
+
+    /// Returns the index of the installment running at the given block. The first installment
+    /// is returned before the activation block and the length of the payment plan after its end.
+    fn current_installment_index(&self, block_number: BlockNumberFor<T>) -> usize {
+        self.payment_plan
+            .iter()
+            .position(|end_block| *end_block >= block_number)
+            .unwrap_or(self.payment_plan.len())
+    }
+
+    /// Records a failed integrity check of the agreement files at the given block. The failed
+    /// checks are counted per installment and every check over the limit of the SLA terms is
+    /// recorded as a breach.
+    ///
+    /// Returns `true` if the failed check breaches the SLA terms.
+    pub fn record_failed_check(&mut self, block_number: BlockNumberFor<T>) -> bool {
+        let Some(sla) = self.sla else {
+            return false;
+        };
+
+        let installment_index = self.current_installment_index(block_number) as u32;
+        if self.sla_record.installment_index != installment_index {
+            self.sla_record.installment_index = installment_index;
+            self.sla_record.failed_checks = 0;
+        }
+
+        self.sla_record.failed_checks.saturating_inc();
+        let breached = self.sla_record.failed_checks > sla.max_failed_checks;
+        if breached {
+            self.sla_record.breaches.saturating_inc();
+        }
+        breached
+    }
+
+    /// Refunds the given share of the installment running at the given block to the consumer.
+    /// The refund is released from the prepaid installment, or from the security deposit for the
+    /// last installment. Nothing is refunded if the installment is not held anymore, i.e. it is
+    /// not prepaid yet or already transferred to the IP.
+    ///
+    /// Returns the amount refunded.
+    pub fn refund_installment_share(
+        &mut self,
+        share: Perbill,
+        block_number: BlockNumberFor<T>,
+    ) -> Result<BalanceOf<T>, DispatchError> {
+        let installment_index = self.current_installment_index(block_number);
+        let Some(cost) = self.calculate_installment_cost(installment_index) else {
+            return Ok(Zero::zero());
+        };
+        let refund = share.mul_floor(cost);
+
+        let (reason, held) = if installment_index == self.payment_plan.len() - 1 {
+            if self.consumer_security_deposit_transferred {
+                return Ok(Zero::zero());
+            }
+            (
+                HoldReason::ConsumerSecurityDeposit,
+                &mut self.consumer_security_deposit,
+            )
+        } else {
+            match self
+                .payment_history
+                .records
+                .get_mut(installment_index)
+                .filter(|record| !record.transferred)
+            {
+                Some(record) => (HoldReason::ConsumerInstallment, &mut record.amount),
+                None => return Ok(Zero::zero()),
+            }
+        };
+
+        let refund = refund.min(*held);
+        T::Currency::release(&reason.into(), &self.consumer, refund, Exact)?;
+        *held = held.saturating_sub(refund);
+        Ok(refund)
+    }
+
+    /// Checks if the agreement has expired at the given block. An agreement request expires when
+    /// it is still at negotiation stage after its activation block, so it can no longer be
+    /// accepted. A completed agreement expires when the consumer has not left feedback by the
+    /// feedback deadline.
+    pub fn is_expired(&self, block_number: BlockNumberFor<T>) -> bool {
+        match self.status {
+            AgreementStatus::ConsumerRequest | AgreementStatus::IPProposedPaymentPlan => {
+                self.activation_block < block_number
+            }
+            AgreementStatus::Completed => self.feedback_deadline() < block_number,
+            _ => false,
+        }
+    }
+
+    /// Returns the last block the consumer can leave feedback for the agreement, `FeedbackPeriod`
+    /// blocks after the end of the payment plan.
+    pub fn feedback_deadline(&self) -> BlockNumberFor<T> {
+        self.payment_plan
+            .last()
+            .copied()
+            .unwrap_or(self.activation_block)
+            .saturating_add(T::FeedbackPeriod::get())
+    }
+
+    /// Checks if the grace period of the overdue installment has expired at the given block. The
+    /// grace period lasts `PaymentGracePeriod` blocks after the installment is due.
+    pub fn grace_period_expired(&self, block_number: BlockNumberFor<T>) -> bool {
+        self.has_overdue_installments(block_number)
+            && self.next_due_installment().is_some_and(|installment| {
+                block_number
+                    > installment
+                        .due_block
+                        .saturating_add(T::PaymentGracePeriod::get())
+            })
+    }
+
+    /// Transfers the late fee for an overdue installment from the consumer to the IP.
+    ///
+    /// Returns the amount transferred.
+    pub fn pay_late_fee(&self, cost: BalanceOf<T>) -> Result<BalanceOf<T>, DispatchError> {
+        let late_fee = T::LateFee::get().mul_floor(cost);
+        T::Currency::transfer(&self.consumer, &self.ip, late_fee, Preserve)?;
+        Ok(late_fee)
+    }
+
     /// Checks if there are any overdue installments. An installment is overdue if it is not
     /// prepaid by the consumer before the start of the installment. The last installment is
     /// always prepaid by the consumer deposit.
     pub fn has_overdue_installments(&self, block_number: BlockNumberFor<T>) -> bool {
         let next_unpaid_installment = self.payment_history.records.len();
 
-        next_unpaid_installment < self.payment_plan.len() - 1
-            && ((next_unpaid_installment == 0 && block_number >= self.activation_block)
-                || (block_number > self.payment_plan[next_unpaid_installment - 1]))
+        if next_unpaid_installment >= self.payment_plan.len().saturating_sub(1) {
+            return false;
+        }
+
+        // The first installment starts at the activation block, the following ones at the end of
+        // the previous installment
+        match next_unpaid_installment.checked_sub(1) {
+            None => block_number >= self.activation_block,
+            Some(previous_installment) => block_number > self.payment_plan[previous_installment],
+        }
+    }
+
+    /// Returns the amount transferred to the IP so far: the transferred installments and the
+    /// consumer security deposit, if it is transferred.
+    pub fn total_paid(&self) -> BalanceOf<T> {
+        let installments = self
+            .payment_history
+            .records
+            .iter()
+            .filter(|record| record.transferred)
+            .fold(BalanceOf::<T>::zero(), |total, record| {
+                total.saturating_add(record.amount)
+            });
+
+        if self.consumer_security_deposit_transferred {
+            installments.saturating_add(self.consumer_security_deposit)
+        } else {
+            installments
+        }
+    }
+}
+
+/// A proposal to renew an agreement. It has:
+/// - `proposed_by` - the party proposing the renewal
+/// - `extension` - the installments appended to the payment plan
+/// - `auto_renew` - flag indicating if the agreement is flagged to auto-renew once accepted
+#[derive(Clone, Encode, Decode, Eq, PartialEq, Debug, MaxEncodedLen, TypeInfo)]
+#[scale_info(skip_type_params(T))]
+#[codec(mel_bound(T: pallet::Config))]
+pub struct RenewalProposal<T: pallet::Config> {
+    /// The party proposing the renewal
+    pub proposed_by: AgreementParty,
+    /// The installments appended to the payment plan
+    pub extension: PaymentPlan<T>,
+    /// Flag indicating if the agreement is flagged to auto-renew once accepted
+    pub auto_renew: bool,
+}
+
+/// A proposal to amend an active agreement. It has:
+/// - `proposed_by` - the party proposing the amendment
+/// - `storage` - the new storage of the agreement, if it changes
+/// - `payment_plan_tail` - the installments replacing the unpaid ones, if they change
+#[derive(Clone, Encode, Decode, Eq, PartialEq, Debug, MaxEncodedLen, TypeInfo)]
+#[scale_info(skip_type_params(T))]
+#[codec(mel_bound(T: pallet::Config))]
+pub struct AmendmentProposal<T: pallet::Config> {
+    /// The party proposing the amendment
+    pub proposed_by: AgreementParty,
+    /// The new storage of the agreement, if it changes
+    pub storage: Option<StorageSizeMB>,
+    /// The installments replacing the unpaid ones, if they change
+    pub payment_plan_tail: Option<PaymentPlan<T>>,
+}
+
+/// Evidence supporting a dispute, e.g. the hash or CID of a document stored off-chain. The
+/// evidence is bounded by the maximum evidence length (MaxEvidenceLength).
+pub type EvidenceHash<T> = BoundedVec<u8, <T as Config>::MaxEvidenceLength>;
+
+/// The parties of an agreement.
+#[derive(Copy, Clone, Encode, Decode, Eq, PartialEq, MaxEncodedLen, TypeInfo, Debug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum AgreementParty {
+    /// The IP of the agreement
+    IP,
+    /// The consumer of the agreement
+    Consumer,
+}
+
+/// The details of a dispute. The dispute has:
+/// - `opened_by` - the party that opened the dispute
+/// - `evidence` - the evidence supporting the dispute
+/// - `opened_at` - the block number when the dispute was opened
+#[derive(Clone, Encode, Decode, Eq, PartialEq, Debug, MaxEncodedLen, TypeInfo)]
+#[scale_info(skip_type_params(T))]
+#[codec(mel_bound(T: pallet::Config))]
+pub struct DisputeDetails<T: pallet::Config> {
+    /// Party that opened the dispute
+    pub opened_by: AgreementParty,
+    /// Evidence supporting the dispute
+    pub evidence: EvidenceHash<T>,
+    /// The block number when the dispute was opened
+    pub opened_at: BlockNumberFor<T>,
+}
+
+/// The outcome of a resolved dispute, depending on the share of the held funds the consumer gets.
+#[derive(Copy, Clone, Encode, Decode, Eq, PartialEq, MaxEncodedLen, TypeInfo, Debug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum DisputeOutcome {
+    /// The consumer gets all the held funds
+    ConsumerFavoured,
+    /// The IP gets all the held funds
+    IPFavoured,
+    /// The held funds are split between the consumer and the IP
+    Split,
+}
+
+impl From<Perbill> for DisputeOutcome {
+    fn from(consumer_share: Perbill) -> Self {
+        if consumer_share.is_one() {
+            DisputeOutcome::ConsumerFavoured
+        } else if consumer_share.is_zero() {
+            DisputeOutcome::IPFavoured
+        } else {
+            DisputeOutcome::Split
+        }
+    }
+}
+
+/// The outcomes of the disputes a party has been involved in:
+/// - `won` - the number of disputes resolved in favour of the party
+/// - `lost` - the number of disputes resolved in favour of the other party
+/// - `split` - the number of disputes where the held funds were split
+#[derive(Clone, Encode, Decode, Eq, PartialEq, Debug, Default, MaxEncodedLen, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct DisputeRecord {
+    /// Number of disputes won
+    pub won: u32,
+    /// Number of disputes lost
+    pub lost: u32,
+    /// Number of disputes with split funds
+    pub split: u32,
+}
+
+impl DisputeRecord {
+    /// Records the outcome of a dispute from the point of view of the given party.
+    pub fn add_outcome(&mut self, outcome: DisputeOutcome, party: AgreementParty) {
+        let counter = match (outcome, party) {
+            (DisputeOutcome::Split, _) => &mut self.split,
+            (DisputeOutcome::ConsumerFavoured, AgreementParty::Consumer)
+            | (DisputeOutcome::IPFavoured, AgreementParty::IP) => &mut self.won,
+            _ => &mut self.lost,
+        };
+        counter.saturating_inc();
     }
 }
 
+/// The reliability of an IP, recorded automatically from the outcomes of its agreements and the
+/// checks of the files it stores:
+/// - `integrity_checks_passed` - the number of data integrity checks passed
+/// - `integrity_checks_failed` - the number of data integrity checks failed
+/// - `file_conflicts` - the number of files whose validation ended in a conflict
+/// - `completed_agreements` - the number of agreements completed
+/// - `nonpay_terminations` - the number of agreements terminated due to non-payment
+/// - `sla_terminations` - the number of agreements terminated due to SLA breaches
+#[derive(Clone, Encode, Decode, Eq, PartialEq, Debug, Default, MaxEncodedLen, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct ReliabilityRecord {
+    /// Number of data integrity checks passed
+    pub integrity_checks_passed: u32,
+    /// Number of data integrity checks failed
+    pub integrity_checks_failed: u32,
+    /// Number of file conflicts
+    pub file_conflicts: u32,
+    /// Number of agreements completed
+    pub completed_agreements: u32,
+    /// Number of agreements terminated due to non-payment
+    pub nonpay_terminations: u32,
+    /// Number of agreements terminated due to SLA breaches
+    pub sla_terminations: u32,
+}
+
+impl ReliabilityRecord {
+    /// Returns the share of the data integrity checks passed, or `None` if no checks have been
+    /// made yet.
+    pub fn integrity_pass_rate(&self) -> Option<Perbill> {
+        let checks = self
+            .integrity_checks_passed
+            .saturating_add(self.integrity_checks_failed);
+        (checks > 0).then(|| Perbill::from_rational(self.integrity_checks_passed, checks))
+    }
+
+    /// Returns the share of the ended agreements that were completed rather than terminated, or
+    /// `None` if no agreements have ended yet.
+    pub fn completion_rate(&self) -> Option<Perbill> {
+        let ended = self
+            .completed_agreements
+            .saturating_add(self.nonpay_terminations)
+            .saturating_add(self.sla_terminations);
+        (ended > 0).then(|| Perbill::from_rational(self.completed_agreements, ended))
+    }
+
+    /// Checks if the record meets the filter. IPs without checks or ended agreements meet any
+    /// minimum rate.
+    pub fn meets(&self, filter: &ReliabilityFilter) -> bool {
+        let meets_rate = |min_rate: Option<Perbill>, rate: Option<Perbill>| match (min_rate, rate) {
+            (Some(min_rate), Some(rate)) => rate >= min_rate,
+            _ => true,
+        };
+
+        meets_rate(filter.min_integrity_pass_rate, self.integrity_pass_rate())
+            && meets_rate(filter.min_completion_rate, self.completion_rate())
+            && filter
+                .max_file_conflicts
+                .map_or(true, |max| self.file_conflicts <= max)
+            && filter
+                .max_sla_terminations
+                .map_or(true, |max| self.sla_terminations <= max)
+    }
+}
+
+/// The filter consumers can use to find reliable IPs. Every criterion is optional:
+/// - `min_integrity_pass_rate` - the minimum share of data integrity checks passed
+/// - `min_completion_rate` - the minimum share of ended agreements that were completed
+/// - `max_file_conflicts` - the maximum number of file conflicts
+/// - `max_sla_terminations` - the maximum number of agreements terminated due to SLA breaches
+#[derive(Clone, Encode, Decode, Eq, PartialEq, Debug, Default, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct ReliabilityFilter {
+    /// Minimum share of data integrity checks passed
+    pub min_integrity_pass_rate: Option<Perbill>,
+    /// Minimum share of ended agreements that were completed
+    pub min_completion_rate: Option<Perbill>,
+    /// Maximum number of file conflicts
+    pub max_file_conflicts: Option<u32>,
+    /// Maximum number of agreements terminated due to SLA breaches
+    pub max_sla_terminations: Option<u32>,
+}
+
 /// The current prices set by the network operator. The prices are:
 /// - `storage_mb_per_block` - the rental cost of 1 MB of storage per block
+/// - `vcore_per_block` - the rental cost of 1 vCore per block
+/// - `ram_mb_per_block` - the rental cost of 1 MB of RAM per block
 #[derive(Clone, Encode, Decode, Eq, PartialEq, Debug, MaxEncodedLen, TypeInfo)]
 #[scale_info(skip_type_params(T))]
 #[codec(mel_bound(T: pallet::Config))]
 pub struct Prices<T: pallet::Config> {
     /// Storage cost of 1 MB per block
     pub storage_mb_per_block: BalanceOf<T>,
+    /// Compute cost of 1 vCore per block
+    pub vcore_per_block: BalanceOf<T>,
+    /// Compute cost of 1 MB of RAM per block
+    pub ram_mb_per_block: BalanceOf<T>,
+}
+
+impl<T: pallet::Config> Prices<T> {
+    /// Calculate the cost of renting the given amount of storage for the given number of blocks.
+    pub fn storage_cost(&self, storage: StorageSizeMB, blocks: BlockNumberFor<T>) -> BalanceOf<T> {
+        Self::resource_cost(self.storage_mb_per_block, storage as u128, blocks)
+    }
+
+    /// Calculate the cost of renting the given compute resources for the given number of blocks.
+    pub fn compute_cost(
+        &self,
+        compute: ComputeResources,
+        blocks: BlockNumberFor<T>,
+    ) -> BalanceOf<T> {
+        Self::resource_cost(self.vcore_per_block, compute.vcores as u128, blocks).saturating_add(
+            Self::resource_cost(self.ram_mb_per_block, compute.ram as u128, blocks),
+        )
+    }
+
+    /// Calculate the cost of renting the given storage and compute resources for the given number
+    /// of blocks.
+    pub fn cost(
+        &self,
+        storage: StorageSizeMB,
+        compute: ComputeResources,
+        blocks: BlockNumberFor<T>,
+    ) -> BalanceOf<T> {
+        self.storage_cost(storage, blocks)
+            .saturating_add(self.compute_cost(compute, blocks))
+    }
+
+    fn resource_cost(
+        price_per_block: BalanceOf<T>,
+        amount: u128,
+        blocks: BlockNumberFor<T>,
+    ) -> BalanceOf<T> {
+        price_per_block.saturating_mul(BalanceOf::<T>::saturated_from(
+            blocks.saturated_into::<u128>().saturating_mul(amount),
+        ))
+    }
 }
 
 impl<T: pallet::Config> Default for Prices<T> {
     fn default() -> Self {
         Self {
             storage_mb_per_block: Default::default(),
+            vcore_per_block: Default::default(),
+            ram_mb_per_block: Default::default(),
         }
     }
 }
+
+/// The terms of an offer published by an IP:
+/// - `prices` - the prices the IP charges for agreements requested against the offer
+/// - `min_storage` - the minimum amount of storage a consumer can request
+/// - `max_storage` - the maximum amount of storage a consumer can request
+/// - `min_duration` - the minimum number of blocks an agreement must last
+/// - `max_installment_period` - the maximum length of a single installment in blocks
+/// - `auto_accept` - whether the agreements requested against the offer are accepted automatically
+#[derive(Clone, Encode, Decode, Eq, PartialEq, Debug, MaxEncodedLen, TypeInfo)]
+#[scale_info(skip_type_params(T))]
+#[codec(mel_bound(T: pallet::Config))]
+pub struct OfferTerms<T: pallet::Config> {
+    /// Prices of the offer
+    pub prices: Prices<T>,
+    /// Minimum amount of storage
+    pub min_storage: StorageSizeMB,
+    /// Maximum amount of storage
+    pub max_storage: StorageSizeMB,
+    /// Minimum duration of an agreement
+    pub min_duration: BlockNumberFor<T>,
+    /// Maximum length of an installment
+    pub max_installment_period: BlockNumberFor<T>,
+    /// Flag indicating if the agreements are accepted without the IP involvement
+    pub auto_accept: bool,
+}
+
+impl<T: pallet::Config> OfferTerms<T> {
+    /// Checks that the terms are consistent. The storage range must not be empty and installments
+    /// must be allowed to last at least one block.
+    pub fn is_valid(&self) -> bool {
+        self.min_storage > 0
+            && self.min_storage <= self.max_storage
+            && self.max_installment_period > Zero::zero()
+    }
+
+    /// Checks that an agreement request satisfies the terms. The payment plan is expected to be
+    /// valid already.
+    pub fn allows(
+        &self,
+        storage: StorageSizeMB,
+        activation_block: BlockNumberFor<T>,
+        payment_plan: &PaymentPlan<T>,
+    ) -> bool {
+        let Some(&end_block) = payment_plan.last() else {
+            return false;
+        };
+
+        let mut installment_start = activation_block;
+        let installments_fit = payment_plan.iter().all(|&installment_end| {
+            let installment_length = installment_end.saturating_sub(installment_start);
+            installment_start = installment_end;
+            installment_length <= self.max_installment_period
+        });
+
+        storage >= self.min_storage
+            && storage <= self.max_storage
+            && end_block.saturating_sub(activation_block) >= self.min_duration
+            && installments_fit
+    }
+}
+
+/// The details of an offer:
+/// - `ip` - the IP publishing the offer
+/// - `terms` - the terms of the offer
+#[derive(Clone, Encode, Decode, Eq, PartialEq, Debug, MaxEncodedLen, TypeInfo)]
+#[scale_info(skip_type_params(T))]
+#[codec(mel_bound(T: pallet::Config))]
+pub struct OfferDetails<T: pallet::Config> {
+    /// IP publishing the offer
+    pub ip: AccountIdOf<T>,
+    /// Terms of the offer
+    pub terms: OfferTerms<T>,
+}
+
+/// An open storage request posted by a consumer without picking an IP. The active IPs bid for
+/// the request until the end of the bidding window. The request has:
+/// - `consumer` - the consumer posting the request
+/// - `storage` - the amount of storage requested
+/// - `compute` - the compute resources requested
+/// - `activation_block` - the block the agreement starts at
+/// - `payment_plan` - the payment plan of the agreement
+/// - `max_price` - the maximum total cost of the agreement the consumer accepts
+/// - `min_ip_reputation` - the minimum reputation score the bidding IPs must have
+/// - `sla` - the SLA terms of the agreement, if any
+/// - `bidding_end` - the last block the IPs can bid at
+/// - `deposit` - the service deposit held from the consumer while the request is open
+/// - `bids` - the number of bids placed
+#[derive(Clone, Encode, Decode, Eq, PartialEq, Debug, MaxEncodedLen, TypeInfo)]
+#[scale_info(skip_type_params(T))]
+#[codec(mel_bound(T: pallet::Config))]
+pub struct OpenRequest<T: pallet::Config> {
+    /// Consumer posting the request
+    pub consumer: AccountIdOf<T>,
+    /// Requested storage
+    pub storage: StorageSizeMB,
+    /// Requested compute resources
+    pub compute: ComputeResources,
+    /// Activation block of the agreement
+    pub activation_block: BlockNumberFor<T>,
+    /// Payment plan of the agreement
+    pub payment_plan: PaymentPlan<T>,
+    /// Maximum total cost of the agreement
+    pub max_price: BalanceOf<T>,
+    /// Minimum reputation score of the bidding IPs, multiplied by `AverageRating::SCALE`
+    pub min_ip_reputation: Option<u32>,
+    /// SLA terms of the agreement
+    pub sla: Option<SlaTerms>,
+    /// Last block of the bidding window
+    pub bidding_end: BlockNumberFor<T>,
+    /// Consumer service deposit held while the request is open
+    pub deposit: BalanceOf<T>,
+    /// Number of bids placed
+    pub bids: u32,
+}
+
+impl<T: pallet::Config> OpenRequest<T> {
+    /// Calculate the total cost of the requested agreement at the given prices.
+    pub fn cost(&self, prices: &Prices<T>) -> BalanceOf<T> {
+        let end_block = self
+            .payment_plan
+            .last()
+            .copied()
+            .unwrap_or(self.activation_block);
+        prices.cost(
+            self.storage,
+            self.compute,
+            end_block.saturating_sub(self.activation_block),
+        )
+    }
+}
+
+/// A bid of an IP for an open request. The bid has:
+/// - `prices` - the prices the IP charges for the requested agreement
+/// - `cost` - the total cost of the requested agreement at the bid prices
+/// - `bond` - the bond held from the IP until the request is closed
+/// - `placed_at` - the block the bid was placed at
+#[derive(Clone, Encode, Decode, Eq, PartialEq, Debug, MaxEncodedLen, TypeInfo)]
+#[scale_info(skip_type_params(T))]
+#[codec(mel_bound(T: pallet::Config))]
+pub struct Bid<T: pallet::Config> {
+    /// Prices of the bid
+    pub prices: Prices<T>,
+    /// Total cost of the agreement at the bid prices
+    pub cost: BalanceOf<T>,
+    /// Bond held from the IP
+    pub bond: BalanceOf<T>,
+    /// Block the bid was placed at
+    pub placed_at: BlockNumberFor<T>,
+}
+
+/// The summary of an IP returned by the runtime API.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, Debug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct IPInfo<AccountId> {
+    /// The IP account
+    pub ip: AccountId,
+    /// Current status of the IP
+    pub status: IPStatus,
+    /// Total IP storage
+    pub total_storage: StorageSizeMB,
+    /// Storage not allocated to any agreement
+    pub free_storage: StorageSizeMB,
+    /// Total IP compute resources
+    pub total_compute: ComputeResources,
+    /// Compute resources not allocated to any agreement
+    pub free_compute: ComputeResources,
+    /// Average rating of the IP, if it has been rated
+    pub average_rating: Option<AverageRating>,
+    /// Average rating of the IP weighted by the value and the age of the agreements, if any
+    /// weight is left
+    pub weighted_rating: Option<AverageRating>,
+    /// Reputation score used to rank the IPs, multiplied by `AverageRating::SCALE`
+    pub reputation: u32,
+    /// Outcomes of the disputes the IP has been involved in
+    pub disputes: DisputeRecord,
+    /// Record of the integrity checks, file conflicts and agreement outcomes of the IP
+    pub reliability: ReliabilityRecord,
+    /// Minimum average consumer rating required by the IP, if any
+    pub min_consumer_rating: Option<u32>,
+    /// Completed agreements archived without feedback from the consumer
+    pub unrated_agreements: u32,
+}
+
+/// An installment of an agreement returned by the runtime API.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, Debug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct InstallmentInfo<Balance, BlockNumber> {
+    /// Index of the installment in the payment plan
+    pub index: u32,
+    /// The block the installment starts at. It must be prepaid before this block.
+    pub due_block: BlockNumber,
+    /// The block the installment ends at
+    pub end_block: BlockNumber,
+    /// The cost of the installment
+    pub cost: Balance,
+}
+
+/// The details of an agreement returned by the runtime API.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, Debug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct AgreementInfo<AccountId, Balance, BlockNumber> {
+    /// IP participating in the agreement
+    pub ip: AccountId,
+    /// Consumer participating in the agreement
+    pub consumer: AccountId,
+    /// Current status of the agreement
+    pub status: AgreementStatus,
+    /// The amount of storage covered by the agreement
+    pub storage: StorageSizeMB,
+    /// The compute resources covered by the agreement
+    pub compute: ComputeResources,
+    /// Storage cost of 1 MB per block locked in the agreement
+    pub storage_mb_per_block: Balance,
+    /// Compute cost of 1 vCore per block locked in the agreement
+    pub vcore_per_block: Balance,
+    /// Compute cost of 1 MB of RAM per block locked in the agreement
+    pub ram_mb_per_block: Balance,
+    /// The block number when the rental starts
+    pub activation_block: BlockNumber,
+    /// The payment plan for the agreement
+    pub payment_plan: Vec<BlockNumber>,
+    /// Service deposit amount held from the consumer
+    pub consumer_service_deposit: Balance,
+    /// Security deposit amount held from the consumer
+    pub consumer_security_deposit: Balance,
+    /// Flag indicating if the consumer security deposit is transferred to the IP
+    pub consumer_security_deposit_transferred: bool,
+    /// The amounts of the installments prepaid by the consumer
+    pub paid_installments: Vec<Balance>,
+    /// The number of installments transferred to the IP
+    pub transferred_installments: u32,
+    /// The next installment the consumer has to prepay
+    pub next_installment: Option<InstallmentInfo<Balance, BlockNumber>>,
+    /// Flag indicating if the agreement has overdue installments
+    pub overdue: bool,
+    /// Flag indicating if the installments are prepaid automatically
+    pub auto_pay: bool,
+    /// Flag indicating if the payment plan is extended automatically when it ends
+    pub auto_renew: bool,
+    /// SLA terms of the agreement, if any
+    pub sla: Option<SlaTerms>,
+    /// Total number of breaches of the SLA terms
+    pub sla_breaches: u32,
+}
+
+/// A review of an IP returned by the runtime API.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, Debug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct ReviewInfo<AccountId, BlockNumber, AgreementId> {
+    /// Index of the review in the review log of the IP
+    pub index: u32,
+    /// The reviewed agreement
+    pub agreement_id: AgreementId,
+    /// The consumer that submitted the feedback
+    pub consumer: AccountId,
+    /// The performance score
+    pub score_performance: Score,
+    /// The stability score
+    pub score_stability: Score,
+    /// The support score
+    pub score_support: Score,
+    /// The comment of the feedback
+    pub comment: Vec<u8>,
+    /// The block the feedback was submitted at
+    pub block_number: BlockNumber,
+}
+
+/// The cost of a proposed agreement returned by the runtime API.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, Debug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct AgreementQuote<Balance> {
+    /// The total cost of all the installments
+    pub total_cost: Balance,
+    /// The security deposit held when the agreement is requested
+    pub security_deposit: Balance,
+    /// The service deposit held when the agreement is requested
+    pub service_deposit: Balance,
+    /// The cost of every installment in the payment plan
+    pub installments: Vec<Balance>,
+}
+
+/// Interface used by other pallets to penalize IPs, e.g. for failed data integrity checks.
+pub trait IPPenaltyHandler<AccountId, Balance> {
+    /// Slashes the given share of the IP deposit. If an affected consumer is given, it receives
+    /// its share of the slashed amount and the rest goes to the treasury.
+    ///
+    /// Returns the amount slashed.
+    fn slash_ip(
+        ip: &AccountId,
+        share: Perbill,
+        consumer: Option<&AccountId>,
+    ) -> Result<Balance, DispatchError>;
+}
@@ -14,13 +14,31 @@
 // You should have received a copy of the GNU General Public License
 // along with Deitos Node.  If not, see <http://www.gnu.org/licenses/>.
 
+use frame_system::EnsureRoot;
+use sp_runtime::traits::AccountIdConversion;
+
 use super::*;
 
 pub type AgreementId = u32;
+pub type OfferId = u32;
 pub type FileId = u32;
 
 parameter_types! {
     pub const DeitosPalletId: PalletId = PalletId(*b"DeitosId");
+    pub const AutoPayLeadBlocks: BlockNumber = HOURS;
+    pub const ConsumerTerminationPenalty: Perbill = Perbill::from_percent(10);
+    pub const IPCancellationPenalty: Perbill = Perbill::from_percent(10);
+    pub const PaymentGracePeriod: BlockNumber = DAYS;
+    pub const LateFee: Perbill = Perbill::from_percent(5);
+    pub const FeedbackPeriod: BlockNumber = 7 * DAYS;
+    pub const ReputationDecayPeriod: BlockNumber = 30 * DAYS;
+    pub const ReputationRetainedWeight: Perbill = Perbill::from_percent(90);
+    pub const ReputationPriorWeight: Balance = 1000 * EXISTENTIAL_DEPOSIT;
+    pub const ExpiredAgreementCleanupReward: Balance = 10 * EXISTENTIAL_DEPOSIT;
+    pub const SlashedDepositConsumerShare: Perbill = Perbill::from_percent(50);
+    pub const BiddingPeriod: BlockNumber = HOURS;
+    pub const BidBond: Balance = 100 * EXISTENTIAL_DEPOSIT;
+    pub TreasuryAccount: AccountId = PalletId(*b"py/trsry").into_account_truncating();
 }
 
 impl pallet_deitos::Config for Runtime {
@@ -29,9 +47,29 @@ impl pallet_deitos::Config for Runtime {
     type Currency = Balances;
     type RuntimeHoldReason = RuntimeHoldReason;
     type AgreementId = AgreementId;
+    type OfferId = OfferId;
     type PaymentPlanLimit = ConstU32<500>;
-    type IPAgreementsLimit = ConstU32<500>;
-    type ConsumerAgreementsLimit = ConstU32<500>;
+    type IPOffersLimit = ConstU32<100>;
+    type MaxEvidenceLength = ConstU32<128>;
+    type MaxCommentLength = ConstU32<256>;
+    type MaxSettlementsPerBlock = ConstU32<100>;
+    type AutoPayLeadBlocks = AutoPayLeadBlocks;
+    type ConsumerTerminationPenalty = ConsumerTerminationPenalty;
+    type IPCancellationPenalty = IPCancellationPenalty;
+    type PaymentGracePeriod = PaymentGracePeriod;
+    type LateFee = LateFee;
+    type FeedbackPeriod = FeedbackPeriod;
+    type ReputationDecayPeriod = ReputationDecayPeriod;
+    type ReputationRetainedWeight = ReputationRetainedWeight;
+    type ReputationPriorWeight = ReputationPriorWeight;
+    type ExpiredAgreementCleanupReward = ExpiredAgreementCleanupReward;
+    type SlashedDepositConsumerShare = SlashedDepositConsumerShare;
+    type BiddingPeriod = BiddingPeriod;
+    type BidBond = BidBond;
+    type MaxBidsPerRequest = ConstU32<20>;
+    type TreasuryAccount = TreasuryAccount;
+    type DisputeOrigin = EnsureRoot<AccountId>;
+    type SlashOrigin = EnsureRoot<AccountId>;
     type PalletId = DeitosPalletId;
 }
 
@@ -63,6 +63,7 @@ pub use sp_runtime::BuildStorage;
 pub use sp_runtime::{Perbill, Permill};
 
 mod deitos;
+pub use deitos::{AgreementId, FileId};
 
 mod constants;
 use crate::constants::time::*;
@@ -125,7 +126,7 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
     //   `spec_version`, and `authoring_version` are the same between Wasm and native.
     // This value is set to 100 to notify Polkadot-JS App (https://polkadot.js.org/apps) to use
     //   the compatible custom types.
-    spec_version: 100,
+    spec_version: 101,
     impl_version: 1,
     apis: RUNTIME_API_VERSIONS,
     transaction_version: 1,
@@ -164,13 +165,22 @@ parameter_types! {
     pub const SS58Prefix: u8 = 42;
 }
 
+/// Filters out the Deitos calls while the Deitos storage is being migrated over several blocks.
+pub struct BaseCallFilter;
+impl frame_support::traits::Contains<RuntimeCall> for BaseCallFilter {
+    fn contains(call: &RuntimeCall) -> bool {
+        !matches!(call, RuntimeCall::Deitos(_) | RuntimeCall::DeitosFS(_))
+            || !Deitos::is_migrating()
+    }
+}
+
 // Configure FRAME pallets to include in runtime.
 
 impl frame_system::Config for Runtime {
     /// The ubiquitous event type.
     type RuntimeEvent = RuntimeEvent;
     /// The basic call filter to use in dispatchable.
-    type BaseCallFilter = frame_support::traits::Everything;
+    type BaseCallFilter = BaseCallFilter;
     /// Block & extrinsics weights: base values and limits.
     type BlockWeights = BlockWeights;
     /// The maximum length of a block (in bytes).
@@ -343,7 +353,7 @@ pub type SignedExtra = (
 ///
 /// This can be a tuple of types, each implementing `OnRuntimeUpgrade`.
 #[allow(unused_parens)]
-type Migrations = ();
+type Migrations = (pallet_deitos::migrations::v1::MigrateToV1<Runtime>,);
 
 /// Unchecked extrinsic type as expected by this runtime.
 pub type UncheckedExtrinsic =
@@ -580,6 +590,67 @@ impl_runtime_apis! {
         }
     }
 
+    impl pallet_deitos_runtime_api::DeitosApi<Block, AccountId, Balance, BlockNumber, AgreementId>
+        for Runtime
+    {
+        fn ips() -> Vec<pallet_deitos::IPInfo<AccountId>> {
+            Deitos::ips_info()
+        }
+
+        fn reliable_ips(filter: pallet_deitos::ReliabilityFilter) -> Vec<pallet_deitos::IPInfo<AccountId>> {
+            Deitos::reliable_ips_info(&filter)
+        }
+
+        fn ip_reviews(
+            ip: AccountId,
+            start: u32,
+            limit: u32,
+        ) -> Vec<pallet_deitos::ReviewInfo<AccountId, BlockNumber, AgreementId>> {
+            Deitos::ip_reviews(&ip, start, limit)
+        }
+
+        fn ip_free_storage(ip: AccountId) -> Option<pallet_deitos::StorageSizeMB> {
+            Deitos::ip_free_storage(&ip)
+        }
+
+        fn ip_agreements(ip: AccountId, start_after: Option<AgreementId>, limit: u32) -> Vec<AgreementId> {
+            Deitos::ip_agreement_ids(&ip, start_after, limit)
+        }
+
+        fn consumer_agreements(
+            consumer: AccountId,
+            start_after: Option<AgreementId>,
+            limit: u32,
+        ) -> Vec<AgreementId> {
+            Deitos::consumer_agreement_ids(&consumer, start_after, limit)
+        }
+
+        fn agreement(
+            agreement_id: AgreementId,
+        ) -> Option<pallet_deitos::AgreementInfo<AccountId, Balance, BlockNumber>> {
+            Deitos::agreement_info(agreement_id)
+        }
+
+        fn quote_agreement(
+            storage: pallet_deitos::StorageSizeMB,
+            compute: pallet_deitos::ComputeResources,
+            activation_block: BlockNumber,
+            payment_plan: Vec<BlockNumber>,
+        ) -> Option<pallet_deitos::AgreementQuote<Balance>> {
+            Deitos::quote_agreement(storage, compute, activation_block, payment_plan)
+        }
+    }
+
+    impl pallet_deitos_fs_runtime_api::DeitosFsApi<Block, AgreementId, FileId> for Runtime {
+        fn file(file_id: FileId) -> Option<pallet_deitos_fs::FileInfo<AgreementId>> {
+            DeitosFS::file_info(file_id)
+        }
+
+        fn agreement_files(agreement_id: AgreementId) -> Vec<FileId> {
+            DeitosFS::agreement_file_ids(agreement_id)
+        }
+    }
+
     #[cfg(feature = "runtime-benchmarks")]
     impl frame_benchmarking::Benchmark<Block> for Runtime {
         fn benchmark_metadata(extra: bool) -> (